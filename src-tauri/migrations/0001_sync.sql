@@ -0,0 +1,125 @@
+-- Stable identifiers for texts so devices can refer to the same text
+ALTER TABLE texts ADD COLUMN uid TEXT;
+UPDATE texts SET uid = lower(hex(randomblob(16))) WHERE uid IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_texts_uid ON texts(uid);
+
+CREATE TRIGGER IF NOT EXISTS texts_assign_uid AFTER INSERT ON texts
+WHEN NEW.uid IS NULL
+BEGIN
+    UPDATE texts SET uid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+END;
+
+-- Key/value state of the sync client (device_id, server_url, pull_cursor)
+CREATE TABLE IF NOT EXISTS sync_state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+-- Local changes waiting to be pushed to the sync server
+CREATE TABLE IF NOT EXISTS sync_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entity TEXT NOT NULL,
+    entity_key TEXT NOT NULL,
+    op TEXT NOT NULL, -- 'upsert' or 'delete'
+    payload TEXT, -- JSON row for upserts
+    changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- Changes are only recorded once a sync server is configured, and never
+-- while changes pulled from the server are being applied.
+
+CREATE TRIGGER IF NOT EXISTS words_sync_insert AFTER INSERT ON words
+WHEN EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('words', NEW.word, 'upsert', json_object(
+        'word', NEW.word, 'status', NEW.status, 'meaning', NEW.meaning,
+        'stability', NEW.stability, 'difficulty', NEW.difficulty,
+        'last_review_date', NEW.last_review_date, 'next_review_date', NEW.next_review_date));
+END;
+
+CREATE TRIGGER IF NOT EXISTS words_sync_update AFTER UPDATE ON words
+WHEN EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('words', NEW.word, 'upsert', json_object(
+        'word', NEW.word, 'status', NEW.status, 'meaning', NEW.meaning,
+        'stability', NEW.stability, 'difficulty', NEW.difficulty,
+        'last_review_date', NEW.last_review_date, 'next_review_date', NEW.next_review_date));
+END;
+
+CREATE TRIGGER IF NOT EXISTS words_sync_delete AFTER DELETE ON words
+WHEN EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op) VALUES ('words', OLD.word, 'delete');
+END;
+
+-- Texts are recorded once they have a uid (the uid assignment is an UPDATE)
+CREATE TRIGGER IF NOT EXISTS texts_sync_insert AFTER INSERT ON texts
+WHEN NEW.uid IS NOT NULL
+    AND EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('texts', NEW.uid, 'upsert', json_object(
+        'uid', NEW.uid, 'title', NEW.title, 'content', NEW.content));
+END;
+
+CREATE TRIGGER IF NOT EXISTS texts_sync_update AFTER UPDATE ON texts
+WHEN NEW.uid IS NOT NULL
+    AND EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('texts', NEW.uid, 'upsert', json_object(
+        'uid', NEW.uid, 'title', NEW.title, 'content', NEW.content));
+END;
+
+CREATE TRIGGER IF NOT EXISTS texts_sync_delete AFTER DELETE ON texts
+WHEN OLD.uid IS NOT NULL
+    AND EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op) VALUES ('texts', OLD.uid, 'delete');
+END;
+
+-- Links are keyed by "<text uid>:<word>"
+CREATE TRIGGER IF NOT EXISTS text_words_sync_insert AFTER INSERT ON text_words
+WHEN EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    SELECT 'text_words', t.uid || ':' || w.word, 'upsert', json_object('text_uid', t.uid, 'word', w.word)
+    FROM texts t, words w
+    WHERE t.id = NEW.text_id AND w.id = NEW.word_id AND t.uid IS NOT NULL;
+END;
+
+CREATE TRIGGER IF NOT EXISTS text_words_sync_delete AFTER DELETE ON text_words
+WHEN EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    SELECT 'text_words', t.uid || ':' || w.word, 'delete', json_object('text_uid', t.uid, 'word', w.word)
+    FROM texts t, words w
+    WHERE t.id = OLD.text_id AND w.id = OLD.word_id AND t.uid IS NOT NULL;
+END;
+
+-- There is only ever one preference row, keyed by the setting name
+CREATE TRIGGER IF NOT EXISTS user_preferences_sync_insert AFTER INSERT ON user_preferences
+WHEN EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('user_preferences', 'country_code', 'upsert', json_object('country_code', NEW.country_code));
+END;
+
+CREATE TRIGGER IF NOT EXISTS user_preferences_sync_update AFTER UPDATE ON user_preferences
+WHEN EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('user_preferences', 'country_code', 'upsert', json_object('country_code', NEW.country_code));
+END;
@@ -0,0 +1,5 @@
+-- Device-local application settings (not part of sync or the sync key)
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
@@ -0,0 +1,46 @@
+-- Library organization: collections with ordered texts, free-form tags and
+-- the dates the text list can be sorted by. Collections and tags are part of
+-- the sync key but not of server sync.
+CREATE TABLE IF NOT EXISTS collections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    description TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+ALTER TABLE texts ADD COLUMN collection_id INTEGER REFERENCES collections(id) ON DELETE SET NULL;
+ALTER TABLE texts ADD COLUMN position INTEGER; -- Order inside the collection
+ALTER TABLE texts ADD COLUMN created_at TEXT;
+ALTER TABLE texts ADD COLUMN last_opened_at TEXT;
+CREATE INDEX IF NOT EXISTS idx_texts_collection ON texts(collection_id, position);
+
+UPDATE texts SET created_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE created_at IS NULL;
+
+CREATE TRIGGER IF NOT EXISTS texts_assign_created_at AFTER INSERT ON texts
+WHEN NEW.created_at IS NULL
+BEGIN
+    UPDATE texts SET created_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+END;
+
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE IF NOT EXISTS text_tags (
+    text_id INTEGER NOT NULL REFERENCES texts(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (text_id, tag_id)
+);
+
+-- Opening or moving a text must not queue its whole content for sync
+DROP TRIGGER IF EXISTS texts_sync_update;
+CREATE TRIGGER texts_sync_update AFTER UPDATE OF uid, title, content ON texts
+WHEN NEW.uid IS NOT NULL
+    AND EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('texts', NEW.uid, 'upsert', json_object(
+        'uid', NEW.uid, 'title', NEW.title, 'content', NEW.content));
+END;
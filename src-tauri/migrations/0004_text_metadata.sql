@@ -0,0 +1,15 @@
+-- Where a text comes from and what the learner noted about it. `level` is a
+-- CEFR level (A1 to C2) picked by the learner, not detected.
+ALTER TABLE texts ADD COLUMN source TEXT; -- URL or file name
+ALTER TABLE texts ADD COLUMN author TEXT;
+ALTER TABLE texts ADD COLUMN level TEXT;
+ALTER TABLE texts ADD COLUMN notes TEXT;
+ALTER TABLE texts ADD COLUMN updated_at TEXT;
+
+UPDATE texts SET updated_at = created_at WHERE updated_at IS NULL;
+
+CREATE TRIGGER IF NOT EXISTS texts_assign_updated_at AFTER INSERT ON texts
+WHEN NEW.updated_at IS NULL
+BEGIN
+    UPDATE texts SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', 'now') WHERE id = NEW.id;
+END;
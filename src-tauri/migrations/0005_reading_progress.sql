@@ -0,0 +1,19 @@
+-- Where the learner stopped in each text. Offsets count the words of the
+-- content from the start (0 is the first word), as split by
+-- repo::texts::tokens for get_text_with_words.
+CREATE TABLE IF NOT EXISTS reading_progress (
+    text_id INTEGER PRIMARY KEY REFERENCES texts(id) ON DELETE CASCADE,
+    token_offset INTEGER NOT NULL DEFAULT 0,
+    completed INTEGER NOT NULL DEFAULT 0,
+    completed_at TEXT,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS bookmarks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    text_id INTEGER NOT NULL REFERENCES texts(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_offset INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_bookmarks_text ON bookmarks(text_id, token_offset);
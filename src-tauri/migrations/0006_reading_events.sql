@@ -0,0 +1,21 @@
+-- Bulk status changes made while reading, kept so they can be undone.
+-- `page` is NULL when the whole text was finished at once.
+CREATE TABLE IF NOT EXISTS reading_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    text_id INTEGER NOT NULL REFERENCES texts(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    page INTEGER,
+    words_per_page INTEGER,
+    created_at TEXT NOT NULL,
+    undone_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_reading_events_text ON reading_events(text_id, created_at);
+
+-- The words an event changed and what they were before
+CREATE TABLE IF NOT EXISTS reading_event_words (
+    event_id INTEGER NOT NULL REFERENCES reading_events(id) ON DELETE CASCADE,
+    word_id INTEGER NOT NULL REFERENCES words(id) ON DELETE CASCADE,
+    previous_status TEXT,
+    previous_next_review_date TEXT,
+    PRIMARY KEY (event_id, word_id)
+);
@@ -0,0 +1,10 @@
+-- Moments in the original media (subtitles) for each sentence of a text.
+-- Sentences are word ranges of the content, like reading positions.
+CREATE TABLE IF NOT EXISTS text_timestamps (
+    text_id INTEGER NOT NULL REFERENCES texts(id) ON DELETE CASCADE,
+    token_offset INTEGER NOT NULL,
+    token_count INTEGER NOT NULL,
+    start_ms INTEGER NOT NULL,
+    end_ms INTEGER NOT NULL,
+    PRIMARY KEY (text_id, token_offset)
+);
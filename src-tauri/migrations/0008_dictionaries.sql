@@ -0,0 +1,23 @@
+-- Offline dictionaries imported from files (StarDict, TSV, Wiktionary).
+-- They can be large and are not part of exports or sync.
+CREATE TABLE IF NOT EXISTS dictionaries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    format TEXT NOT NULL, -- stardict, tsv or wiktionary
+    language TEXT, -- Language of the headwords, when the file says
+    entry_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+-- `definitions` is a JSON array of strings, `inflections` a JSON array of
+-- {form, tags} objects
+CREATE TABLE IF NOT EXISTS dictionary_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    dictionary_id INTEGER NOT NULL REFERENCES dictionaries(id) ON DELETE CASCADE,
+    headword TEXT NOT NULL,
+    part_of_speech TEXT,
+    definitions TEXT NOT NULL,
+    inflections TEXT NOT NULL DEFAULT '[]'
+);
+CREATE INDEX IF NOT EXISTS idx_dictionary_entries_headword ON dictionary_entries(headword);
+CREATE INDEX IF NOT EXISTS idx_dictionary_entries_dictionary ON dictionary_entries(dictionary_id);
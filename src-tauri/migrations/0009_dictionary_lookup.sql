@@ -0,0 +1,19 @@
+-- Keys for looking words up in other forms than their headword: lowercase,
+-- lowercase without diacritics, and the stem in the dictionary's language.
+-- They are computed in Rust (see dictionary::lookup), entries imported
+-- before this migration get them at startup.
+ALTER TABLE dictionary_entries ADD COLUMN folded_headword TEXT;
+ALTER TABLE dictionary_entries ADD COLUMN plain_headword TEXT;
+ALTER TABLE dictionary_entries ADD COLUMN stem TEXT;
+CREATE INDEX IF NOT EXISTS idx_dictionary_entries_folded ON dictionary_entries(folded_headword);
+CREATE INDEX IF NOT EXISTS idx_dictionary_entries_plain ON dictionary_entries(plain_headword);
+CREATE INDEX IF NOT EXISTS idx_dictionary_entries_stem ON dictionary_entries(stem);
+
+-- Inflected forms of entries ("ging" for "gehen"), by their plain key
+CREATE TABLE IF NOT EXISTS dictionary_forms (
+    entry_id INTEGER NOT NULL REFERENCES dictionary_entries(id) ON DELETE CASCADE,
+    form TEXT NOT NULL,
+    plain_form TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_dictionary_forms_plain ON dictionary_forms(plain_form);
+CREATE INDEX IF NOT EXISTS idx_dictionary_forms_entry ON dictionary_forms(entry_id);
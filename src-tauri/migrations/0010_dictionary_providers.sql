@@ -0,0 +1,56 @@
+-- Websites a selected word can be looked up on. `url_template` holds the
+-- placeholders {word}, {src} and {dst}; `languages` maps the language picked
+-- in the app to the code the site uses for it as {src} (an empty object
+-- means every language, passed as is). The shipped providers are added by
+-- repo::dictionary_providers::restore_defaults.
+CREATE TABLE IF NOT EXISTS dictionary_providers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uid TEXT,
+    name TEXT NOT NULL,
+    url_template TEXT NOT NULL,
+    languages TEXT NOT NULL DEFAULT '{}',
+    target_language TEXT NOT NULL DEFAULT 'en',
+    position INTEGER NOT NULL DEFAULT 0,
+    embedded INTEGER NOT NULL DEFAULT 0, -- Shown in the side panel rather than linked
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_dictionary_providers_uid ON dictionary_providers(uid);
+
+CREATE TRIGGER IF NOT EXISTS dictionary_providers_assign_uid AFTER INSERT ON dictionary_providers
+WHEN NEW.uid IS NULL
+BEGIN
+    UPDATE dictionary_providers SET uid = lower(hex(randomblob(16))) WHERE id = NEW.id;
+END;
+
+-- Providers sync like texts, by uid once they have one
+CREATE TRIGGER IF NOT EXISTS dictionary_providers_sync_insert AFTER INSERT ON dictionary_providers
+WHEN NEW.uid IS NOT NULL
+    AND EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('dictionary_providers', NEW.uid, 'upsert', json_object(
+        'uid', NEW.uid, 'name', NEW.name, 'url_template', NEW.url_template,
+        'languages', json(NEW.languages), 'target_language', NEW.target_language,
+        'position', NEW.position, 'embedded', NEW.embedded));
+END;
+
+CREATE TRIGGER IF NOT EXISTS dictionary_providers_sync_update AFTER UPDATE ON dictionary_providers
+WHEN NEW.uid IS NOT NULL
+    AND EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op, payload)
+    VALUES ('dictionary_providers', NEW.uid, 'upsert', json_object(
+        'uid', NEW.uid, 'name', NEW.name, 'url_template', NEW.url_template,
+        'languages', json(NEW.languages), 'target_language', NEW.target_language,
+        'position', NEW.position, 'embedded', NEW.embedded));
+END;
+
+CREATE TRIGGER IF NOT EXISTS dictionary_providers_sync_delete AFTER DELETE ON dictionary_providers
+WHEN OLD.uid IS NOT NULL
+    AND EXISTS (SELECT 1 FROM sync_state WHERE key = 'server_url')
+    AND NOT EXISTS (SELECT 1 FROM sync_state WHERE key = 'applying_remote')
+BEGIN
+    INSERT INTO sync_changes (entity, entity_key, op) VALUES ('dictionary_providers', OLD.uid, 'delete');
+END;
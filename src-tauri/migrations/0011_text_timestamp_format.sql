@@ -0,0 +1,9 @@
+-- Edits used to store text timestamps with a `+00:00` offset while inserts
+-- got `Z` from the triggers, so sorting compared unlike strings. Everything
+-- is now written as UTC with milliseconds and `Z`.
+UPDATE texts SET created_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at)
+WHERE created_at IS NOT NULL AND created_at NOT LIKE '%Z';
+UPDATE texts SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', updated_at)
+WHERE updated_at IS NOT NULL AND updated_at NOT LIKE '%Z';
+UPDATE texts SET last_opened_at = strftime('%Y-%m-%dT%H:%M:%fZ', last_opened_at)
+WHERE last_opened_at IS NOT NULL AND last_opened_at NOT LIKE '%Z';
@@ -0,0 +1,60 @@
+// Reference WordEcho sync server.
+//
+// Usage: sync_server --secret SECRET [--bind 127.0.0.1:7878] [--db sync-server.sqlite]
+//
+// The secret can also be given in WORDECHO_SYNC_SECRET. Traffic is not
+// encrypted: bind to localhost or a trusted LAN only.
+
+use tokio::net::TcpListener;
+use word_echo_lib::sync::{protocol, server};
+
+#[tokio::main]
+async fn main() {
+    // Log level comes from RUST_LOG, e.g. RUST_LOG=debug
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+
+    let mut bind = format!("127.0.0.1:{}", protocol::DEFAULT_PORT);
+    let mut db_path = "sync-server.sqlite".to_string();
+    let mut secret = std::env::var("WORDECHO_SYNC_SECRET").unwrap_or_default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => bind = args.next().expect("--bind needs an address"),
+            "--db" => db_path = args.next().expect("--db needs a path"),
+            "--secret" => secret = args.next().expect("--secret needs a value"),
+            "-h" | "--help" => {
+                println!("Usage: sync_server --secret SECRET [--bind 127.0.0.1:7878] [--db sync-server.sqlite]");
+                return;
+            }
+            other => {
+                eprintln!("Unknown argument: {}", other);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if secret.is_empty() {
+        eprintln!("A secret is required: pass --secret or set WORDECHO_SYNC_SECRET");
+        std::process::exit(2);
+    }
+
+    let pool = server::open_database(&db_path)
+        .await
+        .expect("Failed to open sync server database");
+
+    let listener = TcpListener::bind(&bind)
+        .await
+        .expect("Failed to bind sync server address");
+
+    tracing::info!("WordEcho sync server listening on {} (database: {})", bind, db_path);
+
+    server::serve(listener, pool, secret)
+        .await
+        .expect("Sync server stopped");
+}
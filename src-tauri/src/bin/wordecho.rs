@@ -0,0 +1,401 @@
+// Command-line access to a WordEcho library, for scripting bulk work.
+// Opens the same database as the app (the active profile by default).
+//
+// Encrypted libraries read the passphrase from WORDECHO_PASSPHRASE.
+
+use sqlx::sqlite::SqlitePool;
+use std::path::{Path, PathBuf};
+use word_echo_lib::error::{AppError, AppResult};
+use word_echo_lib::clock::{Clock, SystemClock};
+use word_echo_lib::models::TextMetadata;
+use word_echo_lib::{bootstrap, dictionary, encryption, import, library, profiles, repo, transfer};
+
+const USAGE: &str = "Usage: wordecho [--db PATH | --profile NAME] <command>
+
+Commands:
+  texts                           List texts with their level and author
+  import-text FILE [--title T] [--author A] [--level L]
+                                  Add a .txt, .md or .html file as a text; the title
+                                  defaults to the document's or the file name
+  import-epub FILE                Add a book as a collection with one text per chapter
+  import-pdf FILE [--pages 5-20,21-40]
+                                  Add a PDF, one text per chapter or range of pages
+  import-folder DIR [--collection ID]
+                                  Add every supported file of a folder, skipping
+                                  texts already in the library
+  import-subtitles FILE [--title T]
+                                  Add the dialogue of an .srt or .vtt file as a text
+  import-dictionary FILE [--name N] [--language L]
+                                  Add a StarDict (.ifo), TSV or Wiktionary (.jsonl)
+                                  dictionary for offline lookups
+  lookup WORD                     Look a word up in the offline dictionaries
+  export FILE                     Write the whole library as JSON
+  import FILE                     Replace the library with a JSON export
+  export-words [--status S]       Print the vocabulary as tab-separated values
+  due [--limit N]                 List words due for review
+  stats                           Show review statistics
+  sync-key generate [--out FILE]  Print (or save) a sync key
+  sync-key apply KEY|--file FILE  Replace the library with a sync key
+
+Options:
+  --db PATH        Open this database instead of the app's library
+  --profile NAME   Open another profile of the app's library (id or name)";
+
+const PASSPHRASE_VAR: &str = "WORDECHO_PASSPHRASE";
+
+struct Args {
+    db: Option<PathBuf>,
+    profile: Option<String>,
+    command: Vec<String>,
+}
+
+fn parse_args() -> Args {
+    let mut parsed = Args {
+        db: None,
+        profile: None,
+        command: Vec::new(),
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--db" if parsed.command.is_empty() => {
+                parsed.db = Some(PathBuf::from(args.next().unwrap_or_else(|| usage_error("--db needs a path"))));
+            }
+            "--profile" if parsed.command.is_empty() => {
+                parsed.profile = Some(args.next().unwrap_or_else(|| usage_error("--profile needs a name")));
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            _ => parsed.command.push(arg),
+        }
+    }
+
+    parsed
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n\n{}", message, USAGE);
+    std::process::exit(2);
+}
+
+// Value of `--name value` inside the command arguments
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .map(|i| args.get(i + 1).map(String::as_str).unwrap_or_else(|| usage_error(&format!("{} needs a value", name))))
+}
+
+// The first argument that is neither an option nor an option value
+fn positional<'a>(args: &'a [String], options: &[&str]) -> Option<&'a str> {
+    let mut skip = false;
+    for arg in args {
+        if skip {
+            skip = false;
+        } else if options.contains(&arg.as_str()) {
+            skip = true;
+        } else {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+fn library_paths() -> AppResult<library::LibraryPaths> {
+    let (Some(data_dir), Some(config_dir)) = (dirs::data_dir(), dirs::config_dir()) else {
+        return Err(AppError::invalid_input("Cannot find the app directories, use --db"));
+    };
+
+    Ok(library::resolve_desktop(
+        data_dir.join(library::APP_IDENTIFIER),
+        config_dir.join(library::APP_IDENTIFIER),
+    ))
+}
+
+fn database(args: &Args) -> AppResult<PathBuf> {
+    if let Some(db) = &args.db {
+        return Ok(db.clone());
+    }
+
+    let paths = library_paths()?;
+    let profile = match &args.profile {
+        Some(name) => profiles::find(&paths.library_dir, name, SystemClock.now())?,
+        None => profiles::active(&paths.library_dir, SystemClock.now()),
+    };
+    Ok(profiles::database_path(&paths.library_dir, &profile.id))
+}
+
+async fn open(path: &Path) -> AppResult<SqlitePool> {
+    if !path.exists() {
+        return Err(AppError::not_found(format!("No library found at {}", path.display())));
+    }
+
+    let passphrase = if encryption::is_encrypted(path) {
+        let passphrase = std::env::var(PASSPHRASE_VAR).map_err(|_| {
+            AppError::invalid_input(format!("The library is encrypted, set {}", PASSPHRASE_VAR))
+        })?;
+        encryption::verify_passphrase(path, &passphrase).await?;
+        Some(passphrase)
+    } else {
+        None
+    };
+
+    let pool = bootstrap::open_database(path, passphrase.as_deref()).await?;
+    bootstrap::init_db(&pool).await?;
+    Ok(pool)
+}
+
+fn read_file(path: &str) -> AppResult<String> {
+    std::fs::read_to_string(path).map_err(|e| AppError::Io {
+        message: format!("Failed to read {}", path),
+        source: e,
+    })
+}
+
+fn write_file(path: &str, content: &str) -> AppResult<()> {
+    std::fs::write(path, content).map_err(|e| AppError::Io {
+        message: format!("Failed to write {}", path),
+        source: e,
+    })
+}
+
+// Tabs and newlines would break the columns
+fn cell(value: Option<&str>) -> String {
+    value.unwrap_or("").replace(['\t', '\n', '\r'], " ")
+}
+
+async fn run(args: Args) -> AppResult<()> {
+    let Some(command) = args.command.first() else {
+        usage_error("Missing command");
+    };
+    let rest = &args.command[1..];
+
+    let path = database(&args)?;
+    let pool = open(&path).await?;
+
+    match command.as_str() {
+        "texts" => {
+            for text in repo::texts::list(&pool).await? {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    text.id,
+                    cell(Some(&text.title)),
+                    cell(text.metadata.level.as_deref()),
+                    cell(text.metadata.author.as_deref())
+                );
+            }
+        }
+        "import-text" => {
+            let file = positional(rest, &["--title", "--author", "--level"])
+                .unwrap_or_else(|| usage_error("import-text needs a file"));
+            let metadata = TextMetadata {
+                author: option(rest, "--author").map(str::to_string),
+                level: option(rest, "--level").map(str::to_string),
+                ..Default::default()
+            };
+            let title = option(rest, "--title");
+            let report = import::document::import(&pool, Path::new(file), title, &metadata, SystemClock.now()).await?;
+            for text in report.texts {
+                println!("Added text {}: {}", text.id, text.title);
+            }
+        }
+        "import-epub" => {
+            let file = positional(rest, &[]).unwrap_or_else(|| usage_error("import-epub needs a file"));
+            let report = import::epub::import(&pool, Path::new(file), SystemClock.now()).await?;
+            if let Some(collection) = report.collection {
+                println!("Added collection {}: {}", collection.id, collection.name);
+            }
+            for text in report.texts {
+                println!("Added text {}: {}", text.id, text.title);
+            }
+        }
+        "import-pdf" => {
+            let file = positional(rest, &["--pages"]).unwrap_or_else(|| usage_error("import-pdf needs a file"));
+            let sections = option(rest, "--pages").map(|ranges| {
+                ranges
+                    .split(',')
+                    .map(|range| {
+                        let (start, end) = range.split_once('-').unwrap_or((range, range));
+                        match (start.trim().parse(), end.trim().parse()) {
+                            (Ok(start_page), Ok(end_page)) => import::pdf::PdfSection { title: None, start_page, end_page },
+                            _ => usage_error(&format!("Invalid page range {}", range)),
+                        }
+                    })
+                    .collect()
+            });
+            let report = import::pdf::import(&pool, Path::new(file), sections, SystemClock.now()).await?;
+            if let Some(collection) = report.collection {
+                println!("Added collection {}: {}", collection.id, collection.name);
+            }
+            for text in report.texts {
+                println!("Added text {}: {}", text.id, text.title);
+            }
+        }
+        "import-folder" => {
+            let dir = positional(rest, &["--collection"]).unwrap_or_else(|| usage_error("import-folder needs a folder"));
+            let collection_id = option(rest, "--collection")
+                .map(|id| id.parse().unwrap_or_else(|_| usage_error("--collection needs a collection id")));
+            let mut on_progress = |progress: &import::folder::FolderProgress| {
+                eprintln!("[{}/{}] {} {:?}", progress.current, progress.total, progress.path, progress.status);
+            };
+            let report =
+                import::folder::import(&pool, Path::new(dir), collection_id, SystemClock.now(), &mut on_progress).await?;
+            match &report.collection {
+                Some(collection) => println!(
+                    "Added {} texts to collection {}: {}",
+                    report.texts.len(),
+                    collection.id,
+                    collection.name
+                ),
+                None => println!("Added no texts"),
+            }
+            for file in report.skipped {
+                println!("Skipped {}: {}", file.path, file.reason);
+            }
+        }
+        "import-subtitles" => {
+            let file = positional(rest, &["--title"]).unwrap_or_else(|| usage_error("import-subtitles needs a file"));
+            let title = option(rest, "--title");
+            let report = import::subtitles::import(&pool, Path::new(file), title, SystemClock.now()).await?;
+            for text in report.texts {
+                println!("Added text {}: {}", text.id, text.title);
+            }
+        }
+        "import-dictionary" => {
+            let file = positional(rest, &["--name", "--language"])
+                .unwrap_or_else(|| usage_error("import-dictionary needs a file"));
+            let dictionary =
+                dictionary::import(&pool, Path::new(file), option(rest, "--name"), option(rest, "--language")).await?;
+            println!(
+                "Added dictionary {}: {} ({} entries)",
+                dictionary.id, dictionary.name, dictionary.entry_count
+            );
+        }
+        "lookup" => {
+            let word = positional(rest, &[]).unwrap_or_else(|| usage_error("lookup needs a word"));
+            let results = dictionary::lookup::lookup_word(&pool, word, None).await?;
+            if results.is_empty() {
+                println!("No entries for {}", word);
+            }
+            for result in results {
+                let entry = result.entry;
+                let part_of_speech = entry.part_of_speech.map(|pos| format!(" ({})", pos)).unwrap_or_default();
+                let matched = match result.match_kind {
+                    dictionary::lookup::MatchKind::Exact => String::new(),
+                    kind => format!(" ({} match: {})", kind.as_str(), result.matched_form),
+                };
+                println!("{}{} [{}]{}", entry.headword, part_of_speech, entry.dictionary_name, matched);
+                for (i, definition) in entry.definitions.iter().enumerate() {
+                    println!("  {}. {}", i + 1, definition);
+                }
+            }
+        }
+        "export" => {
+            let file = positional(rest, &[]).unwrap_or_else(|| usage_error("export needs a file"));
+            let export = transfer::export_database(&pool).await?;
+            let json = serde_json::to_string_pretty(&export)
+                .map_err(|e| AppError::internal("Failed to serialize database", e))?;
+            write_file(file, &json)?;
+            println!(
+                "Exported {} texts and {} words to {}",
+                export.texts.len(),
+                export.words.len(),
+                file
+            );
+        }
+        "import" => {
+            let file = positional(rest, &[]).unwrap_or_else(|| usage_error("import needs a file"));
+            let export: transfer::DatabaseExport = serde_json::from_str(&read_file(file)?)
+                .map_err(|e| AppError::invalid_data("Failed to deserialize database", e))?;
+            let (texts, words) = (export.texts.len(), export.words.len());
+            transfer::import_database(&pool, export).await?;
+            println!("Imported {} texts and {} words", texts, words);
+        }
+        "export-words" => {
+            let status = option(rest, "--status");
+            println!("word\tstatus\tmeaning\tnext_review_date");
+            for word in repo::words::list(&pool).await? {
+                if status.is_some() && word.status.as_deref() != status {
+                    continue;
+                }
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    cell(Some(&word.word)),
+                    cell(word.status.as_deref()),
+                    cell(word.meaning.as_deref()),
+                    cell(word.next_review_date.as_deref())
+                );
+            }
+        }
+        "due" => {
+            let limit = match option(rest, "--limit") {
+                Some(limit) => limit.parse().unwrap_or_else(|_| usage_error("--limit needs a number")),
+                None => i64::MAX,
+            };
+            for word in repo::words::due(&pool, SystemClock.now(), limit).await? {
+                println!(
+                    "{}\t{}\t{}",
+                    cell(Some(&word.word)),
+                    cell(word.meaning.as_deref()),
+                    cell(word.next_review_date.as_deref())
+                );
+            }
+        }
+        "stats" => {
+            let stats = repo::words::stats(&pool, SystemClock.now()).await?;
+            println!("Words:          {}", stats.total);
+            println!("  new:          {}", stats.new);
+            println!("  seen:         {}", stats.seen);
+            println!("  known:        {}", stats.known);
+            println!("Due now:        {}", stats.due_now);
+            println!("Due today:      {}", stats.due_today);
+            println!("Reviewed today: {}", stats.reviewed_today);
+        }
+        "sync-key" => match rest.first().map(String::as_str) {
+            Some("generate") => {
+                let sync_key = transfer::build_sync_key(&pool).await?;
+                match option(&rest[1..], "--out") {
+                    Some(file) => write_file(file, &sync_key)?,
+                    None => println!("{}", sync_key),
+                }
+            }
+            Some("apply") => {
+                let sync_key = match option(&rest[1..], "--file") {
+                    Some(file) => read_file(file)?,
+                    None => positional(&rest[1..], &[])
+                        .unwrap_or_else(|| usage_error("sync-key apply needs a key or --file"))
+                        .to_string(),
+                };
+                transfer::apply_sync_key(&pool, sync_key.trim()).await?;
+                println!("Sync key applied");
+            }
+            _ => usage_error("sync-key needs generate or apply"),
+        },
+        other => usage_error(&format!("Unknown command: {}", other)),
+    }
+
+    pool.close().await;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    // Only warnings go to stderr, stdout is for the output
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .init();
+
+    if let Err(e) = run(parse_args()).await {
+        eprintln!("error: {}", e.message());
+        if let Some(details) = e.details() {
+            eprintln!("  {}", details);
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,171 @@
+// App initialization shared by desktop and mobile: everything that has to
+// happen before the first command runs. It is driven from the Tauri `setup`
+// hook so paths come from the real app handle.
+
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions, SqliteConnectOptions};
+use std::path::Path;
+use tauri::{AppHandle, Manager, Runtime};
+use tracing::{info, error};
+
+use crate::error::{AppError, AppResult};
+use crate::{clock, encryption, library, logging, repo};
+
+// Schema changes applied on top of schema.sql, tracked with PRAGMA user_version.
+// Append new migrations at the end, never edit an applied one.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../migrations/0001_sync.sql"),
+    include_str!("../migrations/0002_settings.sql"),
+    include_str!("../migrations/0003_collections.sql"),
+    include_str!("../migrations/0004_text_metadata.sql"),
+    include_str!("../migrations/0005_reading_progress.sql"),
+    include_str!("../migrations/0006_reading_events.sql"),
+    include_str!("../migrations/0007_text_timestamps.sql"),
+    include_str!("../migrations/0008_dictionaries.sql"),
+    include_str!("../migrations/0009_dictionary_lookup.sql"),
+    include_str!("../migrations/0010_dictionary_providers.sql"),
+    include_str!("../migrations/0011_text_timestamp_format.sql"),
+];
+
+// user_version once dictionary providers exist, libraries below it get the shipped ones
+const DICTIONARY_PROVIDERS_VERSION: i64 = 10;
+
+pub fn setup(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = library::resolve(app.handle())?;
+    attach_log_dir(app, &paths);
+    encryption::recover_interrupted_rewrite(&paths.database);
+
+    let pool = tauri::async_runtime::block_on(async {
+        if let Err(e) = library::migrate_legacy_database(&paths).await {
+            error!("Failed to migrate the old database: {}", e);
+        }
+
+        // The pool is managed once the user unlocks the library
+        if encryption::is_encrypted(&paths.database) {
+            info!("Library is encrypted, waiting for the passphrase");
+            return Ok(None);
+        }
+
+        let pool = open_database(&paths.database, None).await?;
+        prepare_database(&pool).await?;
+        Ok::<_, AppError>(Some(pool))
+    })?;
+
+    app.manage(paths);
+    app.manage(clock::system());
+    if let Some(pool) = pool {
+        app.manage(pool);
+    }
+    Ok(())
+}
+
+// Opens an encrypted library and hands the pool to the commands
+pub async fn unlock<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    paths: &library::LibraryPaths,
+    passphrase: &str,
+) -> AppResult<()> {
+    if app_handle.try_state::<SqlitePool>().is_some() {
+        return Ok(());
+    }
+
+    encryption::verify_passphrase(&paths.database, passphrase).await?;
+    let pool = open_database(&paths.database, Some(passphrase)).await?;
+    prepare_database(&pool).await?;
+
+    app_handle.manage(pool);
+    info!("Library unlocked");
+    Ok(())
+}
+
+// Everything that needs the open database before the first command
+async fn prepare_database(pool: &SqlitePool) -> AppResult<()> {
+    init_db(pool).await?;
+    logging::apply_saved_filter(pool).await;
+    Ok(())
+}
+
+// Log files go to the platform log directory, or next to the data in portable mode
+fn attach_log_dir(app: &tauri::App, paths: &library::LibraryPaths) {
+    let log_dir = if paths.mode == library::LibraryMode::Portable {
+        Ok(paths.data_dir.join("logs"))
+    } else {
+        app.path()
+            .app_log_dir()
+            .map_err(|e| AppError::internal("Failed to get log directory", e))
+    };
+
+    if let Err(e) = log_dir.and_then(|dir| logging::attach_log_dir(&dir)) {
+        error!("Logging to files is disabled: {}", e);
+    }
+}
+
+// Opens (and creates if needed) a library database, `passphrase` is the SQLCipher key
+pub async fn open_database(path: &Path, passphrase: Option<&str>) -> AppResult<SqlitePool> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(AppError::io("Failed to create library directory"))?;
+    }
+
+    info!("Using database path: {}", path.display());
+
+    let mut options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        .foreign_keys(true);
+    if let Some(passphrase) = passphrase {
+        // sqlx always runs the key pragma first, as SQLCipher requires
+        options = options.pragma("key", encryption::key_pragma(passphrase));
+    }
+
+    // Connect to the SQLite database with foreign key support enabled
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+        .map_err(AppError::database("Failed to connect to database"))?;
+
+    // Verify that foreign keys are enabled
+    let foreign_keys_enabled: i32 = sqlx::query_scalar("PRAGMA foreign_keys;")
+        .fetch_one(&pool)
+        .await
+        .map_err(AppError::database("Failed to check foreign keys"))?;
+    if foreign_keys_enabled != 1 {
+        error!("Foreign keys are NOT enabled!");
+    }
+
+    Ok(pool)
+}
+
+// Creates the schema and applies pending migrations
+pub async fn init_db(pool: &SqlitePool) -> AppResult<()> {
+    let schema = include_str!("../schema.sql");
+    sqlx::query(schema)
+        .execute(pool)
+        .await
+        .map_err(AppError::database("Failed to initialize database"))?;
+
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| AppError::Database {
+                message: format!("Failed to apply migration {}", index + 1),
+                source: e,
+            })?;
+        sqlx::query(&format!("PRAGMA user_version = {}", index + 1))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        info!("Applied database migration {}", index + 1);
+    }
+
+    if version < DICTIONARY_PROVIDERS_VERSION {
+        repo::dictionary_providers::restore_defaults(pool).await?;
+    }
+    repo::dictionaries::index_entries(pool).await?;
+    Ok(())
+}
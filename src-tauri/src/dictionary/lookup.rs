@@ -0,0 +1,208 @@
+// Looking a word up as it appears in a text: "Ging", "maisons" or a typo are
+// rarely headwords. The lookup tries ever looser matches:
+//
+//   1. exact: the headword as written
+//   2. case: ignoring case ("Haus" for "haus")
+//   3. diacritics: ignoring accents and umlauts ("café" for "cafe")
+//   4. inflection: an inflected form listed by the entry ("ging" for "gehen")
+//   5. stem: the same stem in the dictionary's language ("maisons", "maison")
+//   6. fuzzy: headwords a letter or two away
+//
+// The first four all mean the word itself and are returned together; stems
+// are only tried if none of them matched, and fuzzy candidates only if
+// nothing else did.
+
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use std::collections::{BTreeSet, HashSet};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::{AppError, AppResult};
+use crate::repo::dictionaries::{self, DictionaryEntry};
+
+// Fuzzy candidates share the first letters of the word, typos are rarely there
+const FUZZY_PREFIX_LENGTH: usize = 2;
+// How many headwords with that prefix and a close length are compared at most
+const FUZZY_CANDIDATES: i64 = 5000;
+const MAX_FUZZY_RESULTS: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Exact,
+    Case,
+    Diacritics,
+    Inflection,
+    Stem,
+    Fuzzy,
+}
+
+impl MatchKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchKind::Exact => "exact",
+            MatchKind::Case => "case",
+            MatchKind::Diacritics => "diacritics",
+            MatchKind::Inflection => "inflection",
+            MatchKind::Stem => "stem",
+            MatchKind::Fuzzy => "fuzzy",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LookupResult {
+    #[serde(flatten)]
+    pub entry: DictionaryEntry,
+    pub match_kind: MatchKind,
+    pub matched_form: String, // The headword or inflected form that matched
+    pub distance: usize, // Edit distance for fuzzy matches, 0 otherwise
+}
+
+pub fn fold_case(word: &str) -> String {
+    word.trim().to_lowercase()
+}
+
+// Lowercase without diacritics. Letters that are not a base letter with a
+// mark get their usual transliteration.
+pub fn plain(word: &str) -> String {
+    let mut plain = String::with_capacity(word.len());
+    for c in fold_case(word).nfd().filter(|c| !unicode_normalization::char::is_combining_mark(*c)) {
+        match c {
+            'ß' => plain.push_str("ss"),
+            'æ' => plain.push_str("ae"),
+            'œ' => plain.push_str("oe"),
+            'ø' => plain.push('o'),
+            'ł' => plain.push('l'),
+            'đ' => plain.push('d'),
+            'ı' => plain.push('i'),
+            c => plain.push(c),
+        }
+    }
+    plain
+}
+
+fn algorithm(language: &str) -> Option<Algorithm> {
+    let code = language.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+    let algorithm = match code.as_str() {
+        "ar" => Algorithm::Arabic,
+        "da" => Algorithm::Danish,
+        "nl" => Algorithm::Dutch,
+        "en" => Algorithm::English,
+        "fi" => Algorithm::Finnish,
+        "fr" => Algorithm::French,
+        "de" => Algorithm::German,
+        "el" => Algorithm::Greek,
+        "hu" => Algorithm::Hungarian,
+        "it" => Algorithm::Italian,
+        "no" | "nb" | "nn" => Algorithm::Norwegian,
+        "pt" => Algorithm::Portuguese,
+        "ro" => Algorithm::Romanian,
+        "ru" => Algorithm::Russian,
+        "es" => Algorithm::Spanish,
+        "sv" => Algorithm::Swedish,
+        "ta" => Algorithm::Tamil,
+        "tr" => Algorithm::Turkish,
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+// Stemmer for `language` (an ISO 639-1 code), None for languages without one
+pub fn stemmer(language: &str) -> Option<Stemmer> {
+    Some(Stemmer::create(algorithm(language)?))
+}
+
+// Stem of the word with a stemmer made once for many words
+pub fn stem_with(stemmer: &Stemmer, word: &str) -> Option<String> {
+    let stem = stemmer.stem(&fold_case(word)).to_string();
+    (!stem.is_empty()).then_some(stem)
+}
+
+// Stem of the word in `language`, None for languages without a stemmer
+pub fn stem(word: &str, language: Option<&str>) -> Option<String> {
+    stem_with(&stemmer(language?)?, word)
+}
+
+fn max_distance(word: &str) -> usize {
+    if word.chars().count() <= 4 { 1 } else { 2 }
+}
+
+#[derive(Default)]
+struct Results {
+    results: Vec<LookupResult>,
+    seen: HashSet<i64>,
+}
+
+impl Results {
+    fn push(&mut self, entry: DictionaryEntry, match_kind: MatchKind, matched_form: String, distance: usize) {
+        if self.seen.insert(entry.id) {
+            self.results.push(LookupResult { entry, match_kind, matched_form, distance });
+        }
+    }
+
+    fn push_headwords(&mut self, entries: Vec<DictionaryEntry>, match_kind: MatchKind) {
+        for entry in entries {
+            let headword = entry.headword.clone();
+            self.push(entry, match_kind, headword, 0);
+        }
+    }
+}
+
+// Entries for `word`, best matches first, from one dictionary or all of them
+pub async fn lookup_word(pool: &SqlitePool, word: &str, dictionary_id: Option<i64>) -> AppResult<Vec<LookupResult>> {
+    let word = word.trim();
+    if word.is_empty() {
+        return Err(AppError::invalid_input("Nothing to look up"));
+    }
+    let plain_word = plain(word);
+    let mut results = Results::default();
+
+    results.push_headwords(dictionaries::by_headword(pool, word, dictionary_id).await?, MatchKind::Exact);
+    results.push_headwords(dictionaries::by_folded(pool, &fold_case(word), dictionary_id).await?, MatchKind::Case);
+    results.push_headwords(dictionaries::by_plain(pool, &plain_word, dictionary_id).await?, MatchKind::Diacritics);
+    for (entry, form) in dictionaries::by_form(pool, &plain_word, dictionary_id).await? {
+        results.push(entry, MatchKind::Inflection, form, 0);
+    }
+    if !results.results.is_empty() {
+        return Ok(results.results);
+    }
+
+    // Each dictionary is stemmed in its own language
+    let languages: BTreeSet<String> = dictionaries::list(pool)
+        .await?
+        .into_iter()
+        .filter(|d| dictionary_id.is_none_or(|id| id == d.id))
+        .filter_map(|d| d.language)
+        .collect();
+    for language in languages {
+        if let Some(stem) = stem(word, Some(&language)) {
+            let entries = dictionaries::by_stem(pool, &stem, &language, dictionary_id).await?;
+            results.push_headwords(entries, MatchKind::Stem);
+        }
+    }
+    if !results.results.is_empty() {
+        return Ok(results.results);
+    }
+
+    if plain_word.chars().count() > FUZZY_PREFIX_LENGTH {
+        let prefix: String = plain_word.chars().take(FUZZY_PREFIX_LENGTH).collect();
+        let length = plain_word.chars().count();
+        let max = max_distance(&plain_word);
+        let mut candidates: Vec<(usize, DictionaryEntry)> =
+            dictionaries::by_plain_prefix(pool, &prefix, length, max, dictionary_id, FUZZY_CANDIDATES)
+                .await?
+                .into_iter()
+                .map(|entry| (strsim::damerau_levenshtein(&plain_word, &plain(&entry.headword)), entry))
+                .filter(|(distance, _)| *distance <= max)
+                .collect();
+        candidates.sort_by_key(|(distance, _)| *distance);
+        for (distance, entry) in candidates.into_iter().take(MAX_FUZZY_RESULTS) {
+            let headword = entry.headword.clone();
+            results.push(entry, MatchKind::Fuzzy, headword, distance);
+        }
+    }
+
+    Ok(results.results)
+}
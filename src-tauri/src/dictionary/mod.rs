@@ -0,0 +1,152 @@
+// Offline dictionaries: files in one of the supported formats are read into
+// entries (headword, part of speech, definitions, inflections) and stored in
+// repo::dictionaries, so lookups work without a connection.
+
+pub mod lookup;
+pub mod stardict;
+pub mod suggestions;
+pub mod tsv;
+pub mod wiktionary;
+
+use sqlx::sqlite::SqlitePool;
+use std::path::Path;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::error::{AppError, AppResult};
+use crate::import;
+use crate::repo::dictionaries::{self, Dictionary, NewEntry};
+
+// Entries are stored in transactions of this many
+pub const BATCH_SIZE: usize = 2000;
+
+// Parsed batches waiting to be stored
+const QUEUED_BATCHES: usize = 2;
+
+type Batches = Box<dyn Iterator<Item = AppResult<Vec<NewEntry>>> + Send>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    StarDict,
+    Tsv,
+    Wiktionary,
+}
+
+impl Format {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::StarDict => "stardict",
+            Format::Tsv => "tsv",
+            Format::Wiktionary => "wiktionary",
+        }
+    }
+
+    // A StarDict dictionary is picked by any of its files
+    pub fn from_path(path: &Path) -> AppResult<Format> {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let name = name.trim_end_matches(".gz").trim_end_matches(".dz");
+        match name.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("ifo" | "idx" | "dict") => Ok(Format::StarDict),
+            Some("tsv" | "tab" | "txt") => Ok(Format::Tsv),
+            Some("jsonl" | "json") => Ok(Format::Wiktionary),
+            _ => Err(AppError::invalid_input(
+                "Unsupported dictionary, expected StarDict (.ifo), TSV (.tsv) or Wiktionary (.jsonl) files",
+            )),
+        }
+    }
+}
+
+// Collapses whitespace and drops empty and repeated definitions
+pub fn clean_definitions(definitions: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for definition in definitions {
+        let definition = definition.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !definition.is_empty() && !cleaned.contains(&definition) {
+            cleaned.push(definition);
+        }
+    }
+    cleaned
+}
+
+// Creates the dictionary and fills it, or leaves nothing behind
+pub async fn store(
+    pool: &SqlitePool,
+    name: &str,
+    format: Format,
+    language: Option<&str>,
+    mut batches: mpsc::Receiver<AppResult<Vec<NewEntry>>>,
+) -> AppResult<Dictionary> {
+    let dictionary = dictionaries::create(pool, name, format.as_str(), language).await?;
+
+    let result = async {
+        while let Some(batch) = batches.recv().await {
+            dictionaries::add_entries(pool, dictionary.id, &batch?).await?;
+        }
+        let dictionary = dictionaries::get(pool, dictionary.id).await?;
+        if dictionary.entry_count == 0 {
+            return Err(AppError::invalid_data("The dictionary has no entries", name.to_string()));
+        }
+        Ok(dictionary)
+    }
+    .await;
+
+    if let Err(e) = &result {
+        warn!("Import of dictionary {} failed, removing it: {}", name, e);
+        dictionaries::delete(pool, dictionary.id).await.ok();
+    }
+    result
+}
+
+// Batches of parsed entries, owned so they can be handed to another thread
+fn batches(entries: Vec<NewEntry>) -> Batches {
+    let batches: Vec<_> = entries.chunks(BATCH_SIZE).map(|batch| Ok(batch.to_vec())).collect();
+    Box::new(batches.into_iter())
+}
+
+// The name and language the file gives itself, and its entries. A Wiktionary
+// dump is only read as its batches are taken.
+fn open(path: &Path, format: Format) -> AppResult<(Option<String>, Option<String>, Batches)> {
+    match format {
+        Format::StarDict => {
+            let book = stardict::read(path)?;
+            Ok((book.name, None, batches(book.entries)))
+        }
+        Format::Tsv => {
+            let entries = tsv::parse(&import::decode_text(&import::read_file(path)?));
+            Ok((None, None, batches(entries)))
+        }
+        Format::Wiktionary => {
+            let reader = wiktionary::Reader::open(path)?;
+            Ok((None, reader.language.clone(), Box::new(reader)))
+        }
+    }
+}
+
+// Imports a dictionary file, named `name` or what the file calls itself.
+// `language` (an ISO 639-1 code) is what headwords are stemmed in, only
+// Wiktionary files tell it themselves.
+pub async fn import(pool: &SqlitePool, path: &Path, name: Option<&str>, language: Option<&str>) -> AppResult<Dictionary> {
+    let format = Format::from_path(path)?;
+    let name = name.map(str::trim).filter(|n| !n.is_empty()).map(str::to_string);
+    let language = language.map(|l| l.trim().to_lowercase()).filter(|l| !l.is_empty());
+
+    let file = path.to_path_buf();
+    let (own_name, own_language, batches) = import::blocking(move || open(&file, format)).await?;
+    let name = name.or(own_name).unwrap_or_else(|| import::file_stem(path));
+    let language = language.or(own_language);
+
+    // Parsing goes on off the runtime while the batches before are stored
+    let (sender, receiver) = mpsc::channel(QUEUED_BATCHES);
+    tokio::task::spawn_blocking(move || {
+        for batch in batches {
+            // The receiver is gone when storing failed
+            if sender.blocking_send(batch).is_err() {
+                break;
+            }
+        }
+    });
+    let dictionary = store(pool, &name, format, language.as_deref(), receiver).await?;
+
+    info!("Imported dictionary {} with {} entries", dictionary.name, dictionary.entry_count);
+    Ok(dictionary)
+}
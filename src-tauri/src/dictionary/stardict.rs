@@ -0,0 +1,208 @@
+// StarDict dictionaries: an .ifo file describing the book, an .idx index of
+// headwords with the position of their article, and the articles in a .dict
+// file. The index and the articles may be gzipped (.idx.gz, .dict.dz).
+
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::dictionary::clean_definitions;
+use crate::error::{AppError, AppResult};
+use crate::import::{self, html};
+use crate::repo::dictionaries::NewEntry;
+
+lazy_static! {
+    // The headword repeated at the top of XDXF articles
+    static ref XDXF_KEY_REGEX: Regex = Regex::new(r"(?s)<k>.*?</k>").unwrap();
+}
+
+pub struct Book {
+    pub name: Option<String>,
+    pub entries: Vec<NewEntry>,
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::invalid_data("Not a valid StarDict dictionary", message)
+}
+
+// The path of a StarDict file without its extensions, like `dicts/de-en`
+fn base_path(path: &Path) -> PathBuf {
+    let mut base = path.to_path_buf();
+    for extension in ["gz", "dz"] {
+        if base.extension().is_some_and(|e| e == extension) {
+            base.set_extension("");
+        }
+    }
+    if base.extension().is_some_and(|e| e == "ifo" || e == "idx" || e == "dict") {
+        base.set_extension("");
+    }
+    base
+}
+
+fn with_extension(base: &Path, extension: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+// `extension`, or its gzipped variant
+fn read_part(base: &Path, extension: &str, gzipped: &str) -> AppResult<Vec<u8>> {
+    let plain = with_extension(base, extension);
+    if plain.exists() {
+        return import::read_file(&plain);
+    }
+    let compressed = with_extension(base, gzipped);
+    if !compressed.exists() {
+        return Err(invalid(format!("{} is missing", plain.display())));
+    }
+    let mut bytes = Vec::new();
+    GzDecoder::new(&import::read_file(&compressed)?[..])
+        .take(import::MAX_FILE_SIZE)
+        .read_to_end(&mut bytes)
+        .map_err(|e| invalid(format!("Failed to decompress {}: {}", compressed.display(), e)))?;
+    Ok(bytes)
+}
+
+struct Info {
+    name: Option<String>,
+    offset_bits: usize,
+    same_type_sequence: Option<String>,
+}
+
+fn parse_info(content: &str) -> AppResult<Info> {
+    let mut lines = content.lines();
+    if !lines.next().is_some_and(|line| line.trim_start_matches('\u{feff}').starts_with("StarDict's dict ifo file")) {
+        return Err(invalid("The .ifo file has no StarDict header".to_string()));
+    }
+
+    let mut info = Info { name: None, offset_bits: 32, same_type_sequence: None };
+    for (key, value) in lines.filter_map(|line| line.split_once('=')) {
+        let value = value.trim();
+        match key.trim() {
+            "bookname" if !value.is_empty() => info.name = Some(value.to_string()),
+            "idxoffsetbits" => info.offset_bits = if value == "64" { 64 } else { 32 },
+            "sametypesequence" if !value.is_empty() => info.same_type_sequence = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+// Headwords with the position and size of their article in the .dict file
+fn parse_index(index: &[u8], offset_bits: usize) -> AppResult<Vec<(String, usize, usize)>> {
+    let offset_size = offset_bits / 8;
+    let mut words = Vec::new();
+    let mut at = 0;
+    while at < index.len() {
+        let end = index[at..]
+            .iter()
+            .position(|&b| b == 0)
+            .map(|i| at + i)
+            .ok_or_else(|| invalid("Unterminated headword in the index".to_string()))?;
+        let word = String::from_utf8_lossy(&index[at..end]).to_string();
+        at = end + 1;
+
+        let offset = match offset_size {
+            8 => index.get(at..at + 8).and_then(|b| b.try_into().ok()).map(|b| u64::from_be_bytes(b) as usize),
+            _ => read_u32(index, at).map(|n| n as usize),
+        };
+        let size = read_u32(index, at + offset_size);
+        let (Some(offset), Some(size)) = (offset, size) else {
+            return Err(invalid(format!("The index entry of {} is cut off", word)));
+        };
+        at += offset_size + 4;
+        words.push((word, offset, size as usize));
+    }
+    Ok(words)
+}
+
+// Readable text of one field of an article
+fn field_text(kind: char, data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    match kind {
+        'm' | 'l' | 'y' | 'k' | 'w' => Some(text.to_string()),
+        'g' | 'h' => Some(html::to_text(&text.replace('\n', "<br>"))),
+        'x' => Some(html::to_text(&XDXF_KEY_REGEX.replace_all(&text, "").replace('\n', "<br>"))),
+        _ => None, // Phonetics, resources and binary data (uppercase types)
+    }
+}
+
+// The fields of an article: (type, data). With a type sequence from the
+// .ifo, the types are not repeated in the article and the last field runs
+// to its end.
+fn parse_article(article: &[u8], same_type_sequence: Option<&str>) -> Vec<(char, Vec<u8>)> {
+    let mut fields = Vec::new();
+    let mut at = 0;
+    let mut types = same_type_sequence.map(|sequence| sequence.chars().collect::<Vec<_>>().into_iter());
+
+    loop {
+        let (kind, last) = match &mut types {
+            Some(types) => match types.next() {
+                Some(kind) => (kind, types.len() == 0),
+                None => break,
+            },
+            None => match article.get(at) {
+                Some(&kind) => {
+                    at += 1;
+                    (kind as char, false)
+                }
+                None => break,
+            },
+        };
+
+        let rest = &article[at.min(article.len())..];
+        let (data, used) = if last {
+            (rest, rest.len())
+        } else if kind.is_ascii_lowercase() {
+            match rest.iter().position(|&b| b == 0) {
+                Some(end) => (&rest[..end], end + 1),
+                None => (rest, rest.len()),
+            }
+        } else {
+            let size = read_u32(rest, 0).unwrap_or_default() as usize;
+            let end = (4 + size).min(rest.len());
+            (&rest[4.min(rest.len())..end], end)
+        };
+        fields.push((kind, data.to_vec()));
+        at += used;
+    }
+    fields
+}
+
+// Reads the dictionary `path` belongs to
+pub fn read(path: &Path) -> AppResult<Book> {
+    let base = base_path(path);
+    let info = parse_info(&import::decode_text(&import::read_file(&with_extension(&base, "ifo"))?))?;
+    let index = parse_index(&read_part(&base, "idx", "idx.gz")?, info.offset_bits)?;
+    let articles = read_part(&base, "dict", "dict.dz")?;
+
+    let mut entries = Vec::with_capacity(index.len());
+    for (headword, offset, size) in index {
+        let article = offset
+            .checked_add(size)
+            .and_then(|end| articles.get(offset..end))
+            .ok_or_else(|| invalid(format!("The article of {} is outside the .dict file", headword)))?;
+        let definitions = parse_article(article, info.same_type_sequence.as_deref())
+            .into_iter()
+            .filter_map(|(kind, data)| field_text(kind, &data))
+            .flat_map(|text| text.lines().map(str::to_string).collect::<Vec<_>>());
+        let definitions = clean_definitions(definitions);
+        if headword.trim().is_empty() || definitions.is_empty() {
+            continue;
+        }
+        entries.push(NewEntry {
+            headword: headword.trim().to_string(),
+            definitions,
+            ..Default::default()
+        });
+    }
+
+    Ok(Book { name: info.name, entries })
+}
@@ -0,0 +1,144 @@
+// Meanings to offer when the user saves one for a word: what they already
+// wrote for other forms of it ("Haus" for "haus", "gehen" for "ging") and
+// the definitions of the offline dictionaries. Meanings the user wrote come
+// before dictionary definitions that match as closely.
+
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use std::collections::{BTreeSet, HashSet};
+
+use crate::dictionary::lookup::{self, MatchKind};
+use crate::error::{AppError, AppResult};
+use crate::repo;
+
+const MAX_SUGGESTIONS: usize = 12;
+// The first definitions of an entry are the common ones
+const DEFINITIONS_PER_ENTRY: usize = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    Vocabulary,
+    Dictionary,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MeaningSuggestion {
+    pub meaning: String,
+    pub source: SuggestionSource,
+    pub match_kind: MatchKind, // How the word relates to matched_word
+    pub matched_word: String, // The vocabulary word or the dictionary headword
+    pub dictionary_name: Option<String>,
+}
+
+// Forms of the word itself, stems, then near misses
+fn tier(match_kind: MatchKind) -> u8 {
+    match match_kind {
+        MatchKind::Exact | MatchKind::Case | MatchKind::Diacritics | MatchKind::Inflection => 0,
+        MatchKind::Stem => 1,
+        MatchKind::Fuzzy => 2,
+    }
+}
+
+// The language picker saves country codes, stemmers want language codes
+fn picked_language(country_code: &str) -> String {
+    match country_code {
+        "dk" => "da".to_string(),
+        "se" => "sv".to_string(),
+        code => code.to_string(),
+    }
+}
+
+// Languages to compare stems in: those of the dictionaries and the picked one
+async fn stem_languages(pool: &SqlitePool) -> AppResult<BTreeSet<String>> {
+    let mut languages: BTreeSet<String> = repo::dictionaries::list(pool)
+        .await?
+        .into_iter()
+        .filter_map(|d| d.language)
+        .collect();
+    if let Some(country_code) = repo::preferences::country_code(pool).await? {
+        languages.insert(picked_language(&country_code));
+    }
+    Ok(languages)
+}
+
+// Meanings of other vocabulary words that are a form of `word`
+async fn vocabulary_suggestions(
+    pool: &SqlitePool,
+    word_id: i64,
+    word: &str,
+    lemma_forms: &HashSet<String>,
+) -> AppResult<Vec<MeaningSuggestion>> {
+    let folded = lookup::fold_case(word);
+    let plain = lookup::plain(word);
+    // One stemmer per language, each with the stem of `word`
+    let stemmers: Vec<_> = stem_languages(pool)
+        .await?
+        .iter()
+        .filter_map(|language| lookup::stemmer(language))
+        .filter_map(|stemmer| Some((lookup::stem_with(&stemmer, word)?, stemmer)))
+        .collect();
+
+    let mut suggestions = Vec::new();
+    for other in repo::words::with_meaning(pool, word_id).await? {
+        let meaning = other.meaning.as_deref().map(str::trim).unwrap_or_default();
+        let other_plain = lookup::plain(&other.word);
+        let match_kind = if lookup::fold_case(&other.word) == folded {
+            MatchKind::Case
+        } else if other_plain == plain {
+            MatchKind::Diacritics
+        } else if lemma_forms.contains(&other_plain) {
+            MatchKind::Inflection
+        } else if stemmers.iter().any(|(stem, stemmer)| lookup::stem_with(stemmer, &other.word).as_ref() == Some(stem)) {
+            MatchKind::Stem
+        } else {
+            continue;
+        };
+        suggestions.push(MeaningSuggestion {
+            meaning: meaning.to_string(),
+            source: SuggestionSource::Vocabulary,
+            match_kind,
+            matched_word: other.word,
+            dictionary_name: None,
+        });
+    }
+    suggestions.sort_by(|a, b| a.match_kind.cmp(&b.match_kind).then_with(|| a.matched_word.cmp(&b.matched_word)));
+    Ok(suggestions)
+}
+
+// Candidate meanings for a vocabulary word, best first, each meaning once
+pub async fn suggest_meanings(pool: &SqlitePool, word_id: i64) -> AppResult<Vec<MeaningSuggestion>> {
+    let word = repo::words::get(pool, word_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Word ID not found"))?;
+
+    let results = lookup::lookup_word(pool, &word.word, None).await?;
+    // Entries the word is a form of, their other forms share its lemma
+    let entry_ids: Vec<i64> = results
+        .iter()
+        .filter(|r| tier(r.match_kind) == 0)
+        .map(|r| r.entry.id)
+        .collect();
+    let lemma_forms: HashSet<String> = repo::dictionaries::entry_forms(pool, &entry_ids).await?.into_iter().collect();
+
+    let mut suggestions = vocabulary_suggestions(pool, word_id, &word.word, &lemma_forms).await?;
+    for result in results {
+        for definition in result.entry.definitions.iter().take(DEFINITIONS_PER_ENTRY) {
+            suggestions.push(MeaningSuggestion {
+                meaning: definition.clone(),
+                source: SuggestionSource::Dictionary,
+                match_kind: result.match_kind,
+                matched_word: result.entry.headword.clone(),
+                dictionary_name: Some(result.entry.dictionary_name.clone()),
+            });
+        }
+    }
+
+    // Stable, so each group keeps its order
+    suggestions.sort_by_key(|s| (tier(s.match_kind), s.source));
+    // Nothing to suggest in what the word already has
+    let mut seen: HashSet<String> = word.meaning.iter().map(|m| m.trim().to_lowercase()).collect();
+    suggestions.retain(|s| seen.insert(s.meaning.to_lowercase()));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    Ok(suggestions)
+}
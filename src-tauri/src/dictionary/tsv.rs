@@ -0,0 +1,49 @@
+// Tab-separated dictionaries, one definition per line:
+//
+//     headword<TAB>definition[<TAB>part of speech]
+//
+// Lines starting with # are comments, and a first line naming the columns is
+// skipped. Lines with the same headword and part of speech make one entry.
+
+use std::collections::HashMap;
+
+use crate::dictionary::clean_definitions;
+use crate::repo::dictionaries::NewEntry;
+
+const HEADWORD_COLUMNS: &[&str] = &["word", "headword", "term", "lemma"];
+
+pub fn parse(content: &str) -> Vec<NewEntry> {
+    let mut entries: Vec<NewEntry> = Vec::new();
+    let mut positions: HashMap<(String, Option<String>), usize> = HashMap::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut columns = line.split('\t').map(str::trim);
+        let headword = columns.next().unwrap_or_default();
+        let definition = columns.next().unwrap_or_default();
+        let part_of_speech = columns.next().filter(|pos| !pos.is_empty()).map(str::to_string);
+        if headword.is_empty() || definition.is_empty() {
+            continue;
+        }
+        if i == 0 && HEADWORD_COLUMNS.contains(&headword.to_lowercase().as_str()) {
+            continue;
+        }
+
+        let key = (headword.to_string(), part_of_speech.clone());
+        let position = *positions.entry(key).or_insert_with(|| {
+            entries.push(NewEntry {
+                headword: headword.to_string(),
+                part_of_speech,
+                ..Default::default()
+            });
+            entries.len() - 1
+        });
+        let entry = &mut entries[position];
+        entry.definitions = clean_definitions(entry.definitions.drain(..).chain([definition.to_string()]));
+    }
+
+    entries
+}
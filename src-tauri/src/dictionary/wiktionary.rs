@@ -0,0 +1,130 @@
+// Wiktionary dumps from kaikki.org: one JSON object per line, per word and
+// part of speech, with its senses and inflected forms. Dumps run into
+// gigabytes, so they are read a batch of entries at a time.
+
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Lines};
+use std::path::Path;
+
+use crate::dictionary::{clean_definitions, BATCH_SIZE};
+use crate::error::{AppError, AppResult};
+use crate::repo::dictionaries::{Inflection, NewEntry};
+
+// Forms that are not words: inflection table headers, romanizations, ...
+const SKIPPED_FORM_TAGS: &[&str] = &["table-tags", "inflection-template", "class", "romanization"];
+
+#[derive(Deserialize)]
+struct Line {
+    word: String,
+    pos: Option<String>,
+    lang_code: Option<String>,
+    #[serde(default)]
+    senses: Vec<Sense>,
+    #[serde(default)]
+    forms: Vec<Form>,
+}
+
+#[derive(Deserialize)]
+struct Sense {
+    #[serde(default)]
+    glosses: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Form {
+    form: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+// The entry of one line of the dump, with the language of the word. Words
+// without glosses (redirects, for example) have no entry.
+pub fn parse_line(line: &str) -> AppResult<Option<(NewEntry, Option<String>)>> {
+    let line: Line = serde_json::from_str(line).map_err(|e| AppError::invalid_data("Not a Wiktionary entry", e))?;
+
+    // Nested senses list the glosses of their parents first, the last is their own
+    let definitions = clean_definitions(line.senses.into_iter().filter_map(|sense| sense.glosses.into_iter().last()));
+    if line.word.trim().is_empty() || definitions.is_empty() {
+        return Ok(None);
+    }
+
+    let mut inflections: Vec<Inflection> = Vec::new();
+    for form in line.forms {
+        let form_text = form.form.trim();
+        if form_text.is_empty() || form_text == "-" || form.tags.iter().any(|t| SKIPPED_FORM_TAGS.contains(&t.as_str())) {
+            continue;
+        }
+        let inflection = Inflection { form: form_text.to_string(), tags: form.tags };
+        if !inflections.contains(&inflection) {
+            inflections.push(inflection);
+        }
+    }
+
+    let entry = NewEntry {
+        headword: line.word.trim().to_string(),
+        part_of_speech: line.pos,
+        definitions,
+        inflections,
+    };
+    Ok(Some((entry, line.lang_code)))
+}
+
+// The entries of a dump in batches. The language of the dictionary is the
+// one of its first entry.
+pub struct Reader {
+    lines: Lines<BufReader<File>>,
+    line_number: usize,
+    first: Option<NewEntry>,
+    pub language: Option<String>,
+}
+
+impl Reader {
+    pub fn open(path: &Path) -> AppResult<Reader> {
+        let file = File::open(path).map_err(AppError::io("Failed to open the dictionary"))?;
+        let mut reader = Reader {
+            lines: BufReader::new(file).lines(),
+            line_number: 0,
+            first: None,
+            language: None,
+        };
+        if let Some((entry, language)) = reader.next_entry()? {
+            reader.first = Some(entry);
+            reader.language = language;
+        }
+        Ok(reader)
+    }
+
+    fn next_entry(&mut self) -> AppResult<Option<(NewEntry, Option<String>)>> {
+        for line in self.lines.by_ref() {
+            self.line_number += 1;
+            let line = line.map_err(AppError::io("Failed to read the dictionary"))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed = parse_line(&line).map_err(|e| {
+                AppError::invalid_data(format!("Invalid entry on line {}", self.line_number), e)
+            })?;
+            if parsed.is_some() {
+                return Ok(parsed);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for Reader {
+    type Item = AppResult<Vec<NewEntry>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut batch: Vec<NewEntry> = self.first.take().into_iter().collect();
+        while batch.len() < BATCH_SIZE {
+            match self.next_entry() {
+                Ok(Some((entry, _))) => batch.push(entry),
+                Ok(None) => break,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        (!batch.is_empty()).then_some(Ok(batch))
+    }
+}
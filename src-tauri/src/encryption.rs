@@ -0,0 +1,210 @@
+// Optional encryption at rest for the library database, using SQLCipher.
+//
+// An encrypted database is not opened at startup: the app waits for the
+// passphrase (see the `unlock_database` command). Enabling, changing and
+// removing encryption all rewrite the database with `sqlcipher_export` into a
+// new file, check it opens with the new passphrase, and only then replace the
+// original. The app restarts afterwards so every connection uses the new key.
+
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions, SqliteConnectOptions};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::error::{AppError, AppResult};
+
+// Every plaintext SQLite file starts with this, SQLCipher files look like random bytes
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const REWRITE_SUFFIX: &str = "rewrite";
+const BACKUP_SUFFIX: &str = "bak";
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DatabaseStatus {
+    pub encrypted: bool,
+    pub unlocked: bool,
+}
+
+fn sibling(database: &Path, suffix: &str) -> PathBuf {
+    let mut name = database.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+pub fn is_encrypted(database: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match std::fs::File::open(database).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_HEADER,
+        // Missing or empty files are created as plaintext databases
+        Err(_) => false,
+    }
+}
+
+// Value for `PRAGMA key`, quoted as an SQL string literal
+pub fn key_pragma(passphrase: &str) -> String {
+    format!("'{}'", passphrase.replace('\'', "''"))
+}
+
+pub fn check_passphrase(passphrase: &str) -> AppResult<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(AppError::invalid_input(format!(
+            "Passphrase must be at least {} characters long",
+            MIN_PASSPHRASE_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+// SQLCipher accepts any key when connecting, a wrong one only shows on the first read
+pub fn map_key_error(e: sqlx::Error) -> AppError {
+    match e.as_database_error().and_then(|d| d.code()) {
+        Some(code) if code == "26" => AppError::invalid_input("Wrong passphrase"), // SQLITE_NOTADB
+        _ => AppError::Database {
+            message: "Failed to open encrypted database".to_string(),
+            source: e,
+        },
+    }
+}
+
+// Opens a single connection to check a database and its passphrase
+async fn open_single(database: &Path, passphrase: Option<&str>) -> AppResult<SqlitePool> {
+    let mut options = SqliteConnectOptions::new().filename(database).read_only(true);
+    if let Some(passphrase) = passphrase {
+        options = options.pragma("key", key_pragma(passphrase));
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .map_err(map_key_error)?;
+
+    let check: String = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_one(&pool)
+        .await
+        .map_err(map_key_error)?;
+    if check != "ok" {
+        pool.close().await;
+        return Err(AppError::invalid_data("Database integrity check failed", check));
+    }
+
+    Ok(pool)
+}
+
+pub async fn verify_passphrase(database: &Path, passphrase: &str) -> AppResult<()> {
+    open_single(database, Some(passphrase)).await?.close().await;
+    Ok(())
+}
+
+// A crash between moving the old database away and putting the new one in
+// place leaves only the backup; bring it back before opening the library
+pub fn recover_interrupted_rewrite(database: &Path) {
+    let backup = sibling(database, BACKUP_SUFFIX);
+    if database.exists() || !backup.exists() {
+        return;
+    }
+
+    match std::fs::rename(&backup, database) {
+        Ok(()) => warn!("Restored {} after an interrupted rewrite", database.display()),
+        Err(e) => warn!("Failed to restore {}: {}", backup.display(), e),
+    }
+}
+
+// Rewrites the database open in `pool` with `passphrase` (None for plaintext).
+// The pool is closed whether or not this succeeds, the caller restarts the app
+// either way. On failure the original database is left in place.
+pub async fn rewrite(pool: &SqlitePool, database: &Path, passphrase: Option<&str>) -> AppResult<()> {
+    // ATTACH and sqlcipher_export have to run on the same connection
+    let mut conn = pool.acquire().await?;
+    // Nothing can take a new connection from here on, so no write lands in
+    // the old database after the export
+    let closing = pool.close();
+
+    let target = sibling(database, REWRITE_SUFFIX);
+    if target.exists() {
+        std::fs::remove_file(&target).map_err(AppError::io("Failed to remove old rewrite file"))?;
+    }
+
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    sqlx::query("ATTACH DATABASE ? AS rewritten KEY ?")
+        .bind(target.to_string_lossy().to_string())
+        .bind(passphrase.unwrap_or(""))
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::database("Failed to create the new database"))?;
+
+    // The write lock waits for connections that are still writing and keeps
+    // them out until the copy is complete
+    let exported = async {
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+        let copied = async {
+            sqlx::query("SELECT sqlcipher_export('rewritten')")
+                .execute(&mut *conn)
+                .await?;
+            // sqlcipher_export does not copy the schema version the migrations rely on
+            sqlx::query(&format!("PRAGMA rewritten.user_version = {}", version))
+                .execute(&mut *conn)
+                .await?;
+            Ok::<_, sqlx::Error>(())
+        }
+        .await;
+        let end = if copied.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        sqlx::query(end).execute(&mut *conn).await?;
+        copied
+    }
+    .await;
+
+    sqlx::query("DETACH DATABASE rewritten")
+        .execute(&mut *conn)
+        .await?;
+    drop(conn);
+    closing.await;
+
+    if let Err(e) = exported {
+        std::fs::remove_file(&target).ok();
+        return Err(AppError::Database {
+            message: "Failed to copy the database".to_string(),
+            source: e,
+        });
+    }
+
+    // Never replace the original with something that cannot be opened
+    match open_single(&target, passphrase).await {
+        Ok(check) => check.close().await,
+        Err(e) => {
+            std::fs::remove_file(&target).ok();
+            return Err(e);
+        }
+    }
+
+    // Journal files left next to the old database would be applied to the new one
+    for suffix in ["wal", "shm"] {
+        let journal = PathBuf::from(format!("{}-{}", database.display(), suffix));
+        if journal.exists() {
+            std::fs::remove_file(&journal).map_err(AppError::io("Failed to remove journal file"))?;
+        }
+    }
+
+    let backup = sibling(database, BACKUP_SUFFIX);
+    std::fs::rename(database, &backup).map_err(AppError::io("Failed to move the old database"))?;
+    if let Err(e) = std::fs::rename(&target, database) {
+        std::fs::rename(&backup, database).ok();
+        return Err(AppError::Io {
+            message: "Failed to replace the database".to_string(),
+            source: e,
+        });
+    }
+    std::fs::remove_file(&backup).map_err(AppError::io("Failed to remove the old database"))?;
+
+    info!(
+        "Rewrote {} {}",
+        database.display(),
+        if passphrase.is_some() { "with encryption" } else { "without encryption" }
+    );
+    Ok(())
+}
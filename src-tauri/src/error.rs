@@ -0,0 +1,154 @@
+// Error type returned by every Tauri command.
+//
+// The frontend receives it as `{ code, message, details }`: `code` is stable
+// and meant for branching, `message` can be shown to the user as is and
+// `details` carries the underlying error (SQL, IO, ...) for logs and bug reports.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AppError {
+    // The requested row does not exist
+    NotFound(String),
+    // The caller sent something the backend cannot accept
+    InvalidInput(String),
+    // Data coming from outside (sync keys, QR chunks, files) is malformed
+    InvalidData { message: String, details: String },
+    Database { message: String, source: sqlx::Error },
+    Io { message: String, source: std::io::Error },
+    // Talking to the sync server failed
+    Sync { message: String, details: Option<String> },
+    // Something on our side failed that no other input would fix
+    // (serializing our own data, platform directories, logging setup)
+    Internal { message: String, details: String },
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> AppError {
+        AppError::NotFound(message.into())
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> AppError {
+        AppError::InvalidInput(message.into())
+    }
+
+    pub fn invalid_data(message: impl Into<String>, details: impl ToString) -> AppError {
+        AppError::InvalidData {
+            message: message.into(),
+            details: details.to_string(),
+        }
+    }
+
+    pub fn sync(message: impl Into<String>, details: Option<String>) -> AppError {
+        AppError::Sync {
+            message: message.into(),
+            details,
+        }
+    }
+
+    pub fn internal(message: impl Into<String>, details: impl ToString) -> AppError {
+        AppError::Internal {
+            message: message.into(),
+            details: details.to_string(),
+        }
+    }
+
+    // For `map_err` on queries that need more context than "Database error"
+    pub fn database(message: &str) -> impl FnOnce(sqlx::Error) -> AppError + '_ {
+        move |source| AppError::Database {
+            message: message.to_string(),
+            source,
+        }
+    }
+
+    pub fn io(message: &str) -> impl FnOnce(std::io::Error) -> AppError + '_ {
+        move |source| AppError::Io {
+            message: message.to_string(),
+            source,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::InvalidInput(_) => "invalid_input",
+            AppError::InvalidData { .. } => "invalid_data",
+            AppError::Database { .. } => "database",
+            AppError::Io { .. } => "io",
+            AppError::Sync { .. } => "sync",
+            AppError::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(message)
+            | AppError::InvalidInput(message)
+            | AppError::InvalidData { message, .. }
+            | AppError::Database { message, .. }
+            | AppError::Io { message, .. }
+            | AppError::Sync { message, .. }
+            | AppError::Internal { message, .. } => message,
+        }
+    }
+
+    pub fn details(&self) -> Option<String> {
+        match self {
+            AppError::NotFound(_) | AppError::InvalidInput(_) => None,
+            AppError::InvalidData { details, .. } | AppError::Internal { details, .. } => Some(details.clone()),
+            AppError::Database { source, .. } => Some(source.to_string()),
+            AppError::Io { source, .. } => Some(source.to_string()),
+            AppError::Sync { details, .. } => details.clone(),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details() {
+            Some(details) => write!(f, "{}: {}", self.message(), details),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Database { source, .. } => Some(source),
+            AppError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(source: sqlx::Error) -> AppError {
+        AppError::Database {
+            message: "Database error".to_string(),
+            source,
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(source: std::io::Error) -> AppError {
+        AppError::Io {
+            message: "File system error".to_string(),
+            source,
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
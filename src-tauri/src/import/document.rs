@@ -0,0 +1,140 @@
+// Single documents: saved web pages (.html), Markdown (.md) and plain text
+// (.txt). Each becomes one text, its paragraphs kept.
+
+use chrono::{DateTime, Utc};
+use pulldown_cmark::{Options, Parser};
+use sqlx::sqlite::SqlitePool;
+use std::path::Path;
+use tracing::info;
+
+use crate::error::{AppError, AppResult};
+use crate::import::{self, html, ImportReport, ImportedText};
+use crate::models::TextMetadata;
+use crate::repo::texts;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Markdown,
+    PlainText,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> AppResult<Format> {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "html" | "htm" | "xhtml" => Ok(Format::Html),
+            "md" | "markdown" => Ok(Format::Markdown),
+            "txt" | "text" | "" => Ok(Format::PlainText),
+            _ => Err(AppError::invalid_input(format!(
+                "Unsupported file type .{}, expected .html, .md or .txt",
+                extension
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Document {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub content: String,
+}
+
+// Paragraphs are separated by blank lines; the lines of a paragraph are
+// joined, they are usually wrapped at a fixed width. A file without blank
+// lines has one paragraph per line.
+fn plain_text(content: &str) -> String {
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+    let lines: Vec<&str> = content.lines().map(str::trim).collect();
+
+    let paragraphs: Vec<String> = if lines.iter().any(|line| line.is_empty()) {
+        lines
+            .split(|line| line.is_empty())
+            .filter(|lines| !lines.is_empty())
+            .map(|lines| lines.join(" "))
+            .collect()
+    } else {
+        lines.iter().map(|line| line.to_string()).collect()
+    };
+
+    paragraphs
+        .iter()
+        .map(|p| p.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn markdown(content: &str) -> Document {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let mut rendered = String::new();
+    pulldown_cmark::html::push_html(&mut rendered, Parser::new_ext(content, options));
+
+    Document {
+        title: html::first_heading(&rendered),
+        author: None,
+        content: html::to_text(&rendered),
+    }
+}
+
+pub fn parse(content: &str, format: Format) -> Document {
+    match format {
+        Format::Html => {
+            let article = html::article(content);
+            Document {
+                title: article.title,
+                author: article.author,
+                content: article.content,
+            }
+        }
+        Format::Markdown => markdown(content),
+        Format::PlainText => Document {
+            content: plain_text(content),
+            ..Default::default()
+        },
+    }
+}
+
+// `title` and the fields of `metadata` replace what the document says about itself
+pub async fn import(
+    pool: &SqlitePool,
+    path: &Path,
+    title: Option<&str>,
+    metadata: &TextMetadata,
+    now: DateTime<Utc>,
+) -> AppResult<ImportReport> {
+    let format = Format::from_path(path)?;
+    let document = parse(&import::decode_text(&import::read_file(path)?), format);
+    if texts::token_count(&document.content) == 0 {
+        return Err(AppError::invalid_data(
+            "The file has no text",
+            format!("No words found in {}", path.display()),
+        ));
+    }
+
+    let title = title
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .or(document.title)
+        .unwrap_or_else(|| import::file_stem(path));
+    let metadata = TextMetadata {
+        source: metadata.source.clone().or_else(|| import::file_name(path)),
+        author: metadata.author.clone().or(document.author),
+        ..metadata.clone()
+    };
+    let text = texts::create_with_metadata(pool, &title, &document.content, &metadata, now).await?;
+
+    info!("Imported {} as text {}", path.display(), text.id);
+    Ok(ImportReport {
+        collection: None,
+        texts: vec![ImportedText { id: text.id, title: text.title }],
+    })
+}
@@ -0,0 +1,256 @@
+// EPUB books: every document of the spine becomes a text, all of them in a
+// collection named after the book. Chapter titles come from the table of
+// contents (EPUB 3 navigation document or EPUB 2 NCX), otherwise from the
+// first heading of the chapter.
+
+use chrono::{DateTime, Utc};
+use roxmltree::{Document, Node};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::error::{AppError, AppResult};
+use crate::import::{self, html, ImportReport};
+use crate::models::TextMetadata;
+use crate::repo::texts;
+
+const CONTAINER_FILE: &str = "META-INF/container.xml";
+
+#[derive(Debug, Default)]
+pub struct Chapter {
+    pub title: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Book {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub chapters: Vec<Chapter>,
+}
+
+struct ManifestItem {
+    href: String, // Path inside the archive
+    media_type: String,
+    properties: String,
+}
+
+fn invalid(message: &str) -> AppError {
+    AppError::invalid_data("Not a valid EPUB file", message.to_string())
+}
+
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> AppResult<String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|_| invalid(&format!("{} is missing", name)))?;
+    let mut content = String::new();
+    entry
+        .take(import::MAX_FILE_SIZE)
+        .read_to_string(&mut content)
+        .map_err(|e| invalid(&format!("Failed to read {}: {}", name, e)))?;
+    Ok(content)
+}
+
+// NCX files usually come with a DOCTYPE, which roxmltree rejects by default
+fn parse_xml(content: &str) -> AppResult<Document<'_>> {
+    let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    Document::parse_with_options(content, options).map_err(|e| invalid(&e.to_string()))
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+fn element_text(node: Node) -> Option<String> {
+    let text: String = node.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+fn percent_decode(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+// Resolves `href` (relative to the file at `base`) to a path inside the archive
+fn resolve(base: &str, href: &str) -> String {
+    let href = percent_decode(href.split('#').next().unwrap_or_default());
+    let mut parts: Vec<&str> = base.split('/').collect();
+    parts.pop(); // The file name of the base
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+// Chapter titles by document path, from the EPUB 3 navigation document
+fn nav_titles(nav: &str, nav_path: &str) -> HashMap<String, String> {
+    let document = scraper::Html::parse_document(nav);
+    let nav_selector = scraper::Selector::parse("nav").unwrap();
+    let link_selector = scraper::Selector::parse("a[href]").unwrap();
+
+    // The table of contents is the nav with epub:type="toc", or the first one
+    let navs: Vec<_> = document.select(&nav_selector).collect();
+    let toc = navs
+        .iter()
+        .find(|nav| nav.value().attrs().any(|(name, value)| name.ends_with("type") && value == "toc"))
+        .or(navs.first());
+
+    let mut titles = HashMap::new();
+    if let Some(toc) = toc {
+        for link in toc.select(&link_selector) {
+            let title = link.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ");
+            if let (Some(href), false) = (link.value().attr("href"), title.is_empty()) {
+                titles.entry(resolve(nav_path, href)).or_insert(title);
+            }
+        }
+    }
+    titles
+}
+
+// Chapter titles by document path, from the EPUB 2 NCX
+fn ncx_titles(ncx: &str, ncx_path: &str) -> HashMap<String, String> {
+    let mut titles = HashMap::new();
+    let Ok(document) = parse_xml(ncx) else {
+        return titles;
+    };
+    for point in document.descendants().filter(|n| n.tag_name().name() == "navPoint") {
+        let title = child(point, "navLabel").and_then(|label| child(label, "text")).and_then(element_text);
+        let src = child(point, "content").and_then(|content| content.attribute("src"));
+        if let (Some(title), Some(src)) = (title, src) {
+            titles.entry(resolve(ncx_path, src)).or_insert(title);
+        }
+    }
+    titles
+}
+
+pub fn parse(bytes: &[u8]) -> AppResult<Book> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| invalid(&e.to_string()))?;
+
+    // The container points at the package document (OPF)
+    let container = read_entry(&mut archive, CONTAINER_FILE)?;
+    let container = parse_xml(&container)?;
+    let opf_path = container
+        .descendants()
+        .find(|n| n.tag_name().name() == "rootfile")
+        .and_then(|n| n.attribute("full-path"))
+        .ok_or_else(|| invalid("The container has no rootfile"))?
+        .to_string();
+
+    let opf = read_entry(&mut archive, &opf_path)?;
+    let opf = parse_xml(&opf)?;
+    let package = opf.root_element();
+
+    let metadata = child(package, "metadata");
+    let meta = |name: &str| {
+        metadata.and_then(|m| m.descendants().find(|n| n.tag_name().name() == name)).and_then(element_text)
+    };
+    let mut book = Book {
+        title: meta("title"),
+        author: meta("creator"),
+        chapters: Vec::new(),
+    };
+
+    let mut manifest = HashMap::new();
+    if let Some(items) = child(package, "manifest") {
+        for item in items.children().filter(|n| n.tag_name().name() == "item") {
+            if let (Some(id), Some(href)) = (item.attribute("id"), item.attribute("href")) {
+                manifest.insert(id.to_string(), ManifestItem {
+                    href: resolve(&opf_path, href),
+                    media_type: item.attribute("media-type").unwrap_or_default().to_string(),
+                    properties: item.attribute("properties").unwrap_or_default().to_string(),
+                });
+            }
+        }
+    }
+    let spine = child(package, "spine").ok_or_else(|| invalid("The package has no spine"))?;
+
+    let mut titles = HashMap::new();
+    if let Some(nav) = manifest.values().find(|item| item.properties.split_whitespace().any(|p| p == "nav")) {
+        if let Ok(content) = read_entry(&mut archive, &nav.href) {
+            titles = nav_titles(&content, &nav.href);
+        }
+    }
+    if titles.is_empty() {
+        if let Some(ncx) = spine.attribute("toc").and_then(|id| manifest.get(id)) {
+            if let Ok(content) = read_entry(&mut archive, &ncx.href) {
+                titles = ncx_titles(&content, &ncx.href);
+            }
+        }
+    }
+
+    for itemref in spine.children().filter(|n| n.tag_name().name() == "itemref") {
+        // Non-linear documents are notes or pop-ups, not part of the reading order
+        if itemref.attribute("linear") == Some("no") {
+            continue;
+        }
+        let Some(item) = itemref.attribute("idref").and_then(|id| manifest.get(id)) else {
+            continue;
+        };
+        if !item.media_type.contains("html") {
+            continue;
+        }
+
+        let document = read_entry(&mut archive, &item.href)?;
+        let content = html::to_text(&document);
+        // Covers and image-only pages have nothing to read
+        if texts::token_count(&content) == 0 {
+            continue;
+        }
+
+        book.chapters.push(Chapter {
+            title: titles.get(&item.href).cloned().or_else(|| html::first_heading(&document)),
+            content,
+        });
+    }
+
+    if book.chapters.is_empty() {
+        return Err(invalid("The book has no chapters with text"));
+    }
+    Ok(book)
+}
+
+pub async fn import(pool: &SqlitePool, path: &Path, now: DateTime<Utc>) -> AppResult<ImportReport> {
+    let file = path.to_path_buf();
+    let book = import::blocking(move || parse(&import::read_file(&file)?)).await?;
+    let title = book.title.clone().unwrap_or_else(|| import::file_stem(path));
+
+    let chapters = book
+        .chapters
+        .into_iter()
+        .enumerate()
+        .map(|(i, chapter)| {
+            let chapter_title = chapter.title.unwrap_or_else(|| format!("{} ({})", title, i + 1));
+            (chapter_title, chapter.content)
+        })
+        .collect();
+
+    let metadata = TextMetadata {
+        source: import::file_name(path),
+        author: book.author,
+        ..Default::default()
+    };
+    import::create_collection(pool, &title, None, &metadata, chapters, now).await
+}
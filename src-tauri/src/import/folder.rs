@@ -0,0 +1,267 @@
+// Folders of lesson files: every file in a supported format, subfolders
+// included, becomes a text of one collection. Texts with the same content as
+// one already in the library (or earlier in the folder) are skipped, so a
+// folder can be imported again after files were added to it.
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use crate::error::{AppError, AppResult};
+use crate::import::{self, document, epub, pdf, subtitles, ImportedText};
+use crate::models::{Collection, TextMetadata};
+use crate::repo;
+use crate::repo::timestamps::SentenceTimestamp;
+
+const EXTENSIONS: &[&str] = &[
+    "txt", "text", "md", "markdown", "html", "htm", "xhtml", "srt", "vtt", "pdf", "epub",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Imported,
+    Duplicate,
+    Failed,
+}
+
+// Sent after each file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FolderProgress {
+    pub current: usize, // Files done, this one included
+    pub total: usize,
+    pub path: String,
+    pub status: FileStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SkippedFile {
+    pub path: String,
+    pub status: FileStatus,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FolderReport {
+    pub collection: Option<Collection>, // None if no text was added to a new collection
+    pub texts: Vec<ImportedText>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+// What a file holds, before it is added
+struct FileText {
+    title: String,
+    content: String,
+    author: Option<String>,
+    timestamps: Vec<SentenceTimestamp>,
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+// Supported files of the folder, in file name order. Hidden files and
+// folders (.git, .DS_Store, ...) are left out.
+pub fn scan(dir: &Path) -> AppResult<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Err(AppError::invalid_input(format!("{} is not a folder", dir.display())));
+    }
+
+    let mut files = Vec::new();
+    let entries = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'));
+    for entry in entries {
+        let entry = entry.map_err(|e| AppError::invalid_input(format!("Failed to read {}: {}", dir.display(), e)))?;
+        if entry.file_type().is_file() && EXTENSIONS.contains(&extension(entry.path()).as_str()) {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+// Same content, ignoring the whitespace around it
+fn content_hash(content: &str) -> String {
+    Sha256::digest(content.trim().as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+async fn library_hashes(pool: &SqlitePool) -> AppResult<HashSet<String>> {
+    let contents: Vec<String> = sqlx::query_scalar("SELECT content FROM texts").fetch_all(pool).await?;
+    Ok(contents.iter().map(|content| content_hash(content)).collect())
+}
+
+// The texts of a file, titled the way the file titles itself or after its name
+fn read_texts(path: &Path) -> AppResult<Vec<FileText>> {
+    let bytes = import::read_file(path)?;
+    let stem = import::file_stem(path);
+    let file_text = |title: Option<String>, content: String, author: Option<String>| FileText {
+        title: title.unwrap_or_else(|| stem.clone()),
+        content,
+        author,
+        timestamps: Vec::new(),
+    };
+
+    let texts = match extension(path).as_str() {
+        "epub" => {
+            let book = epub::parse(&bytes)?;
+            let title = book.title.unwrap_or_else(|| stem.clone());
+            book.chapters
+                .into_iter()
+                .enumerate()
+                .map(|(i, chapter)| FileText {
+                    title: chapter.title.unwrap_or_else(|| format!("{} ({})", title, i + 1)),
+                    content: chapter.content,
+                    author: book.author.clone(),
+                    timestamps: Vec::new(),
+                })
+                .collect()
+        }
+        "pdf" => {
+            let info = pdf::inspect(&bytes)?;
+            let pages = pdf::clean_pages(&pdf::extract_pages(&bytes)?);
+            vec![file_text(info.title, pdf::pages_text(&pages), info.author)]
+        }
+        "srt" | "vtt" => {
+            let transcript = subtitles::transcript(&subtitles::parse(&import::decode_text(&bytes))?);
+            vec![FileText {
+                timestamps: transcript.timestamps,
+                ..file_text(None, transcript.content, None)
+            }]
+        }
+        _ => {
+            let parsed = document::parse(&import::decode_text(&bytes), document::Format::from_path(path)?);
+            vec![file_text(parsed.title, parsed.content, parsed.author)]
+        }
+    };
+    Ok(texts)
+}
+
+// Adds the texts of one file that are not in the library yet, all of them or
+// none. Without a `collection`, one named `new_name` is created with the
+// first text.
+async fn import_file(
+    pool: &SqlitePool,
+    path: &Path,
+    collection: &mut Option<Collection>,
+    new_name: &str,
+    hashes: &mut HashSet<String>,
+    now: DateTime<Utc>,
+) -> AppResult<Vec<ImportedText>> {
+    let file = path.to_path_buf();
+    let texts: Vec<FileText> = import::blocking(move || read_texts(&file))
+        .await?
+        .into_iter()
+        .filter(|text| repo::texts::token_count(&text.content) > 0)
+        .collect();
+    if texts.is_empty() {
+        return Err(AppError::invalid_data("The file has no text", path.display().to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut new_collection = None;
+    let mut added = HashSet::new();
+    let mut imported = Vec::new();
+    for text in texts {
+        let hash = content_hash(&text.content);
+        if hashes.contains(&hash) || !added.insert(hash) {
+            continue;
+        }
+        let collection_id = match collection.as_ref().or(new_collection.as_ref()) {
+            Some(collection) => collection.id,
+            None => new_collection.insert(repo::collections::create_in(&mut tx, new_name, None).await?).id,
+        };
+
+        let metadata = TextMetadata {
+            source: import::file_name(path),
+            author: text.author,
+            ..Default::default()
+        };
+        let created = repo::texts::create_with_metadata_in(&mut tx, &text.title, &text.content, &metadata, now).await?;
+        repo::collections::set_text_collection_in(&mut tx, created.id, Some(collection_id)).await?;
+        if !text.timestamps.is_empty() {
+            repo::timestamps::save_in(&mut tx, created.id, &text.timestamps).await?;
+        }
+        imported.push(ImportedText { id: created.id, title: created.title });
+    }
+    tx.commit().await?;
+
+    hashes.extend(added);
+    if new_collection.is_some() {
+        *collection = new_collection;
+    }
+    Ok(imported)
+}
+
+// Imports every supported file of `dir` into the collection, or into a new
+// collection named after the folder. A file that fails is reported and the
+// import goes on with the next one. The new collection is only created once a
+// file has text to add.
+pub async fn import(
+    pool: &SqlitePool,
+    dir: &Path,
+    collection_id: Option<i64>,
+    now: DateTime<Utc>,
+    on_progress: &mut (dyn FnMut(&FolderProgress) + Send),
+) -> AppResult<FolderReport> {
+    let files = scan(dir)?;
+    let mut collection = match collection_id {
+        Some(id) => Some(repo::collections::get(pool, id).await?),
+        None => None,
+    };
+    let new_name = repo::collections::available_name(pool, &import::file_stem(dir)).await?;
+
+    let mut hashes = library_hashes(pool).await?;
+    let mut texts = Vec::new();
+    let mut skipped = Vec::new();
+    for (i, path) in files.iter().enumerate() {
+        let relative = path.strip_prefix(dir).unwrap_or(path).display().to_string();
+        let status = match import_file(pool, path, &mut collection, &new_name, &mut hashes, now).await {
+            Ok(imported) if imported.is_empty() => {
+                skipped.push(SkippedFile {
+                    path: relative.clone(),
+                    status: FileStatus::Duplicate,
+                    reason: "Already in the library".to_string(),
+                });
+                FileStatus::Duplicate
+            }
+            Ok(imported) => {
+                texts.extend(imported);
+                FileStatus::Imported
+            }
+            Err(e) => {
+                warn!("Failed to import {}: {}", path.display(), e);
+                skipped.push(SkippedFile {
+                    path: relative.clone(),
+                    status: FileStatus::Failed,
+                    reason: e.to_string(),
+                });
+                FileStatus::Failed
+            }
+        };
+        on_progress(&FolderProgress {
+            current: i + 1,
+            total: files.len(),
+            path: relative,
+            status,
+        });
+    }
+
+    let collection = match collection {
+        Some(collection) => Some(repo::collections::get(pool, collection.id).await?),
+        None => None,
+    };
+    info!(
+        "Imported {} texts from {} into collection {} ({} files skipped)",
+        texts.len(),
+        dir.display(),
+        collection.as_ref().map_or("(none)", |c| c.name.as_str()),
+        skipped.len()
+    );
+    Ok(FolderReport { collection, texts, skipped })
+}
@@ -0,0 +1,302 @@
+// HTML and XHTML to plain text: one paragraph per block element, separated
+// by blank lines, whitespace inside a paragraph collapsed.
+//
+// Saved web pages also get their article picked out of the navigation, ads
+// and comments around it (see `article`), with heuristics along the lines of
+// Readability: paragraphs vote for the elements containing them, and
+// elements full of links or with class names like "sidebar" lose.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+
+// Elements that start and end a paragraph
+const BLOCK_TAGS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "dd", "div", "dl", "dt", "figcaption",
+    "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "ol",
+    "p", "pre", "section", "table", "td", "th", "tr", "ul",
+];
+
+// Elements whose content is never text to read
+const SKIPPED_TAGS: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "math", "title", "iframe", "object",
+];
+
+// Elements around an article that are never part of it
+const BOILERPLATE_TAGS: &[&str] = &[
+    "nav", "aside", "footer", "header", "form", "button", "select", "menu", "dialog",
+];
+
+// Elements that are never boilerplate, whatever their class
+const CONTAINER_TAGS: &[&str] = &["html", "body", "main", "article"];
+
+// Paragraphs shorter than this (in characters) are captions, bylines and the like
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+
+lazy_static! {
+    // Class and id of navigation, ads, comments, ...
+    static ref UNLIKELY_REGEX: Regex = Regex::new(
+        r"(?i)nav|menu|sidebar|footer|banner|comment|share|social|related|promo|advert|sponsor|cookie|newsletter|subscribe|breadcrumb|popup|modal|widget|(^|[\s_-])ads?([\s_-]|$)"
+    ).unwrap();
+    // Class and id of article bodies
+    static ref LIKELY_REGEX: Regex = Regex::new(r"(?i)article|body|content|main|post|entry|story|text|prose").unwrap();
+    // Between the title of a page and the name of the site
+    static ref TITLE_SEPARATOR_REGEX: Regex = Regex::new(r"\s+(\||-|–|—|::|·)\s+").unwrap();
+}
+
+#[derive(Debug, Default)]
+pub struct Article {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub content: String,
+}
+
+#[derive(Default)]
+struct Paragraphs {
+    done: Vec<String>,
+    current: String,
+}
+
+impl Paragraphs {
+    // Whitespace between text nodes counts as one space, never at the start of a line
+    fn space(&mut self) {
+        if !self.current.is_empty() && !self.current.ends_with([' ', '\n']) {
+            self.current.push(' ');
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if text.starts_with(char::is_whitespace) {
+            self.space();
+        }
+        self.current.push_str(&words.join(" "));
+        if !words.is_empty() && text.ends_with(char::is_whitespace) {
+            self.space();
+        }
+    }
+
+    fn line_break(&mut self) {
+        let trimmed = self.current.trim_end().len();
+        self.current.truncate(trimmed);
+        if !self.current.is_empty() {
+            self.current.push('\n');
+        }
+    }
+
+    fn flush(&mut self) {
+        let paragraph = self.current.trim();
+        if !paragraph.is_empty() {
+            self.done.push(paragraph.to_string());
+        }
+        self.current.clear();
+    }
+}
+
+// `skip` leaves out elements with everything in them
+fn walk(element: ElementRef, paragraphs: &mut Paragraphs, skip: &dyn Fn(ElementRef) -> bool) {
+    let name = element.value().name();
+    if SKIPPED_TAGS.contains(&name) || skip(element) {
+        return;
+    }
+    let block = BLOCK_TAGS.contains(&name);
+    if block {
+        paragraphs.flush();
+    }
+
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => paragraphs.push_text(text),
+            Node::Element(e) if e.name() == "br" => paragraphs.line_break(),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    walk(child, paragraphs, skip);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if block {
+        paragraphs.flush();
+    }
+}
+
+fn paragraphs(element: ElementRef, skip: &dyn Fn(ElementRef) -> bool) -> Vec<String> {
+    let mut paragraphs = Paragraphs::default();
+    walk(element, &mut paragraphs, skip);
+    paragraphs.flush();
+    paragraphs.done
+}
+
+fn element_text(element: ElementRef) -> String {
+    paragraphs(element, &|_| false).join("\n\n")
+}
+
+// Readable text of a whole document or a fragment
+pub fn to_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    element_text(document.root_element())
+}
+
+// Text of the first h1, h2 or h3, a title for chapters without one in the table of contents
+pub fn first_heading(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("h1, h2, h3").unwrap();
+    document
+        .select(&selector)
+        .map(|heading| element_text(heading).replace('\n', " "))
+        .find(|heading| !heading.is_empty())
+}
+
+fn collapsed_text(element: ElementRef) -> String {
+    element.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn text_length(element: ElementRef) -> usize {
+    collapsed_text(element).chars().count()
+}
+
+// Share of the text that is in links
+fn link_density(element: ElementRef) -> f64 {
+    let length = text_length(element);
+    if length == 0 {
+        return 0.0;
+    }
+    let selector = Selector::parse("a").unwrap();
+    let links: usize = element.select(&selector).map(text_length).sum();
+    links as f64 / length as f64
+}
+
+fn class_and_id(element: ElementRef) -> String {
+    let e = element.value();
+    format!("{} {}", e.attr("class").unwrap_or_default(), e.id().unwrap_or_default())
+}
+
+fn is_boilerplate(element: ElementRef) -> bool {
+    let e = element.value();
+    if CONTAINER_TAGS.contains(&e.name()) {
+        return false;
+    }
+    if BOILERPLATE_TAGS.contains(&e.name()) {
+        return true;
+    }
+    let role = e.attr("role").unwrap_or_default();
+    if ["navigation", "banner", "complementary", "contentinfo", "dialog"].contains(&role) {
+        return true;
+    }
+    let names = class_and_id(element);
+    UNLIKELY_REGEX.is_match(&names) && !LIKELY_REGEX.is_match(&names)
+}
+
+// Bonus for elements named like an article, penalty for the rest of the page
+fn class_weight(element: ElementRef) -> f64 {
+    let names = class_and_id(element);
+    let mut weight = 0.0;
+    if LIKELY_REGEX.is_match(&names) {
+        weight += 25.0;
+    }
+    if UNLIKELY_REGEX.is_match(&names) {
+        weight -= 25.0;
+    }
+    if ["article", "main"].contains(&element.value().name()) {
+        weight += 10.0;
+    }
+    weight
+}
+
+// The element holding the article: each paragraph scores its parent, and
+// half as much its grandparent, more for longer paragraphs with more commas
+fn best_candidate(document: &Html) -> Option<ElementRef<'_>> {
+    let selector = Selector::parse("p, pre").unwrap();
+    let mut scores: HashMap<_, f64> = HashMap::new();
+
+    for paragraph in document.select(&selector) {
+        let in_boilerplate = std::iter::successors(Some(paragraph), |e| e.parent().and_then(ElementRef::wrap))
+            .any(|e| is_boilerplate(e) || SKIPPED_TAGS.contains(&e.value().name()));
+        let text = collapsed_text(paragraph);
+        let length = text.chars().count();
+        if in_boilerplate || length < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+
+        let commas = text.matches([',', '，', '、']).count();
+        let score = 1.0 + commas as f64 + (length / 100).min(3) as f64;
+        let parent = paragraph.parent().and_then(ElementRef::wrap);
+        let grandparent = parent.and_then(|p| p.parent()).and_then(ElementRef::wrap);
+        if let Some(parent) = parent {
+            *scores.entry(parent.id()).or_insert_with(|| class_weight(parent)) += score;
+        }
+        if let Some(grandparent) = grandparent {
+            *scores.entry(grandparent.id()).or_insert_with(|| class_weight(grandparent)) += score / 2.0;
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = document.tree.get(id).and_then(ElementRef::wrap)?;
+            Some((element, score * (1.0 - link_density(element))))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(element, _)| element)
+}
+
+// Title of the page without the site name, as in "Title | Site"
+fn page_title(document: &Html) -> Option<String> {
+    let meta = Selector::parse(r#"meta[property="og:title"]"#).unwrap();
+    if let Some(title) = document.select(&meta).filter_map(|m| m.value().attr("content")).map(str::trim).find(|t| !t.is_empty()) {
+        return Some(title.to_string());
+    }
+
+    // A page with a single h1 has its title in it
+    let h1 = Selector::parse("h1").unwrap();
+    let headings: Vec<String> = document.select(&h1).map(collapsed_text).filter(|h| !h.is_empty()).collect();
+    if let [heading] = &headings[..] {
+        return Some(heading.clone());
+    }
+
+    let title = Selector::parse("title").unwrap();
+    let title = document.select(&title).map(collapsed_text).find(|t| !t.is_empty())?;
+    TITLE_SEPARATOR_REGEX
+        .split(&title)
+        .map(str::trim)
+        .max_by_key(|part| part.chars().count())
+        .map(str::to_string)
+}
+
+fn page_author(document: &Html) -> Option<String> {
+    let selector = Selector::parse(r#"meta[name="author"]"#).unwrap();
+    document
+        .select(&selector)
+        .filter_map(|m| m.value().attr("content"))
+        .map(str::trim)
+        .find(|author| !author.is_empty())
+        .map(str::to_string)
+}
+
+// The main text of a saved web page, with its title and author if the page has them
+pub fn article(html: &str) -> Article {
+    let document = Html::parse_document(html);
+    let title = page_title(&document);
+
+    let root = best_candidate(&document).unwrap_or_else(|| document.root_element());
+    // Inside the article, link lists (tags, "read more", ...) are boilerplate too
+    let skip = |element: ElementRef| {
+        element.id() != root.id()
+            && (is_boilerplate(element)
+                || (BLOCK_TAGS.contains(&element.value().name()) && link_density(element) > 0.5))
+    };
+    let mut paragraphs = paragraphs(root, &skip);
+    // The heading above the text repeats the title
+    if paragraphs.first().is_some_and(|p| Some(p) == title.as_ref()) {
+        paragraphs.remove(0);
+    }
+
+    Article {
+        title,
+        author: page_author(&document),
+        content: paragraphs.join("\n\n"),
+    }
+}
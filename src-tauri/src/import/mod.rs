@@ -0,0 +1,113 @@
+// Importers that turn files into texts. Each format parses into plain text
+// first and then goes through repo::texts like a pasted text, so words are
+// linked the same way.
+
+pub mod document;
+pub mod epub;
+pub mod folder;
+pub mod html;
+pub mod pdf;
+pub mod subtitles;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Collection, TextMetadata};
+use crate::repo;
+
+// Nothing we import is anywhere near this, it protects against runaway files
+pub const MAX_FILE_SIZE: u64 = 200 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportedText {
+    pub id: i64,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportReport {
+    pub collection: Option<Collection>,
+    pub texts: Vec<ImportedText>,
+}
+
+pub fn read_file(path: &Path) -> AppResult<Vec<u8>> {
+    let file = std::fs::File::open(path).map_err(AppError::io("Failed to open the file"))?;
+    let mut bytes = Vec::new();
+    file.take(MAX_FILE_SIZE + 1)
+        .read_to_end(&mut bytes)
+        .map_err(AppError::io("Failed to read the file"))?;
+    if bytes.len() as u64 > MAX_FILE_SIZE {
+        return Err(AppError::invalid_input(format!(
+            "{} is larger than {} MB",
+            path.display(),
+            MAX_FILE_SIZE / 1024 / 1024
+        )));
+    }
+    Ok(bytes)
+}
+
+// Parsing a large file takes seconds, it runs off the async runtime
+pub async fn blocking<T, F>(parse: F) -> AppResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(parse)
+        .await
+        .map_err(|e| AppError::internal("The import task failed", e))?
+}
+
+// Text files are mostly UTF-8, older ones are often in a legacy encoding
+pub fn decode_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(content) => content.to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(), // Latin-1
+    }
+}
+
+// File name without the folder, recorded as the source of imported texts
+pub fn file_name(path: &Path) -> Option<String> {
+    path.file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+// File name without the extension, the title when the file has none
+pub fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+// Creates a collection holding `texts` (title and content) in order. If one
+// fails nothing of the import is kept.
+pub async fn create_collection(
+    pool: &SqlitePool,
+    name: &str,
+    description: Option<&str>,
+    metadata: &TextMetadata,
+    texts: Vec<(String, String)>,
+    now: DateTime<Utc>,
+) -> AppResult<ImportReport> {
+    let name = repo::collections::available_name(pool, name).await?;
+    let mut tx = pool.begin().await?;
+    let collection = repo::collections::create_in(&mut tx, &name, description).await?;
+
+    let mut imported = Vec::with_capacity(texts.len());
+    for (title, content) in texts {
+        let text = repo::texts::create_with_metadata_in(&mut tx, &title, &content, metadata, now).await?;
+        repo::collections::set_text_collection_in(&mut tx, text.id, Some(collection.id)).await?;
+        imported.push(ImportedText { id: text.id, title: text.title });
+    }
+    tx.commit().await?;
+
+    info!("Imported {} texts into collection {}", imported.len(), name);
+    Ok(ImportReport {
+        collection: Some(repo::collections::get(pool, collection.id).await?),
+        texts: imported,
+    })
+}
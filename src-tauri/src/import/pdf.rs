@@ -0,0 +1,300 @@
+// PDF documents: the text of each page is extracted in reading order, then
+// cleaned of what only makes sense on paper: running headers and footers,
+// page numbers and words hyphenated at the end of a line.
+//
+// A PDF becomes one text per section, a range of pages. Sections are the
+// chapters of the outline (bookmarks) unless the user picks page ranges;
+// pages before the first chapter (cover, contents, ...) are left out.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use lopdf::{Document, Object};
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::info;
+
+use crate::error::{AppError, AppResult};
+use crate::import::{self, ImportReport, ImportedText};
+use crate::models::TextMetadata;
+use crate::repo::texts;
+
+// Lines at the top and bottom of a page that may be a running header or footer
+const MARGIN_LINES: usize = 2;
+
+lazy_static! {
+    // "12", "- 12 -", "Seite 12", "Page 12 of 80", "xii"
+    static ref PAGE_NUMBER_REGEX: Regex = Regex::new(
+        r"(?i)^(page|seite|p\.|s\.)?\s*[-–—]?\s*(\d+|x{0,3}(ix|iv|v?i{0,3}))\s*[-–—]?\s*((/|of|von|de|sur)\s*\d+)?$"
+    ).unwrap();
+    static ref SENTENCE_END_REGEX: Regex = Regex::new(r#"[.!?…:]["'»”’)\]]*$"#).unwrap();
+}
+
+// Pages are numbered from 1, both ends included
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PdfSection {
+    pub title: Option<String>,
+    pub start_page: usize,
+    pub end_page: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PdfInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub page_count: usize,
+    pub chapters: Vec<PdfSection>, // Empty if the document has no outline
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::invalid_data("Not a readable PDF file", message)
+}
+
+fn load(bytes: &[u8]) -> AppResult<Document> {
+    let document = Document::load_mem(bytes).map_err(|e| invalid(e.to_string()))?;
+    if document.is_encrypted() {
+        return Err(AppError::invalid_input("Password protected PDFs cannot be imported"));
+    }
+    Ok(document)
+}
+
+// An entry of the document information dictionary
+fn info_entry(document: &Document, key: &[u8]) -> Option<String> {
+    let info = match document.trailer.get(b"Info").ok()? {
+        Object::Reference(id) => document.get_dictionary(*id).ok()?,
+        Object::Dictionary(info) => info,
+        _ => return None,
+    };
+    let value = lopdf::decode_text_string(info.get(key).ok()?).ok()?;
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!value.is_empty()).then_some(value)
+}
+
+// Top level entries of the outline, each running until the next one
+fn chapters(document: &Document, page_count: usize) -> Vec<PdfSection> {
+    let Ok(toc) = document.get_toc() else {
+        return Vec::new();
+    };
+    let Some(top_level) = toc.toc.iter().map(|entry| entry.level).min() else {
+        return Vec::new();
+    };
+
+    let mut starts: Vec<(usize, String)> = Vec::new();
+    for entry in toc.toc.iter().filter(|entry| entry.level == top_level) {
+        let title = entry.title.split_whitespace().collect::<Vec<_>>().join(" ");
+        // Entries pointing at the same page as the previous one add nothing
+        if starts.last().is_some_and(|(page, _)| *page >= entry.page) || entry.page > page_count {
+            continue;
+        }
+        starts.push((entry.page, title));
+    }
+
+    let mut chapters = Vec::with_capacity(starts.len());
+    for (i, (start_page, title)) in starts.iter().enumerate() {
+        let end_page = starts.get(i + 1).map(|(next, _)| next - 1).unwrap_or(page_count);
+        chapters.push(PdfSection {
+            title: (!title.is_empty()).then(|| title.clone()),
+            start_page: *start_page,
+            end_page,
+        });
+    }
+    chapters
+}
+
+pub fn inspect(bytes: &[u8]) -> AppResult<PdfInfo> {
+    let document = load(bytes)?;
+    let page_count = document.get_pages().len();
+    Ok(PdfInfo {
+        title: info_entry(&document, b"Title"),
+        author: info_entry(&document, b"Author"),
+        page_count,
+        chapters: chapters(&document, page_count),
+    })
+}
+
+// Raw text of every page. The extractor panics on some malformed files,
+// which must not take the app down.
+pub fn extract_pages(bytes: &[u8]) -> AppResult<Vec<String>> {
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| invalid("The text of the document could not be extracted".to_string()))?
+        .map_err(|e| invalid(e.to_string()))
+}
+
+// Lines that look the same on most pages, digits aside, like "Chapter 3 · 41"
+fn line_pattern(line: &str) -> String {
+    line.to_lowercase().chars().map(|c| if c.is_ascii_digit() { '#' } else { c }).collect()
+}
+
+// Indices of the first and last non-empty lines of a page
+fn margin_lines(lines: &[&str]) -> Vec<usize> {
+    let filled: Vec<usize> = (0..lines.len()).filter(|&i| !lines[i].is_empty()).collect();
+    let mut margins: Vec<usize> = filled.iter().take(MARGIN_LINES).copied().collect();
+    margins.extend(filled.iter().rev().take(MARGIN_LINES).copied());
+    margins.sort_unstable();
+    margins.dedup();
+    margins
+}
+
+// The lines of every page, without page numbers and running headers and footers
+pub fn clean_pages(pages: &[String]) -> Vec<Vec<String>> {
+    let pages: Vec<Vec<&str>> = pages.iter().map(|page| page.lines().map(str::trim).collect()).collect();
+
+    // On how many pages each margin line appears
+    let mut repeated: HashMap<String, usize> = HashMap::new();
+    for lines in &pages {
+        let mut patterns: Vec<String> = margin_lines(lines).into_iter().map(|i| line_pattern(lines[i])).collect();
+        patterns.sort_unstable();
+        patterns.dedup();
+        for pattern in patterns {
+            *repeated.entry(pattern).or_default() += 1;
+        }
+    }
+    let running = |line: &str| {
+        let count = repeated.get(&line_pattern(line)).copied().unwrap_or_default();
+        count >= 2 && count * 2 >= pages.len()
+    };
+
+    pages
+        .iter()
+        .map(|lines| {
+            let margins = margin_lines(lines);
+            let mut kept: Vec<String> = lines
+                .iter()
+                .enumerate()
+                .filter(|(i, line)| !margins.contains(i) || !(PAGE_NUMBER_REGEX.is_match(line) || running(line)))
+                .map(|(_, line)| line.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect();
+            // The space that was around them is not a paragraph break
+            let start = kept.iter().position(|line| !line.is_empty()).unwrap_or(kept.len());
+            let end = kept.iter().rposition(|line| !line.is_empty()).map_or(start, |i| i + 1);
+            kept.truncate(end);
+            kept.drain(..start);
+            kept
+        })
+        .collect()
+}
+
+// Appends a line to a paragraph, joining words split by a hyphen at the end
+// of the line ("Ent-" + "scheidung"). A capital after the hyphen is a
+// compound ("Nord-" + "Süd"), which keeps it.
+fn append_line(paragraph: &mut String, line: &str) {
+    if paragraph.is_empty() {
+        paragraph.push_str(line);
+        return;
+    }
+    let hyphenated = paragraph.strip_suffix('-').is_some_and(|rest| rest.ends_with(char::is_alphabetic));
+    if hyphenated {
+        if line.starts_with(char::is_lowercase) {
+            paragraph.pop();
+        }
+    } else {
+        paragraph.push(' ');
+    }
+    paragraph.push_str(line);
+}
+
+// Text of `pages` (cleaned lines): blank lines end paragraphs, and so does
+// the end of a page if its last line ends a sentence
+pub fn pages_text(pages: &[Vec<String>]) -> String {
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    for lines in pages {
+        for line in lines {
+            if line.is_empty() {
+                if !current.is_empty() {
+                    paragraphs.push(std::mem::take(&mut current));
+                }
+            } else {
+                append_line(&mut current, line);
+            }
+        }
+        if SENTENCE_END_REGEX.is_match(&current) {
+            paragraphs.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current);
+    }
+    paragraphs.join("\n\n")
+}
+
+fn check_sections(sections: &[PdfSection], page_count: usize) -> AppResult<()> {
+    if sections.is_empty() {
+        return Err(AppError::invalid_input("Choose at least one range of pages"));
+    }
+    for section in sections {
+        if section.start_page < 1 || section.start_page > section.end_page || section.end_page > page_count {
+            return Err(AppError::invalid_input(format!(
+                "Pages {}-{} are not in the document, it has {} pages",
+                section.start_page, section.end_page, page_count
+            )));
+        }
+    }
+    Ok(())
+}
+
+// One text per section: the chapters of the outline if `sections` is None,
+// or the whole document if it has no outline. More than one text go into a
+// collection named after the document.
+pub async fn import(
+    pool: &SqlitePool,
+    path: &Path,
+    sections: Option<Vec<PdfSection>>,
+    now: DateTime<Utc>,
+) -> AppResult<ImportReport> {
+    let file = path.to_path_buf();
+    let (info, sections, pages) = import::blocking(move || {
+        let bytes = import::read_file(&file)?;
+        let info = inspect(&bytes)?;
+        let sections = match sections {
+            Some(sections) => sections,
+            None if !info.chapters.is_empty() => info.chapters.clone(),
+            None => vec![PdfSection { title: None, start_page: 1, end_page: info.page_count }],
+        };
+        check_sections(&sections, info.page_count)?;
+        let pages = clean_pages(&extract_pages(&bytes)?);
+        Ok((info, sections, pages))
+    })
+    .await?;
+    let name = info.title.clone().unwrap_or_else(|| import::file_stem(path));
+    let metadata = TextMetadata {
+        source: import::file_name(path),
+        author: info.author.clone(),
+        ..Default::default()
+    };
+
+    let mut parts = Vec::new();
+    for section in sections {
+        let content = pages_text(pages.get(section.start_page - 1..section.end_page).unwrap_or_default());
+        // Scanned pages have no text to extract
+        if texts::token_count(&content) == 0 {
+            continue;
+        }
+        let title = section.title.unwrap_or_else(|| {
+            if section.start_page == 1 && section.end_page == info.page_count {
+                name.clone()
+            } else if section.start_page == section.end_page {
+                format!("{} (p. {})", name, section.start_page)
+            } else {
+                format!("{} (pp. {}-{})", name, section.start_page, section.end_page)
+            }
+        });
+        parts.push((title, content));
+    }
+
+    match parts.len() {
+        0 => Err(invalid("The pages have no text, they may be scanned images".to_string())),
+        1 => {
+            let (title, content) = parts.remove(0);
+            let text = texts::create_with_metadata(pool, &title, &content, &metadata, now).await?;
+            info!("Imported {} as text {}", path.display(), text.id);
+            Ok(ImportReport {
+                collection: None,
+                texts: vec![ImportedText { id: text.id, title: text.title }],
+            })
+        }
+        _ => import::create_collection(pool, &name, None, &metadata, parts, now).await,
+    }
+}
@@ -0,0 +1,228 @@
+// SubRip (.srt) and WebVTT (.vtt) subtitles: the cues are cleaned of their
+// formatting and merged into paragraphs, a pause in the dialogue starts a new
+// one. Every sentence keeps the time of the cues it came from, see
+// repo::timestamps.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use sqlx::sqlite::SqlitePool;
+use std::path::Path;
+use tracing::info;
+
+use crate::error::{AppError, AppResult};
+use crate::import::{self, ImportReport, ImportedText};
+use crate::models::TextMetadata;
+use crate::pagination;
+use crate::repo::texts;
+use crate::repo::timestamps::{self, SentenceTimestamp};
+
+// A silence longer than this starts a new paragraph
+const PARAGRAPH_GAP_MS: i64 = 2000;
+// Past this many words a paragraph ends with the next sentence
+const PARAGRAPH_WORDS: i64 = 80;
+
+lazy_static! {
+    // <i>, <font color=...>, <c.yellow>, <v Speaker>, <00:00:01.000> and their closing tags
+    static ref TAG_REGEX: Regex = Regex::new(r"<[^>]*>").unwrap();
+    // Positioning and styling overrides carried over from ASS, like {\an8}
+    static ref OVERRIDE_REGEX: Regex = Regex::new(r"\{\\[^}]*\}").unwrap();
+    // Lines that only describe sounds: [music], (laughs), ♪ ... ♪
+    static ref ANNOTATION_REGEX: Regex = Regex::new(r"^(\[[^\]]*\]|\([^)]*\)|[♪♫#\s]*)$").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub text: String, // Cleaned, on one line
+}
+
+#[derive(Debug, Default)]
+pub struct Transcript {
+    pub content: String,
+    pub timestamps: Vec<SentenceTimestamp>,
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::invalid_data("Not a valid subtitle file", message)
+}
+
+// `01:02:03,456` (SubRip) or `01:02:03.456` / `02:03.456` (WebVTT), in milliseconds
+fn parse_time(time: &str) -> Option<i64> {
+    let (clock, fraction) = time.trim().split_once([',', '.'])?;
+    let parts: Vec<i64> = clock.split(':').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+    let seconds = match parts[..] {
+        [h, m, s] => h * 3600 + m * 60 + s,
+        [m, s] => m * 60 + s,
+        _ => return None,
+    };
+    if fraction.is_empty() || fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let millis: i64 = format!("{:0<3}", fraction).parse().ok()?;
+    Some(seconds * 1000 + millis)
+}
+
+// `start --> end`, followed by cue settings in WebVTT
+fn parse_timing(line: &str) -> Option<(i64, i64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_time(start)?, parse_time(end)?))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lrm;", "")
+        .replace("&rlm;", "")
+        .replace("&amp;", "&")
+}
+
+// Text of a cue without formatting, sound descriptions or dialogue dashes
+fn clean_cue(lines: &[&str]) -> String {
+    let mut cleaned = Vec::new();
+    for line in lines {
+        let line = OVERRIDE_REGEX.replace_all(line, "");
+        let line = decode_entities(&TAG_REGEX.replace_all(&line, ""));
+        let line = line.trim().trim_start_matches(['-', '–', '—']).trim();
+        if ANNOTATION_REGEX.is_match(line) {
+            continue;
+        }
+        cleaned.push(line.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    cleaned.join(" ")
+}
+
+// Cues in order of time, empty ones left out
+pub fn parse(content: &str) -> AppResult<Vec<Cue>> {
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n").replace('\r', "\n");
+    let mut blocks = content.split("\n\n").map(str::trim).filter(|b| !b.is_empty()).peekable();
+
+    let webvtt = blocks.peek().is_some_and(|b| b.starts_with("WEBVTT"));
+    if webvtt {
+        blocks.next();
+    }
+
+    let mut cues = Vec::new();
+    for block in blocks {
+        let lines: Vec<&str> = block.lines().collect();
+        // The timing line comes after the optional cue number (SubRip) or identifier (WebVTT)
+        let Some(timing) = lines.iter().take(2).position(|line| line.contains("-->")) else {
+            if webvtt {
+                continue; // NOTE, STYLE and REGION blocks
+            }
+            return Err(invalid(format!("Cue without timing: {}", lines[0])));
+        };
+        let (start_ms, end_ms) =
+            parse_timing(lines[timing]).ok_or_else(|| invalid(format!("Invalid timing: {}", lines[timing])))?;
+
+        let text = clean_cue(&lines[timing + 1..]);
+        if !text.is_empty() {
+            cues.push(Cue { start_ms, end_ms, text });
+        }
+    }
+
+    if cues.is_empty() {
+        return Err(invalid("The file has no subtitles".to_string()));
+    }
+    cues.sort_by_key(|cue| cue.start_ms);
+    Ok(cues)
+}
+
+fn ends_sentence(text: &str) -> bool {
+    text.trim_end_matches(['"', '\'', '»', '”', '’', ')', ']']).ends_with(['.', '!', '?', '…'])
+}
+
+// Content made of the cues and the time of each of its sentences
+pub fn transcript(cues: &[Cue]) -> Transcript {
+    let mut content = String::new();
+    let mut paragraphs = Vec::new(); // Byte ranges
+    let mut cue_ranges = Vec::with_capacity(cues.len());
+
+    let mut paragraph_start = 0;
+    let mut paragraph_words = 0;
+    for (i, cue) in cues.iter().enumerate() {
+        if i > 0 {
+            let previous = &cues[i - 1];
+            let pause = cue.start_ms - previous.end_ms > PARAGRAPH_GAP_MS;
+            let long = paragraph_words >= PARAGRAPH_WORDS && ends_sentence(&previous.text);
+            if pause || long {
+                paragraphs.push((paragraph_start, content.len()));
+                content.push_str("\n\n");
+                paragraph_start = content.len();
+                paragraph_words = 0;
+            } else {
+                content.push(' ');
+            }
+        }
+        let start = content.len();
+        content.push_str(&cue.text);
+        cue_ranges.push((start, content.len()));
+        paragraph_words += texts::token_count(&cue.text);
+    }
+    paragraphs.push((paragraph_start, content.len()));
+
+    let mut timestamps = Vec::new();
+    let mut token_offset = 0;
+    for paragraph in paragraphs {
+        for (start, end) in pagination::sentences(&content, paragraph) {
+            let token_count = texts::token_count(&content[start..end]);
+            if token_count == 0 {
+                continue;
+            }
+            // The cues the sentence overlaps, a sentence can span several and a cue hold several
+            let mut overlapping = cues
+                .iter()
+                .zip(&cue_ranges)
+                .filter(|(_, (cue_start, cue_end))| *cue_start < end && *cue_end > start)
+                .map(|(cue, _)| cue);
+            if let Some(first) = overlapping.next() {
+                let last = overlapping.next_back().unwrap_or(first);
+                timestamps.push(SentenceTimestamp {
+                    token_offset,
+                    token_count,
+                    start_ms: first.start_ms,
+                    end_ms: last.end_ms,
+                });
+            }
+            token_offset += token_count;
+        }
+    }
+
+    Transcript { content, timestamps }
+}
+
+pub async fn import(
+    pool: &SqlitePool,
+    path: &Path,
+    title: Option<&str>,
+    now: DateTime<Utc>,
+) -> AppResult<ImportReport> {
+    let cues = parse(&import::decode_text(&import::read_file(path)?))?;
+    let transcript = transcript(&cues);
+
+    let title = title
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| import::file_stem(path));
+    let metadata = TextMetadata {
+        source: import::file_name(path),
+        ..Default::default()
+    };
+    let text = texts::create_with_metadata(pool, &title, &transcript.content, &metadata, now).await?;
+    if let Err(e) = timestamps::save(pool, text.id, &transcript.timestamps).await {
+        texts::delete(pool, text.id).await.ok();
+        return Err(e);
+    }
+
+    info!("Imported {} cues from {} into text {}", cues.len(), path.display(), text.id);
+    Ok(ImportReport {
+        collection: None,
+        texts: vec![ImportedText { id: text.id, title: text.title }],
+    })
+}
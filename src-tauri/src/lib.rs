@@ -11,978 +11,831 @@ find the time to get more experience with Rust then i will come back
 and refactor the codebase.
 **********************************************************************/
 
-use tauri::Manager;
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
-use sqlx::FromRow;
-use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
 use dotenv::dotenv;
-use lazy_static::lazy_static;
-use regex::Regex;
-use chrono::Utc;
+use tauri::Manager;
 
-use base64::{Engine as _, engine::general_purpose};
-use flate2::write::GzEncoder;
-use flate2::read::GzDecoder;
-use flate2::Compression;
-use std::io::{Write, Read};
+pub use bootstrap::init_db;
+
+pub mod bootstrap;
+pub mod clock;
+pub mod dictionary;
+pub mod encryption;
+pub mod error;
+pub mod import;
+pub mod library;
+pub mod logging;
+pub mod models;
+pub mod pagination;
+pub mod profiles;
+pub mod repo;
+pub mod review;
+pub mod simulation;
+pub mod sync;
+pub mod transfer;
+
+use clock::SharedClock;
+use error::{AppError, AppResult};
+use models::{Collection, Tag, Text, TextMetadata, TextSummary, Word};
 
-lazy_static! {
-    static ref NOW: chrono::DateTime<Utc> = Utc::now();
+#[tauri::command]
+async fn generate_sync_key(state: tauri::State<'_, SqlitePool>) -> AppResult<String> {
+    transfer::build_sync_key(&state).await
 }
 
-lazy_static! {
-    // Regex to remove specific punctuation: . , " ' / [ ( ) ]
-    static ref PUNCTUATION_REGEX: Regex = Regex::new("[.,:!’?\"!“.”'’,,’’…/\\[\\](){}<>«'»-]").unwrap();
+// Split the sync key into QR codes for devices where pasting it is not an option
+#[tauri::command]
+async fn generate_sync_key_chunks(
+    state: tauri::State<'_, SqlitePool>,
+    chunk_size: Option<usize>,
+    format: sync::chunks::ImageFormat,
+) -> AppResult<Vec<sync::chunks::SyncKeyChunk>> {
+    let sync_key = transfer::build_sync_key(&state).await?;
+    let texts = sync::chunks::split_sync_key(
+        &sync_key,
+        chunk_size.unwrap_or(sync::chunks::DEFAULT_CHUNK_SIZE),
+    )?;
+    let total = texts.len();
+
+    let mut chunks = Vec::with_capacity(total);
+    for (i, text) in texts.into_iter().enumerate() {
+        let image = sync::chunks::render_chunk(&text, format)?;
+        chunks.push(sync::chunks::SyncKeyChunk {
+            index: i + 1,
+            total,
+            text,
+            image,
+        });
+    }
+
+    Ok(chunks)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Text {
-    id: i64, // SQLite INTEGER maps to i64 in Rust
-    title: String,
-    content: String,
+// Scanned chunks can arrive in any order; once nothing is missing the
+// result carries the sync key, ready for apply_sync_key
+#[tauri::command]
+async fn reassemble_sync_key_chunks(chunks: Vec<String>) -> AppResult<sync::chunks::ChunkAssembly> {
+    sync::chunks::assemble_sync_key(&chunks)
 }
 
-#[derive(Serialize, Deserialize, Debug, FromRow)]
-struct Word {
+#[tauri::command]
+async fn apply_sync_key(
+    state: tauri::State<'_, SqlitePool>, 
+    sync_key: String
+) -> AppResult<()> {
+    transfer::apply_sync_key(&state, &sync_key).await
+}
+
+// Opening a text for reading also records it as last opened
+#[tauri::command]
+async fn get_text_with_words(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
     id: i64,
-    word: String,
-    status: Option<String>, 
-    meaning: Option<String>, // Nullable field
-    stability: Option<f64>,  // Stability parameter from FSRS
-    difficulty: Option<f64>, // Difficulty parameter from FSRS
-    last_review_date: Option<String>, // Timestamp of the last review (ISO 8601)
-    next_review_date: Option<String>, // Timestamp for the next review (ISO 8601)
+) -> AppResult<(Text, Vec<Word>)> {
+    let text = repo::texts::get_with_words(&state, id).await?;
+    repo::texts::mark_opened(&state, id, clock.now()).await?;
+    Ok(text)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct TextWord {
+// Page boundaries of a text with the word counts of each page
+#[tauri::command]
+async fn get_text_pages(
+    state: tauri::State<'_, SqlitePool>,
     text_id: i64,
-    word_id: i64,
+    words_per_page: Option<usize>,
+) -> AppResult<Vec<pagination::PageSummary>> {
+    let words_per_page = pagination::check_words_per_page(words_per_page)?;
+    pagination::pages(&state, text_id, words_per_page).await
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct UserPreference {
-    id: i64,
-    country_code: String,
+// One page of a text with its words, the paged version of get_text_with_words
+#[tauri::command]
+async fn get_text_page(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    text_id: i64,
+    page: usize,
+    words_per_page: Option<usize>,
+) -> AppResult<pagination::TextPage> {
+    let words_per_page = pagination::check_words_per_page(words_per_page)?;
+    let text_page = pagination::page(&state, text_id, page, words_per_page).await?;
+    repo::texts::mark_opened(&state, text_id, clock.now()).await?;
+    Ok(text_page)
 }
 
-// Structure to hold all database content for export/import
-#[derive(Serialize, Deserialize, Debug)]
-struct DatabaseExport {
-    texts: Vec<Text>,
-    words: Vec<Word>,
-    text_words: Vec<TextWord>,
-    user_preferences: Vec<UserPreference>,
-    version: String, // For future compatibility
+// Creates a collection with one text per chapter of the book
+#[tauri::command]
+async fn import_epub(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    path: String,
+) -> AppResult<import::ImportReport> {
+    import::epub::import(&state, std::path::Path::new(&path), clock.now()).await
 }
 
-const BASE_STABILITY: f64 = 0.5; // Initial stability for new words
-const BASE_DIFFICULTY: f64 = 0.3; // Initial difficulty (0-1 scale, lower means harder)
+// Creates a text from a saved web page, Markdown or plain text file
+#[tauri::command]
+async fn import_document(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    path: String,
+    title: Option<String>,
+    metadata: Option<TextMetadata>,
+) -> AppResult<import::ImportReport> {
+    let metadata = metadata.unwrap_or_default();
+    import::document::import(&state, std::path::Path::new(&path), title.as_deref(), &metadata, clock.now()).await
+}
 
-const EASY_BONUS: f64 = 1.3; // Additional boost for "Easy" rating
-const HARD_PENALTY: f64 = 0.5; // Penalty for "Hard" rating
-const AGAIN_RESET_PCT: f64 = 0.2; // How much stability is retained after "Again"
-const MIN_INTERVAL: i64 = 1; // Minimum interval in days
-const MAX_INTERVAL: i64 = 365 * 10; // Maximum interval (10 years)
-const INTERVAL_MODIFIER: f64 = 1.0; // Global scaling factor
+// Page count and chapters of a PDF, to choose what to import
+#[tauri::command]
+async fn inspect_pdf(path: String) -> AppResult<import::pdf::PdfInfo> {
+    import::pdf::inspect(&import::read_file(std::path::Path::new(&path))?)
+}
 
-const TARGET_RETENTION: f64 = 0.85; // Target probability of recall
+// Creates one text per chosen range of pages, by default one per chapter
+#[tauri::command]
+async fn import_pdf(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    path: String,
+    sections: Option<Vec<import::pdf::PdfSection>>,
+) -> AppResult<import::ImportReport> {
+    import::pdf::import(&state, std::path::Path::new(&path), sections, clock.now()).await
+}
 
+// Imports every supported file of a folder into a collection, a new one
+// named after the folder if none is given. Sends a folder-import-progress
+// event after each file.
 #[tauri::command]
-async fn generate_sync_key(state: tauri::State<'_, SqlitePool>) -> Result<String, String> {
-    // 1. Extract all data from the database
-    let db_export = export_database(&state).await?;
-    
-    // 2. Serialize to JSON
-    let json_data = serde_json::to_string(&db_export)
-        .map_err(|e| format!("Failed to serialize database: {}", e))?;
-    
-    // 3. Compress and encode
-    let sync_key = compress_and_encode(&json_data)
-        .map_err(|e| format!("Failed to compress data: {}", e))?;
-    
-    Ok(sync_key)
+async fn import_folder(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    path: String,
+    collection_id: Option<i64>,
+) -> AppResult<import::folder::FolderReport> {
+    use tauri::Emitter;
+    let mut on_progress = |progress: &import::folder::FolderProgress| {
+        if let Err(e) = app_handle.emit("folder-import-progress", progress) {
+            tracing::warn!("Failed to send import progress: {}", e);
+        }
+    };
+    import::folder::import(&state, std::path::Path::new(&path), collection_id, clock.now(), &mut on_progress).await
 }
 
+// Creates a text from the dialogue of an .srt or .vtt file
 #[tauri::command]
-async fn apply_sync_key(
-    state: tauri::State<'_, SqlitePool>, 
-    sync_key: String
-) -> Result<(), String> {
-    // 1. Decode and decompress
-    let json_data = decode_and_decompress(&sync_key)
-        .map_err(|e| format!("Failed to decode sync key: {}", e))?;
-    
-    // 2. Deserialize
-    let db_export: DatabaseExport = serde_json::from_str(&json_data)
-        .map_err(|e| format!("Failed to deserialize database: {}", e))?;
-    
-    // 3. Import into database (this will overwrite existing data)
-    import_database(&state, db_export).await?;
-    
-    Ok(())
+async fn import_subtitles(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    path: String,
+    title: Option<String>,
+) -> AppResult<import::ImportReport> {
+    import::subtitles::import(&state, std::path::Path::new(&path), title.as_deref(), clock.now()).await
 }
 
-async fn export_database(state: &SqlitePool) -> Result<DatabaseExport, String> {
-    // Fetch all texts
-    let texts = sqlx::query_as!(
-        Text,
-        r#"SELECT id, title, content FROM texts"#
-    )
-    .fetch_all(state)
-    .await
-    .map_err(|e| format!("Failed to fetch texts: {}", e.to_string()))?;
-
-    // Fetch all words
-    let words = sqlx::query_as!(
-        Word,
-        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words"#
-    )
-    .fetch_all(state)
-    .await
-    .map_err(|e| format!("Failed to fetch words: {}", e.to_string()))?;
-
-    // Fetch all text_words relationships
-    let text_words = sqlx::query_as!(
-        TextWord,
-        r#"SELECT text_id, word_id FROM text_words"#
-    )
-    .fetch_all(state)
-    .await
-    .map_err(|e| format!("Failed to fetch text_words: {}", e.to_string()))?;
-
-    // Fetch user preferences
-    let user_preferences = sqlx::query_as!(
-        UserPreference,
-        r#"SELECT id, country_code FROM user_preferences"#
-    )
-    .fetch_all(state)
-    .await
-    .map_err(|e| format!("Failed to fetch user preferences: {}", e.to_string()))?;
-
-    // Create the export structure
-    let export = DatabaseExport {
-        texts,
-        words,
-        text_words,
-        user_preferences,
-        version: "1.0".to_string(), // For future compatibility
-    };
+// When each sentence of the text is said, empty for texts not made from subtitles
+#[tauri::command]
+async fn get_text_timestamps(
+    state: tauri::State<'_, SqlitePool>,
+    text_id: i64,
+) -> AppResult<Vec<repo::timestamps::SentenceTimestamp>> {
+    repo::timestamps::list(&state, text_id).await
+}
 
-    Ok(export)
-}
-
-async fn import_database(state: &SqlitePool, db_export: DatabaseExport) -> Result<(), String> {
-    // Begin a transaction
-    let mut tx = state.begin().await.map_err(|e| e.to_string())?;
-
-    // Clear existing tables
-    sqlx::query!("DELETE FROM text_words")
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to clear text_words: {}", e))?;
-
-    sqlx::query!("DELETE FROM texts")
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to clear texts: {}", e))?;
-
-    sqlx::query!("DELETE FROM words")
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to clear words: {}", e))?;
-
-    sqlx::query!("DELETE FROM user_preferences")
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to clear user_preferences: {}", e))?;
-
-    // Insert texts
-    for text in &db_export.texts {
-        sqlx::query!(
-            r#"INSERT INTO texts (id, title, content) VALUES (?, ?, ?)"#,
-            text.id,
-            text.title,
-            text.content
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to insert text: {}", e))?;
-    }
+// The sentence, with its time, that holds the word at `token_offset`
+#[tauri::command]
+async fn get_word_timestamp(
+    state: tauri::State<'_, SqlitePool>,
+    text_id: i64,
+    token_offset: i64,
+) -> AppResult<Option<repo::timestamps::SentenceTimestamp>> {
+    repo::timestamps::at_offset(&state, text_id, token_offset).await
+}
 
-    // Insert words
-    for word in &db_export.words {
-        sqlx::query!(
-            r#"INSERT INTO words (id, word, status, meaning, stability, difficulty, last_review_date, next_review_date) 
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
-            word.id,
-            word.word,
-            word.status,
-            word.meaning,
-            word.stability,
-            word.difficulty,
-            word.last_review_date,
-            word.next_review_date
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to insert word: {}", e))?;
-    }
+// Imports a StarDict (.ifo), TSV or Wiktionary (.jsonl) dictionary file
+#[tauri::command]
+async fn import_dictionary(
+    state: tauri::State<'_, SqlitePool>,
+    path: String,
+    name: Option<String>,
+    language: Option<String>,
+) -> AppResult<repo::dictionaries::Dictionary> {
+    dictionary::import(&state, std::path::Path::new(&path), name.as_deref(), language.as_deref()).await
+}
 
-    // Insert text_words relationships
-    for text_word in &db_export.text_words {
-        sqlx::query!(
-            r#"INSERT INTO text_words (text_id, word_id) VALUES (?, ?)"#,
-            text_word.text_id,
-            text_word.word_id
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to insert text_word relationship: {}", e))?;
-    }
+#[tauri::command]
+async fn list_dictionaries(state: tauri::State<'_, SqlitePool>) -> AppResult<Vec<repo::dictionaries::Dictionary>> {
+    repo::dictionaries::list(&state).await
+}
 
-    // Insert user preferences
-    for pref in &db_export.user_preferences {
-        sqlx::query!(
-            r#"INSERT INTO user_preferences (id, country_code) VALUES (?, ?)"#,
-            pref.id,
-            pref.country_code
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to insert user preference: {}", e))?;
-    }
+#[tauri::command]
+async fn delete_dictionary(state: tauri::State<'_, SqlitePool>, dictionary_id: i64) -> AppResult<()> {
+    repo::dictionaries::delete(&state, dictionary_id).await
+}
 
-    // Commit the transaction
-    tx.commit().await.map_err(|e| format!("Failed to commit transaction: {}", e))?;
+// Definitions of a word in the offline dictionaries, or in one of them. Words
+// that are not a headword fall back to looser matches, best first.
+#[tauri::command]
+async fn lookup_word(
+    state: tauri::State<'_, SqlitePool>,
+    word: String,
+    dictionary_id: Option<i64>,
+) -> AppResult<Vec<dictionary::lookup::LookupResult>> {
+    dictionary::lookup::lookup_word(&state, &word, dictionary_id).await
+}
 
-    // Reset SQLite sequences
-    sqlx::query!("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM texts) WHERE name = 'texts'")
-        .execute(state)
-        .await
-        .map_err(|e| format!("Failed to reset texts sequence: {}", e))?;
+#[tauri::command]
+async fn list_dictionary_providers(
+    state: tauri::State<'_, SqlitePool>,
+) -> AppResult<Vec<repo::dictionary_providers::DictionaryProvider>> {
+    repo::dictionary_providers::list(&state).await
+}
 
-    sqlx::query!("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM words) WHERE name = 'words'")
-        .execute(state)
-        .await
-        .map_err(|e| format!("Failed to reset words sequence: {}", e))?;
+#[tauri::command]
+async fn create_dictionary_provider(
+    state: tauri::State<'_, SqlitePool>,
+    provider: repo::dictionary_providers::ProviderInput,
+) -> AppResult<repo::dictionary_providers::DictionaryProvider> {
+    repo::dictionary_providers::create(&state, &provider).await
+}
 
-    sqlx::query!("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM user_preferences) WHERE name = 'user_preferences'")
-        .execute(state)
-        .await
-        .map_err(|e| format!("Failed to reset user_preferences sequence: {}", e))?;
+#[tauri::command]
+async fn update_dictionary_provider(
+    state: tauri::State<'_, SqlitePool>,
+    provider_id: i64,
+    provider: repo::dictionary_providers::ProviderInput,
+) -> AppResult<repo::dictionary_providers::DictionaryProvider> {
+    repo::dictionary_providers::update(&state, provider_id, &provider).await
+}
 
-    Ok(())
+#[tauri::command]
+async fn delete_dictionary_provider(state: tauri::State<'_, SqlitePool>, provider_id: i64) -> AppResult<()> {
+    repo::dictionary_providers::delete(&state, provider_id).await
 }
 
-fn compress_and_encode(data: &str) -> Result<String, std::io::Error> {
-    // Create a gzip encoder with high compression level
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
-    
-    // Write data to the encoder
-    encoder.write_all(data.as_bytes())?;
-    
-    // Finish compression and get the compressed data
-    let compressed_data = encoder.finish()?;
-    
-    // Encode to base64 for easy transfer
-    let encoded = general_purpose::URL_SAFE.encode(compressed_data);
-    
-    Ok(encoded)
+#[tauri::command]
+async fn reorder_dictionary_providers(state: tauri::State<'_, SqlitePool>, provider_ids: Vec<i64>) -> AppResult<()> {
+    repo::dictionary_providers::reorder(&state, &provider_ids).await
 }
 
-fn decode_and_decompress(encoded: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // Decode from base64
-    let compressed_data = general_purpose::URL_SAFE.decode(encoded)?;
-    
-    // Create a gzip decoder
-    let mut decoder = GzDecoder::new(&compressed_data[..]);
-    
-    // Read decompressed data
-    let mut decompressed_data = String::new();
-    decoder.read_to_string(&mut decompressed_data)?;
-    
-    Ok(decompressed_data)
+// Brings back the shipped providers, keeping the ones the user added
+#[tauri::command]
+async fn restore_default_dictionary_providers(
+    state: tauri::State<'_, SqlitePool>,
+) -> AppResult<Vec<repo::dictionary_providers::DictionaryProvider>> {
+    repo::dictionary_providers::restore_defaults(&state).await?;
+    repo::dictionary_providers::list(&state).await
 }
 
+// Where to look the word up, for the language picked in the app unless `language` is given
 #[tauri::command]
-async fn get_text_with_words(
+async fn get_dictionary_links(
     state: tauri::State<'_, SqlitePool>,
-    id: i64,
-) -> Result<(Text, Vec<Word>), String> {
-    // Fetch the text
-    let text = sqlx::query_as!(
-        Text,
-        r#"SELECT id, title, content FROM texts WHERE id = ?"#,
-        id
-    )
-    .fetch_optional(&*state)
-    .await
-    .map_err(|e| e.to_string())?
-    .ok_or("Text not found")?;
-
-    // Remove punctuation from the content and split into words
-    let cleaned_content = PUNCTUATION_REGEX.replace_all(&text.content, "").to_string();
-    let words: Vec<&str> = cleaned_content.split_whitespace().collect();
-
-    // Fetch the corresponding words from the database
-    let mut word_data = Vec::new();
-    for word in words {
-        let word_record = sqlx::query_as::<_, Word>(
-            r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date 
-            FROM words 
-            WHERE word = ?"#,
-        )
-        .bind(word)
-        .fetch_optional(&*state)
-        .await
-        .map_err(|e| e.to_string())?;
-        if let Some(w) = word_record {
-            word_data.push(w);
-        }
-    }
+    word: String,
+    language: Option<String>,
+) -> AppResult<Vec<repo::dictionary_providers::DictionaryLink>> {
+    repo::dictionary_providers::links(&state, &word, language.as_deref()).await
+}
+
+#[tauri::command]
+async fn get_texts(state: tauri::State<'_, SqlitePool>) -> AppResult<Vec<Text>> {
+    repo::texts::list(&state).await
+}
+
+// The library list, without the content of the texts but with their word counts
+#[tauri::command]
+async fn list_texts(
+    state: tauri::State<'_, SqlitePool>,
+    collection_id: Option<i64>,
+    tag: Option<String>,
+    level: Option<String>,
+    sort: Option<repo::texts::TextSort>,
+    descending: Option<bool>,
+) -> AppResult<Vec<TextSummary>> {
+    let filter = repo::texts::TextFilter {
+        collection_id,
+        tag,
+        level,
+        sort,
+        descending: descending.unwrap_or(false),
+    };
+    repo::texts::list_summaries(&state, &filter).await
+}
+
+#[tauri::command]
+async fn list_collections(state: tauri::State<'_, SqlitePool>) -> AppResult<Vec<Collection>> {
+    repo::collections::list(&state).await
+}
 
-    // println!("Fetched words: {:?}", word_data);
-    Ok((text, word_data))
+#[tauri::command]
+async fn create_collection(
+    state: tauri::State<'_, SqlitePool>,
+    name: String,
+    description: Option<String>,
+) -> AppResult<Collection> {
+    repo::collections::create(&state, &name, description.as_deref()).await
+}
+
+#[tauri::command]
+async fn update_collection(
+    state: tauri::State<'_, SqlitePool>,
+    collection_id: i64,
+    name: String,
+    description: Option<String>,
+) -> AppResult<Collection> {
+    repo::collections::update(&state, collection_id, &name, description.as_deref()).await
+}
+
+#[tauri::command]
+async fn delete_collection(
+    state: tauri::State<'_, SqlitePool>,
+    collection_id: i64,
+) -> AppResult<()> {
+    repo::collections::delete(&state, collection_id).await
+}
+
+// `collection_id` None takes the text out of its collection
+#[tauri::command]
+async fn set_text_collection(
+    state: tauri::State<'_, SqlitePool>,
+    text_id: i64,
+    collection_id: Option<i64>,
+) -> AppResult<()> {
+    repo::collections::set_text_collection(&state, text_id, collection_id).await
+}
+
+#[tauri::command]
+async fn reorder_collection(
+    state: tauri::State<'_, SqlitePool>,
+    collection_id: i64,
+    text_ids: Vec<i64>,
+) -> AppResult<()> {
+    repo::collections::reorder(&state, collection_id, &text_ids).await
 }
 
 #[tauri::command]
-async fn get_texts(state: tauri::State<'_, SqlitePool>) -> Result<Vec<Text>, String> {
-    let texts = sqlx::query_as!(
-        Text,
-        r#"SELECT id, title, content FROM texts"#
-    )
-    .fetch_all(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
+async fn list_tags(state: tauri::State<'_, SqlitePool>) -> AppResult<Vec<Tag>> {
+    repo::tags::list(&state).await
+}
 
-    Ok(texts)
+#[tauri::command]
+async fn set_text_tags(
+    state: tauri::State<'_, SqlitePool>,
+    text_id: i64,
+    tags: Vec<String>,
+) -> AppResult<Vec<String>> {
+    repo::tags::set_text_tags(&state, text_id, &tags).await
 }
 
 #[tauri::command]
 async fn get_text_by_id(
     state: tauri::State<'_, SqlitePool>,
     id: i64,
-) -> Result<Option<Text>, String> {
-    let text = sqlx::query_as!(
-        Text,
-        r#"SELECT id, title, content FROM texts WHERE id = ?"#,
-        id
-    )
-    .fetch_optional(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    Ok(text)
+) -> AppResult<Option<Text>> {
+    repo::texts::get(&state, id).await
 }
 
 #[tauri::command]
 async fn create_text(
     state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
     title: String,
     content: String,
-) -> Result<Text, String> {
-    let text = sqlx::query_as!(
-        Text,
-        r#"INSERT INTO texts (title, content) VALUES (?, ?) RETURNING id, title, content"#,
-        title,
-        content
-    )
-    .fetch_one(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-    println!("Inserted text with ID: {}", text.id);
-
-    let re = Regex::new(r"\b\p{L}+\b").unwrap();
-    let words: Vec<&str> = re.find_iter(&content).map(|mat| mat.as_str()).collect();
-
-    for word in words {
-        let existing_word = sqlx::query_as::<_, Word>(
-            r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words WHERE word = ?"#,
-        )
-        .bind(word)
-        .fetch_optional(&*state)
-        .await
-        .map_err(|e| e.to_string())?;
-
-        let word_id = if let Some(existing) = existing_word {
-            existing.id
-        } else {
-            let inserted_word = sqlx::query!(
-                r#"INSERT INTO words (word, status, meaning, stability, difficulty, last_review_date, next_review_date) 
-                VALUES (?, 'new', NULL, 1.0, 5.0, NULL, NULL) RETURNING id"#,
-                word
-            )
-            .fetch_one(&*state)
-            .await
-            .map_err(|e| e.to_string())?;
-            inserted_word.id
-        };
-
-        sqlx::query!(
-            r#"INSERT OR IGNORE INTO text_words (text_id, word_id) VALUES (?, ?)"#,
-            text.id,
-            word_id
-        )
-        .execute(&*state)
-        .await
-        .map_err(|e| e.to_string())?;
-    }
-
-    Ok(text)
+    metadata: Option<TextMetadata>,
+) -> AppResult<Text> {
+    repo::texts::create_with_metadata(&state, &title, &content, &metadata.unwrap_or_default(), clock.now()).await
 }
 
 #[tauri::command]
 async fn get_text_word_counts(
     state: tauri::State<'_, SqlitePool>,
     text_id: i64,
-) -> Result<(i64, i64, i64), String> {
-    let counts = sqlx::query!(
-        r#"
-        SELECT
-            COALESCE(SUM(CASE WHEN w.status = 'new' THEN 1 ELSE 0 END), 0) AS new_count,
-            COALESCE(SUM(CASE WHEN w.status = 'seen' THEN 1 ELSE 0 END), 0) AS seen_count,
-            COALESCE(SUM(CASE WHEN w.status = 'known' THEN 1 ELSE 0 END), 0) AS known_count
-        FROM text_words tw
-        JOIN words w ON tw.word_id = w.id
-        WHERE tw.text_id = ?
-        "#,
-        text_id
-    )
-    .fetch_one(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    Ok((
-        counts.new_count,
-        counts.seen_count,
-        counts.known_count,
-    ))
+) -> AppResult<(i64, i64, i64)> {
+    repo::texts::word_counts(&state, text_id).await
 }
 
 #[tauri::command]
 async fn update_text(
     state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
     id: i64,
     title: String,
     content: String,
-) -> Result<(), String> {
-    // Update the text's title and content
-    sqlx::query!(
-        r#"UPDATE texts SET title = ?, content = ? WHERE id = ?"#,
-        title,
-        content,
-        id
-    )
-    .execute(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    println!("Updated text with ID: {}", id);
-
-    // Use a Unicode-aware regex to extract words
-    let re = Regex::new(r"\b\p{L}+\b").unwrap(); // Matches whole words with Unicode letters
-    let words: Vec<&str> = re.find_iter(&content).map(|mat| mat.as_str()).collect();
-
-    println!("Extracted words: {:?}", words);
-
-    for word in words {
-        // Check if the word already exists in the database
-        let existing_word = sqlx::query_as::<_, Word>(
-            r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words WHERE word = ?"#,
-        )
-        .bind(word)
-        .fetch_optional(&*state)
-        .await
-        .map_err(|e| e.to_string())?;
-
-        let word_id = if let Some(existing) = existing_word {
-            println!("Found existing word: {} (ID: {})", word, existing.id);
-            existing.id // Use the existing word's ID
-        } else {
-            // Insert the word if it doesn't exist
-            println!("Inserting new word: {}", word);
-            let inserted_word = sqlx::query!(
-                r#"INSERT INTO words (word, status, meaning, stability, difficulty, last_review_date, next_review_date) 
-                VALUES (?, 'new', NULL, 1.0, 5.0, NULL, NULL) RETURNING id"#,
-                word
-            )
-            .fetch_one(&*state)
-            .await
-            .map_err(|e| e.to_string())?;
-            println!("Inserted word: {} (ID: {})", word, inserted_word.id);
-            inserted_word.id
-        };
-
-        // Link the word to the text in the `text_words` table
-        println!("Linking word ID {} to text ID {}", word_id, id);
-        sqlx::query!(
-            r#"INSERT OR IGNORE INTO text_words (text_id, word_id) VALUES (?, ?)"#,
-            id,
-            word_id
-        )
-        .execute(&*state)
-        .await
-        .map_err(|e| e.to_string())?;
-    }
-
-    Ok(())
+    metadata: Option<TextMetadata>,
+) -> AppResult<()> {
+    // Callers that only edit title and content leave the metadata alone
+    repo::texts::update_with_metadata(&state, id, &title, &content, metadata.as_ref(), clock.now()).await
 }
 
 #[tauri::command]
 async fn delete_text(
     state: tauri::State<'_, SqlitePool>,
     text_id: i64,
-) -> Result<(), String> {
-    println!("Attempting to delete text with ID: {}", text_id);
-
-    // Begin a transaction to ensure atomicity
-    let mut tx = state.begin().await.map_err(|e| {
-        println!("Error beginning transaction: {}", e);
-        e.to_string()
-    })?;
-
-    // Delete rseted rows from the text_words table
-    sqlx::query!("DELETE FROM text_words WHERE text_id = ?", text_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            println!("Error deleting from text_words: {}", e);
-            e.to_string()
-        })?;
-    println!("Deleted related rows from text_words for text ID: {}", text_id);
-
-    // Delete the text from the texts table
-    sqlx::query!("DELETE FROM texts WHERE id = ?", text_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            println!("Error deleting from texts: {}", e);
-            e.to_string()
-        })?;
-    println!("Deleted text with ID: {}", text_id);
-
-    // Commit the transaction
-    tx.commit().await.map_err(|e| {
-        println!("Error committing transaction: {}", e);
-        e.to_string()
-    })?;
+) -> AppResult<()> {
+    repo::texts::delete(&state, text_id).await
+}
 
-    Ok(())
+// Progress of a text that was never opened starts at the first word
+#[tauri::command]
+async fn get_reading_progress(
+    state: tauri::State<'_, SqlitePool>,
+    text_id: i64,
+) -> AppResult<repo::progress::ReadingProgress> {
+    repo::progress::get(&state, text_id).await
+}
+
+#[tauri::command]
+async fn save_reading_position(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    text_id: i64,
+    token_offset: i64,
+) -> AppResult<repo::progress::ReadingProgress> {
+    repo::progress::save_position(&state, text_id, token_offset, clock.now()).await
+}
+
+#[tauri::command]
+async fn set_text_completed(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    text_id: i64,
+    completed: bool,
+) -> AppResult<repo::progress::ReadingProgress> {
+    repo::progress::set_completed(&state, text_id, completed, clock.now()).await
+}
+
+#[tauri::command]
+async fn continue_reading(
+    state: tauri::State<'_, SqlitePool>,
+    limit: Option<i64>,
+) -> AppResult<Vec<repo::progress::ContinueReading>> {
+    repo::progress::continue_reading(&state, limit.unwrap_or(10)).await
+}
+
+#[tauri::command]
+async fn list_bookmarks(
+    state: tauri::State<'_, SqlitePool>,
+    text_id: i64,
+) -> AppResult<Vec<repo::progress::Bookmark>> {
+    repo::progress::bookmarks(&state, text_id).await
+}
+
+#[tauri::command]
+async fn add_bookmark(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    text_id: i64,
+    name: String,
+    token_offset: i64,
+) -> AppResult<repo::progress::Bookmark> {
+    repo::progress::add_bookmark(&state, text_id, &name, token_offset, clock.now()).await
+}
+
+#[tauri::command]
+async fn delete_bookmark(
+    state: tauri::State<'_, SqlitePool>,
+    bookmark_id: i64,
+) -> AppResult<()> {
+    repo::progress::delete_bookmark(&state, bookmark_id).await
 }
 
 #[tauri::command]
 async fn mark_word_as_known(
     state: tauri::State<'_, SqlitePool>,
     word_id: i64,
-) -> Result<(), String> {
-    // Update the status to "known" and clear the `next_review_date`
-    match sqlx::query!(
-        r#"UPDATE words SET status = 'known', next_review_date = NULL WHERE id = ?"#,
-        word_id
-    )
-    .execute(&*state)
-    .await
-    {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                Err("Word ID not found".to_string())
-            } else {
-                Ok(())
-            }
-        }
-        Err(e) => Err(format!("Database error: {}", e)),
-    }
+) -> AppResult<()> {
+    repo::words::mark_known(&state, word_id).await
+}
+
+// Marks every word of the page that is still new as known. Without `page`
+// the whole text is finished.
+#[tauri::command]
+async fn finish_page(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    text_id: i64,
+    page: Option<usize>,
+    words_per_page: Option<usize>,
+) -> AppResult<repo::reading_events::ReadingEvent> {
+    let page = match page {
+        Some(index) => Some((index, pagination::check_words_per_page(words_per_page)?)),
+        None => None,
+    };
+    repo::reading_events::finish_page(&state, text_id, page, clock.now()).await
+}
+
+#[tauri::command]
+async fn undo_reading_event(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    event_id: i64,
+) -> AppResult<repo::reading_events::ReadingEvent> {
+    repo::reading_events::undo(&state, event_id, clock.now()).await
+}
+
+#[tauri::command]
+async fn list_reading_events(
+    state: tauri::State<'_, SqlitePool>,
+    text_id: i64,
+) -> AppResult<Vec<repo::reading_events::ReadingEvent>> {
+    repo::reading_events::list(&state, text_id).await
 }
 
 #[tauri::command]
 async fn update_word_meaning(
     state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
     word_id: i64,
     meaning: String,
-) -> Result<(), String> {
-    let now_iso = chrono::Utc::now().to_rfc3339(); // Current timestamp as ISO 8601
-
-    // Update both `meaning`, `status`, and `next_review_date`
-    match sqlx::query!(
-        r#"UPDATE words SET meaning = ?, status = 'seen', next_review_date = ? WHERE id = ?"#,
-        meaning,
-        now_iso, // Set `next_review_date` to the current time
-        word_id
-    )
-    .execute(&*state)
-    .await
-    {
-        Ok(result) => {
-            if result.rows_affected() == 0 {
-                Err("Word ID not found".to_string())
-            } else {
-                Ok(())
-            }
-        }
-        Err(e) => Err(format!("Database error: {}", e)),
-    }
+) -> AppResult<()> {
+    repo::words::update_meaning(&state, word_id, &meaning, clock.now()).await
 }
 
+// Meanings to fill in for a word: those of its other forms in the vocabulary,
+// then dictionary definitions
 #[tauri::command]
-async fn get_random_word(state: tauri::State<'_, SqlitePool>) -> Result<Word, String> {
-    // Get the current timestamp as an ISO 8601 string
-    let now = chrono::Utc::now().to_rfc3339();
-
-    // Fetch a random word due for review
-    // We prioritize words that are most overdue (lowest retention probability)
-    let word = sqlx::query_as::<_, Word>(
-        r#"
-        SELECT 
-            id, word, status, meaning, stability, difficulty, 
-            last_review_date, next_review_date 
-        FROM words 
-        WHERE 
-            status = 'seen' AND 
-            next_review_date <= ? 
-        ORDER BY next_review_date ASC
-        LIMIT 1
-        "#,
-    )
-    .bind(now) // Bind the current timestamp to the query
-    .fetch_optional(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    match word {
-        Some(word) => Ok(word),
-        None => Err("No words are due for review".to_string()),
-    }
+async fn suggest_meanings(
+    state: tauri::State<'_, SqlitePool>,
+    word_id: i64,
+) -> AppResult<Vec<dictionary::suggestions::MeaningSuggestion>> {
+    dictionary::suggestions::suggest_meanings(&state, word_id).await
+}
+
+#[tauri::command]
+async fn get_random_word(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+) -> AppResult<Word> {
+    review::next_due(&state, clock.now()).await
 }
 
 #[tauri::command]
 async fn estimate_word_retention(
     state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
     word_id: i64,
-) -> Result<f64, String> {
-    // Fetch the word from the database
-    let word = sqlx::query_as::<_, Word>(
-        r#"SELECT stability, last_review_date FROM words WHERE id = ?"#,
-    )
-    .bind(word_id)
-    .fetch_optional(&*state)
-    .await
-    .map_err(|e| e.to_string())?
-    .ok_or("Word not found")?;
-    
-    // If we don't have stability data, return default
-    let stability = match word.stability {
-        Some(s) => s,
-        None => return Ok(0.0), // No retention data
-    };
-    
-    // If no last review, assume 100% retention
-    let last_review_date = match word.last_review_date {
-        Some(date) => date,
-        None => return Ok(1.0), // No decay yet
-    };
-    
-    // Calculate days since last review
-    let last_review = match chrono::DateTime::parse_from_rfc3339(&last_review_date) {
-        Ok(dt) => dt.with_timezone(&chrono::Utc),
-        Err(_) => return Err("Invalid last review date format".to_string()),
-    };
-    
-    let now = chrono::Utc::now();
-    let days_elapsed = now.signed_duration_since(last_review).num_days().max(0) as f64;
-    
-    // Calculate current retention using exponential forgetting curve
-    // R = e^(-d/S) where d is days since last review and S is stability
-    let retention = (-days_elapsed / stability).exp();
-    
-    Ok(retention)
+) -> AppResult<f64> {
+    review::estimate_retention(&state, word_id, clock.now()).await
 }
 
 #[tauri::command]
 async fn save_selected_country_code(
     state: tauri::State<'_, SqlitePool>,
     country_code: String,
-) -> Result<(), String> {
-    // Clear the existing preference (if any) and insert the new one
-    sqlx::query!("DELETE FROM user_preferences")
-        .execute(&*state)
-        .await
-        .map_err(|e| e.to_string())?;
-
-    sqlx::query!(
-        r#"INSERT INTO user_preferences (country_code) VALUES (?)"#,
-        country_code
-    )
-    .execute(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    Ok(())
-}
-
-fn update_word_parameters(
-    stability: f64,
-    difficulty: f64,
-    last_interval: Option<i64>,
-    rating: &str,
-) -> (f64, f64, i64, f64) {
-    let mut new_stability = stability;
-    let mut new_difficulty = difficulty;
-    let default_last_interval = 1;
-    let last_interval = last_interval.unwrap_or(default_last_interval);
-    
-    // Calculate retrievability (theoretical probability of recall)
-    // R = e^(-d/S) where d is days since last review and S is stability
-    let retrievability = (-1.0 * (last_interval as f64) / stability).exp();
-    
-    // Update difficulty based on performance vs expected
-    match rating {
-        "Again" => {
-            // Lapse: reset stability but keep some memory trace
-            new_stability = stability * AGAIN_RESET_PCT;
-            // If you failed despite high retrievability, increase difficulty
-            let expected_success = retrievability;
-            let actual_success = 0.0; // Failed
-            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.2)
-                .clamp(0.1, 1.0);
-        }
-        "Hard" => {
-            // Succeeded but with difficulty
-            let memory_strength = HARD_PENALTY;
-            new_stability = stability * (1.0 + memory_strength * (1.0 - retrievability));
-            
-            // Adjust difficulty (if it was hard despite high retrievability, increase difficulty)
-            let expected_success = retrievability;
-            let actual_success = 0.6; // Partial success
-            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.15)
-                .clamp(0.1, 1.0);
-        }
-        "Good" => {
-            // Standard success
-            let memory_strength = 1.0;
-            new_stability = stability * (1.0 + memory_strength * (1.0 - retrievability));
-            
-            // Adjust difficulty
-            let expected_success = retrievability;
-            let actual_success = 1.0; // Full success
-            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.1)
-                .clamp(0.1, 1.0);
-        }
-        "Easy" => {
-            // Easy success
-            let memory_strength = EASY_BONUS;
-            new_stability = stability * (1.0 + memory_strength * (1.0 - retrievability));
-            
-            // Reduce difficulty
-            let expected_success = retrievability;
-            let actual_success = 1.0; // Very easy success
-            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.08)
-                .clamp(0.1, 1.0);
-        }
-        _ => {}
-    }
-    
-    // Calculate optimal interval based on stability, target retention and difficulty
-    // Solve for t in: e^(-t/S) = R where R is target retention
-    // t = -S * ln(R)
-    let optimal_days = -new_stability * TARGET_RETENTION.ln();
-    
-    // Apply difficulty modifier (higher difficulty = shorter intervals)
-    let difficulty_modifier = 1.0 - (new_difficulty - 0.3) * 0.5;
-    
-    // Calculate scheduled days with global interval modifier
-    let scheduled_days = (optimal_days * difficulty_modifier * INTERVAL_MODIFIER).round() as i64;
-    
-    // Ensure scheduled days is within bounds
-    let scheduled_days = scheduled_days.clamp(MIN_INTERVAL, MAX_INTERVAL);
-    
-    // For debugging: calculate actual predicted retention at review time
-    let predicted_retention = (-1.0 * (scheduled_days as f64) / new_stability).exp();
-    
-    (new_stability, new_difficulty, scheduled_days, predicted_retention)
+) -> AppResult<()> {
+    repo::preferences::save_country_code(&state, &country_code).await
 }
 
 #[tauri::command]
 async fn get_selected_country_code(
     state: tauri::State<'_, SqlitePool>,
-) -> Result<Option<String>, String> {
-    let preference = sqlx::query!(
-        r#"SELECT country_code FROM user_preferences LIMIT 1"#
-    )
-    .fetch_optional(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-
-    Ok(preference.map(|p| p.country_code))
+) -> AppResult<Option<String>> {
+    repo::preferences::country_code(&state).await
 }
 
 #[tauri::command]
 async fn review_word(
     state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
     word_id: i64,
     rating: String,
-) -> Result<(), String> {
-    // Fetch the word from the database
-    let word = sqlx::query_as::<_, Word>(
-        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words WHERE id = ?"#,
-    )
-    .bind(word_id)
-    .fetch_optional(&*state)
-    .await
-    .map_err(|e| e.to_string())?
-    .ok_or("Word not found")?;
-
-    // Make sure we have valid stability and difficulty values
-    let stability = word.stability.unwrap_or(BASE_STABILITY);
-    let difficulty = word.difficulty.unwrap_or(BASE_DIFFICULTY);
-    
-    // Calculate days since last review (default to 1 if first review)
-    let days_since_last_review = if let Some(last_date) = &word.last_review_date {
-        let last_review = match chrono::DateTime::parse_from_rfc3339(last_date) {
-            Ok(dt) => dt.with_timezone(&chrono::Utc),
-            Err(_) => return Err("Invalid last review date format".to_string()),
-        };
-        
-        let now = chrono::Utc::now();
-        let duration = now.signed_duration_since(last_review);
-        duration.num_days().max(1) // At least 1 day
-    } else {
-        1 // First review
+) -> AppResult<()> {
+    review::review(&state, word_id, &rating, clock.now()).await?;
+    Ok(())
+}
+
+// Projects the daily review load and retention of the library, see simulation.rs
+#[tauri::command]
+async fn simulate_schedule(
+    state: tauri::State<'_, SqlitePool>,
+    clock: tauri::State<'_, SharedClock>,
+    days: u32,
+    new_words_per_day: Option<u32>,
+    seed: Option<u64>,
+) -> AppResult<simulation::SimulationReport> {
+    let options = simulation::SimulationOptions {
+        days,
+        new_words_per_day: new_words_per_day.unwrap_or(0),
+        seed,
     };
+    simulation::simulate(&state, clock.now(), &options).await
+}
 
-    println!(
-        "Review - Word: {}, Initial Stability: {}, Difficulty: {}, Days Since Last: {}",
-        word.word,
-        stability,
-        difficulty,
-        days_since_last_review
-    );
-
-    // Parse the rating
-    if !["Again", "Hard", "Good", "Easy"].contains(&rating.as_str()) {
-        return Err("Invalid rating".to_string());
-    }
+#[tauri::command]
+async fn get_all_words(state: tauri::State<'_, SqlitePool>) -> AppResult<Vec<Word>> {
+    repo::words::list(&state).await
+}
+
+#[tauri::command]
+async fn delete_word(
+    state: tauri::State<'_, SqlitePool>,
+    word_id: i64,
+) -> AppResult<()> {
+    repo::words::delete(&state, word_id).await
+}
+
+#[tauri::command]
+async fn configure_sync_server(
+    state: tauri::State<'_, SqlitePool>,
+    server_url: String,
+    secret: String,
+) -> AppResult<sync::client::SyncStatus> {
+    sync::client::configure(&state, &server_url, &secret).await
+}
+
+#[tauri::command]
+async fn disconnect_sync_server(state: tauri::State<'_, SqlitePool>) -> AppResult<()> {
+    sync::client::disconnect(&state).await
+}
+
+#[tauri::command]
+async fn get_sync_status(state: tauri::State<'_, SqlitePool>) -> AppResult<sync::client::SyncStatus> {
+    sync::client::status(&state).await
+}
+
+#[tauri::command]
+async fn sync_with_server(state: tauri::State<'_, SqlitePool>) -> AppResult<sync::client::SyncReport> {
+    sync::client::sync_now(&state).await
+}
+
+#[tauri::command]
+async fn get_log_settings(state: tauri::State<'_, SqlitePool>) -> AppResult<logging::LogSettings> {
+    Ok(logging::LogSettings {
+        filter: logging::load_filter(&state).await?,
+        directory: logging::directory().map(|d| d.to_string_lossy().to_string()),
+        files: logging::list_files()?,
+    })
+}
+
+#[tauri::command]
+async fn set_log_filter(
+    state: tauri::State<'_, SqlitePool>,
+    filter: String,
+) -> AppResult<()> {
+    logging::save_filter(&state, filter.trim()).await
+}
+
+#[tauri::command]
+async fn get_library_location(
+    paths: tauri::State<'_, library::LibraryPaths>,
+) -> AppResult<library::LibraryLocation> {
+    Ok(library::location(&paths))
+}
+
+// `library_dir` None moves the library back to the default location
+#[tauri::command]
+async fn set_library_location(
+    paths: tauri::State<'_, library::LibraryPaths>,
+    library_dir: Option<String>,
+) -> AppResult<library::LibraryLocation> {
+    library::set_library_dir(&paths, library_dir.map(std::path::PathBuf::from)).await
+}
 
-    // Update stability, difficulty, and scheduled days
-    let (updated_stability, updated_difficulty, scheduled_days, predicted_retention) = 
-        update_word_parameters(
-            stability,
-            difficulty,
-            Some(days_since_last_review),
-            &rating,
-        );
-
-    println!(
-        "Updated Stability: {}, Difficulty: {}, Scheduled Days: {}, Predicted Retention: {:.1}%",
-        updated_stability, 
-        updated_difficulty, 
-        scheduled_days,
-        predicted_retention * 100.0
-    );
-
-    // Calculate the next review date
-    let now = chrono::Utc::now();
-    let next_review = now + chrono::Duration::days(scheduled_days);
-    let next_review_iso = next_review.to_rfc3339();
-    let now_iso = now.to_rfc3339();
-
-    // Update the word in the database
-    sqlx::query!(
-        r#"
-        UPDATE words
-        SET
-            stability = ?,
-            difficulty = ?,
-            last_review_date = ?,
-            next_review_date = ?
-        WHERE id = ?
-        "#,
-        updated_stability,
-        updated_difficulty,
-        now_iso,         // Current timestamp as ISO 8601
-        next_review_iso, // Next review date as ISO 8601
-        word_id
-    )
-    .execute(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
+#[tauri::command]
+async fn list_profiles(
+    paths: tauri::State<'_, library::LibraryPaths>,
+    clock: tauri::State<'_, SharedClock>,
+) -> AppResult<profiles::ProfileList> {
+    Ok(profiles::list(&paths.library_dir, clock.now()))
+}
 
+#[tauri::command]
+async fn create_profile(
+    paths: tauri::State<'_, library::LibraryPaths>,
+    clock: tauri::State<'_, SharedClock>,
+    name: String,
+) -> AppResult<profiles::Profile> {
+    profiles::create(&paths.library_dir, &name, clock.now())
+}
+
+#[tauri::command]
+async fn rename_profile(
+    paths: tauri::State<'_, library::LibraryPaths>,
+    clock: tauri::State<'_, SharedClock>,
+    profile_id: String,
+    name: String,
+) -> AppResult<profiles::Profile> {
+    profiles::rename(&paths.library_dir, &profile_id, &name, clock.now())
+}
+
+// Restarts the app, the database of the new profile is opened on startup
+#[tauri::command]
+async fn switch_profile(
+    app_handle: tauri::AppHandle,
+    paths: tauri::State<'_, library::LibraryPaths>,
+    clock: tauri::State<'_, SharedClock>,
+    profile_id: String,
+) -> AppResult<()> {
+    let profile = profiles::switch(&paths.library_dir, &profile_id, clock.now())?;
+    if profiles::database_path(&paths.library_dir, &profile.id) != paths.database {
+        app_handle.restart();
+    }
     Ok(())
 }
 
 #[tauri::command]
-async fn get_all_words(state: tauri::State<'_, SqlitePool>) -> Result<Vec<Word>, String> {
-    let words = sqlx::query_as::<_, Word>(
-        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words"#
-    )
-    .fetch_all(&*state)
-    .await
-    .map_err(|e| e.to_string())?;
-    Ok(words)
+async fn delete_profile(
+    paths: tauri::State<'_, library::LibraryPaths>,
+    clock: tauri::State<'_, SharedClock>,
+    profile_id: String,
+) -> AppResult<()> {
+    profiles::delete(&paths.library_dir, &profile_id, clock.now())
 }
 
 #[tauri::command]
-async fn delete_word(
+async fn get_database_status(
+    app_handle: tauri::AppHandle,
+    paths: tauri::State<'_, library::LibraryPaths>,
+) -> AppResult<encryption::DatabaseStatus> {
+    Ok(encryption::DatabaseStatus {
+        encrypted: encryption::is_encrypted(&paths.database),
+        unlocked: app_handle.try_state::<SqlitePool>().is_some(),
+    })
+}
+
+// Every other command that touches the library fails until this succeeds
+#[tauri::command]
+async fn unlock_database(
+    app_handle: tauri::AppHandle,
+    paths: tauri::State<'_, library::LibraryPaths>,
+    passphrase: String,
+) -> AppResult<()> {
+    bootstrap::unlock(&app_handle, &paths, &passphrase).await
+}
+
+// The encryption commands rewrite the database and restart the app
+// The pool is closed once a rewrite starts, so the app restarts even when it
+// failed, onto the original database
+fn restart_after_rewrite(app_handle: &tauri::AppHandle, rewritten: AppResult<()>) -> AppResult<()> {
+    match rewritten {
+        Ok(()) => app_handle.restart(),
+        Err(e) => {
+            tracing::error!("Failed to rewrite the database: {}", e);
+            app_handle.request_restart();
+            Err(e)
+        }
+    }
+}
+
+#[tauri::command]
+async fn enable_encryption(
+    app_handle: tauri::AppHandle,
     state: tauri::State<'_, SqlitePool>,
-    word_id: i64,
-) -> Result<(), String> {
-    println!("Attempting to delete word with ID: {}", word_id);
-
-    // Begin a transaction to ensure atomicity
-    let mut tx = state.begin().await.map_err(|e| {
-        println!("Error beginning transaction: {}", e);
-        e.to_string()
-    })?;
-
-    // Delete related rows from the text_words table
-    sqlx::query!("DELETE FROM text_words WHERE word_id = ?", word_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            println!("Error deleting from text_words: {}", e);
-            e.to_string()
-        })?;
-    println!("Deleted related rows from text_words for word ID: {}", word_id);
-
-    // Delete the word from the words table
-    let result = sqlx::query!("DELETE FROM words WHERE id = ?", word_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            println!("Error deleting from words: {}", e);
-            e.to_string()
-        })?;
-
-    if result.rows_affected() == 0 {
-        return Err("Word ID not found".to_string());
+    paths: tauri::State<'_, library::LibraryPaths>,
+    passphrase: String,
+) -> AppResult<()> {
+    if encryption::is_encrypted(&paths.database) {
+        return Err(AppError::invalid_input("The library is already encrypted"));
     }
+    encryption::check_passphrase(&passphrase)?;
 
-    println!("Deleted word with ID: {}", word_id);
+    let rewritten = encryption::rewrite(&state, &paths.database, Some(&passphrase)).await;
+    restart_after_rewrite(&app_handle, rewritten)
+}
 
-    // Commit the transaction
-    tx.commit().await.map_err(|e| {
-        println!("Error committing transaction: {}", e);
-        e.to_string()
-    })?;
+#[tauri::command]
+async fn change_encryption_passphrase(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SqlitePool>,
+    paths: tauri::State<'_, library::LibraryPaths>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> AppResult<()> {
+    if !encryption::is_encrypted(&paths.database) {
+        return Err(AppError::invalid_input("The library is not encrypted"));
+    }
+    encryption::check_passphrase(&new_passphrase)?;
+    encryption::verify_passphrase(&paths.database, &current_passphrase).await?;
 
-    Ok(())
+    let rewritten = encryption::rewrite(&state, &paths.database, Some(&new_passphrase)).await;
+    restart_after_rewrite(&app_handle, rewritten)
 }
 
-// Create a function to initialize the database
-pub async fn init_db(pool: &SqlitePool) {
-    let schema = include_str!("../schema.sql");
-    sqlx::query(schema)
-        .execute(pool)
-        .await
-        .expect("Failed to initialize database");
+#[tauri::command]
+async fn remove_encryption(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, SqlitePool>,
+    paths: tauri::State<'_, library::LibraryPaths>,
+    passphrase: String,
+) -> AppResult<()> {
+    if !encryption::is_encrypted(&paths.database) {
+        return Err(AppError::invalid_input("The library is not encrypted"));
+    }
+    encryption::verify_passphrase(&paths.database, &passphrase).await?;
+
+    let rewritten = encryption::rewrite(&state, &paths.database, None).await;
+    restart_after_rewrite(&app_handle, rewritten)
 }
 
-// Create a function that sets up the application with all the commands
+// The one place where commands are registered, used by desktop and mobile
 pub fn app() -> tauri::Builder<tauri::Wry> {
     dotenv().ok();
     
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .setup(bootstrap::setup)
         .invoke_handler(tauri::generate_handler![
             get_texts,
             create_text,
@@ -997,86 +850,81 @@ pub fn app() -> tauri::Builder<tauri::Wry> {
             save_selected_country_code,
             get_selected_country_code,
             review_word,
+            simulate_schedule,
             get_all_words,
             delete_word,
             generate_sync_key,
             apply_sync_key,
+            generate_sync_key_chunks,
+            reassemble_sync_key_chunks,
             estimate_word_retention,
+            configure_sync_server,
+            disconnect_sync_server,
+            get_sync_status,
+            sync_with_server,
+            get_log_settings,
+            set_log_filter,
+            get_library_location,
+            set_library_location,
+            list_profiles,
+            create_profile,
+            rename_profile,
+            switch_profile,
+            delete_profile,
+            get_database_status,
+            unlock_database,
+            enable_encryption,
+            change_encryption_passphrase,
+            remove_encryption,
+            list_texts,
+            list_collections,
+            create_collection,
+            update_collection,
+            delete_collection,
+            set_text_collection,
+            reorder_collection,
+            list_tags,
+            set_text_tags,
+            get_reading_progress,
+            save_reading_position,
+            set_text_completed,
+            continue_reading,
+            list_bookmarks,
+            add_bookmark,
+            delete_bookmark,
+            get_text_pages,
+            get_text_page,
+            finish_page,
+            undo_reading_event,
+            list_reading_events,
+            import_epub,
+            import_subtitles,
+            get_text_timestamps,
+            get_word_timestamp,
+            import_document,
+            inspect_pdf,
+            import_pdf,
+            import_folder,
+            import_dictionary,
+            list_dictionaries,
+            delete_dictionary,
+            lookup_word,
+            list_dictionary_providers,
+            create_dictionary_provider,
+            update_dictionary_provider,
+            delete_dictionary_provider,
+            reorder_dictionary_providers,
+            restore_default_dictionary_providers,
+            get_dictionary_links,
+            suggest_meanings,
         ])
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tokio::runtime::Runtime::new().unwrap().block_on(async {
-        // Build the Tauri app and extract the app handle
-        let app = tauri::Builder::default()
-            .build(tauri::generate_context!())
-            .expect("Failed to build Tauri app");
-
-        let app_handle = app.app_handle();
-
-        // Determine the database path based on the platform
-        let db_path = if cfg!(target_os = "android") {
-            let app_dir = app_handle
-                .path()
-                .app_data_dir()
-                .expect("Failed to get app data directory");
-            app_dir.join("db.sqlite").to_str().unwrap().to_string()
-        } else if cfg!(target_os = "ios") {
-            let docs_dir = app_handle
-                .path()
-                .document_dir()
-                .expect("Failed to get documents directory");
-            docs_dir.join("db.sqlite").to_str().unwrap().to_string()
-        } else {
-            "../db.sqlite".to_string()
-        };
-
-        println!("Using database path: {}", db_path);
-
-        // Connect to the SQLite database with foreign key support enabled
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
-            .connect_with(
-                sqlx::sqlite::SqliteConnectOptions::new()
-                    .filename(db_path)
-                    .create_if_missing(true)
-                    .foreign_keys(true),
-            )
-            .await
-            .expect("Failed to connect to database");
-
-        // Initialize the database asynchronously
-        init_db(&pool).await;
-
-        // Run the app with the database connection pool
-        tauri::Builder::default()
-            .manage(pool) // Attach the database connection pool
-            .setup(|_app| {
-                // Additional setup logic can go here
-                Ok(())
-            })
-            .invoke_handler(tauri::generate_handler![
-                get_texts,
-                create_text,
-                get_text_with_words,
-                get_text_by_id,
-                update_text,
-                delete_text,
-                update_word_meaning,
-                mark_word_as_known,
-                get_text_word_counts,
-                get_random_word,
-                save_selected_country_code,
-                get_selected_country_code,
-                review_word,
-                get_all_words,
-                delete_word,
-                generate_sync_key,
-                apply_sync_key,
-                estimate_word_retention,
-            ])
-            .run(tauri::generate_context!())
-            .expect("Error while running Tauri application");
-    });
-}
\ No newline at end of file
+    logging::init();
+
+    app()
+        .run(tauri::generate_context!())
+        .expect("Error while running Tauri application");
+}
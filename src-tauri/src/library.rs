@@ -0,0 +1,249 @@
+// Where the library (the SQLite database) lives.
+//
+// Desktop builds keep it in the OS app-data directory unless
+//   - a file named `portable` sits next to the executable: everything goes
+//     into a `data` folder beside it, so the app can run from a USB stick
+//   - the user picked a library folder: stored in `library.json` in the
+//     config directory (the database cannot store its own location)
+// Mobile builds keep their fixed locations.
+//
+// The library folder holds one database per learner profile (see profiles.rs).
+
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqlitePoolOptions, SqliteConnectOptions};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+use tracing::{info, warn};
+
+use crate::error::{AppError, AppResult};
+use crate::{encryption, profiles};
+
+pub const DATABASE_FILE: &str = "db.sqlite";
+// Must match `identifier` in tauri.conf.json, the app directories are named after it
+pub const APP_IDENTIFIER: &str = "com.word-echo.app";
+const CONFIG_FILE: &str = "library.json";
+const PORTABLE_MARKER: &str = "portable";
+const PORTABLE_DATA_DIR: &str = "data";
+// Where development and pre-1.0 desktop builds kept the database, relative to the working directory
+const LEGACY_DATABASE: &str = "../db.sqlite";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryMode {
+    Default,
+    Portable,
+    Custom,
+    Mobile,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct LibraryConfig {
+    library_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LibraryPaths {
+    pub library_dir: PathBuf,
+    pub database: PathBuf, // Database of the active profile
+    pub data_dir: PathBuf, // Default home of the database, logs go below it in portable mode
+    pub config_file: PathBuf,
+    pub mode: LibraryMode,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LibraryLocation {
+    pub database_path: String,
+    pub mode: LibraryMode,
+    pub restart_required: bool,
+}
+
+fn portable_data_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    exe_dir
+        .join(PORTABLE_MARKER)
+        .exists()
+        .then(|| exe_dir.join(PORTABLE_DATA_DIR))
+}
+
+fn read_config(config_file: &Path) -> LibraryConfig {
+    let Ok(content) = std::fs::read_to_string(config_file) else {
+        return LibraryConfig::default();
+    };
+
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!("Ignoring invalid {}: {}", config_file.display(), e);
+        LibraryConfig::default()
+    })
+}
+
+fn write_config(config_file: &Path, config: &LibraryConfig) -> AppResult<()> {
+    if let Some(dir) = config_file.parent() {
+        std::fs::create_dir_all(dir).map_err(AppError::io("Failed to create config directory"))?;
+    }
+
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| AppError::internal("Failed to serialize library config", e))?;
+    std::fs::write(config_file, content).map_err(AppError::io("Failed to write library config"))?;
+
+    Ok(())
+}
+
+pub fn resolve<R: Runtime>(app_handle: &AppHandle<R>) -> AppResult<LibraryPaths> {
+    let path_error = |e: tauri::Error| AppError::internal("Failed to get app directories", e);
+
+    if cfg!(target_os = "android") || cfg!(target_os = "ios") {
+        let data_dir = if cfg!(target_os = "android") {
+            app_handle.path().app_data_dir().map_err(path_error)?
+        } else {
+            app_handle.path().document_dir().map_err(path_error)?
+        };
+
+        return Ok(LibraryPaths {
+            library_dir: data_dir.clone(),
+            database: profiles::database_path(&data_dir, &profiles::active_id(&data_dir)),
+            config_file: app_handle.path().app_config_dir().map_err(path_error)?.join(CONFIG_FILE),
+            data_dir,
+            mode: LibraryMode::Mobile,
+        });
+    }
+
+    Ok(resolve_desktop(
+        app_handle.path().app_data_dir().map_err(path_error)?,
+        app_handle.path().app_config_dir().map_err(path_error)?,
+    ))
+}
+
+// Desktop lookup without an app handle, also used by the command-line tool
+pub fn resolve_desktop(app_data_dir: PathBuf, app_config_dir: PathBuf) -> LibraryPaths {
+    let (data_dir, config_file, mode) = match portable_data_dir() {
+        Some(data_dir) => {
+            let config_file = data_dir.join(CONFIG_FILE);
+            (data_dir, config_file, LibraryMode::Portable)
+        }
+        None => (app_data_dir, app_config_dir.join(CONFIG_FILE), LibraryMode::Default),
+    };
+
+    let (library_dir, mode) = match read_config(&config_file).library_dir {
+        Some(library_dir) => (library_dir, LibraryMode::Custom),
+        None => (data_dir.clone(), mode),
+    };
+
+    LibraryPaths {
+        database: profiles::database_path(&library_dir, &profiles::active_id(&library_dir)),
+        library_dir,
+        data_dir,
+        config_file,
+        mode,
+    }
+}
+
+// Copies a live database into `target` in one consistent snapshot (WAL included)
+async fn copy_database(source: &Path, target: &Path) -> AppResult<()> {
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir).map_err(AppError::io("Failed to create library directory"))?;
+    }
+
+    // Without the passphrase an encrypted database can only be copied as a file
+    if encryption::is_encrypted(source) {
+        std::fs::copy(source, target).map_err(AppError::io("Failed to copy database"))?;
+        return Ok(());
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(SqliteConnectOptions::new().filename(source).read_only(true))
+        .await
+        .map_err(AppError::database("Failed to open database to copy"))?;
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(target.to_string_lossy().to_string())
+        .execute(&pool)
+        .await
+        .map_err(AppError::database("Failed to copy database"))?;
+
+    pool.close().await;
+    Ok(())
+}
+
+// First launch after the move to the app-data directory: bring the old database along.
+// The old file is left in place and becomes the default profile.
+pub async fn migrate_legacy_database(paths: &LibraryPaths) -> AppResult<()> {
+    let database = paths.library_dir.join(DATABASE_FILE);
+    if !matches!(paths.mode, LibraryMode::Default | LibraryMode::Portable) || database.exists() {
+        return Ok(());
+    }
+
+    let legacy = Path::new(LEGACY_DATABASE);
+    if !legacy.exists() {
+        return Ok(());
+    }
+
+    info!(
+        "Migrating database from {} to {}",
+        legacy.display(),
+        database.display()
+    );
+    copy_database(legacy, &database).await
+}
+
+pub fn location(paths: &LibraryPaths) -> LibraryLocation {
+    LibraryLocation {
+        database_path: paths.database.to_string_lossy().to_string(),
+        mode: paths.mode,
+        restart_required: false,
+    }
+}
+
+// Moves the library to `library_dir` (or back to the default location when None).
+// If the folder has no library yet, the current one (all profiles) is copied
+// there; an existing library in that folder is opened as is. Takes effect on
+// the next launch.
+pub async fn set_library_dir(
+    paths: &LibraryPaths,
+    library_dir: Option<PathBuf>,
+) -> AppResult<LibraryLocation> {
+    if paths.mode == LibraryMode::Mobile {
+        return Err(AppError::invalid_input("The library location cannot be changed on mobile"));
+    }
+
+    let target_dir = match &library_dir {
+        Some(dir) if dir.is_relative() => {
+            return Err(AppError::invalid_input("Library folder must be an absolute path"));
+        }
+        Some(dir) => dir.clone(),
+        None => paths.data_dir.clone(),
+    };
+
+    if target_dir != paths.library_dir && !target_dir.join(DATABASE_FILE).exists() {
+        for file in profiles::database_files(&paths.library_dir) {
+            let source = paths.library_dir.join(&file);
+            if source.exists() {
+                copy_database(&source, &target_dir.join(&file)).await?;
+            }
+        }
+
+        let registry = paths.library_dir.join(profiles::REGISTRY_FILE);
+        if registry.exists() {
+            std::fs::copy(&registry, target_dir.join(profiles::REGISTRY_FILE))
+                .map_err(AppError::io("Failed to copy profiles"))?;
+        }
+    }
+
+    // The active profile is read from the new folder on the next launch
+    let target = profiles::database_path(&target_dir, &profiles::active_id(&target_dir));
+
+    let mode = match (&library_dir, portable_data_dir()) {
+        (Some(_), _) => LibraryMode::Custom,
+        (None, Some(_)) => LibraryMode::Portable,
+        (None, None) => LibraryMode::Default,
+    };
+
+    write_config(&paths.config_file, &LibraryConfig { library_dir })?;
+    info!("Library moved to {}", target_dir.display());
+
+    Ok(LibraryLocation {
+        database_path: target.to_string_lossy().to_string(),
+        mode,
+        restart_required: target != paths.database,
+    })
+}
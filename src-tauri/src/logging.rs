@@ -0,0 +1,218 @@
+// Logging for the app: a global `tracing` subscriber that writes to stdout in
+// debug builds and to daily rotated files in the app log directory.
+//
+// Logging starts before the database and the app handle exist, so the filter
+// can be swapped at runtime (it is stored in the `settings` table) and the log
+// file is attached once the log directory is known. Events logged before that
+// only reach stdout.
+
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::error::{AppError, AppResult};
+
+pub const DEFAULT_FILTER: &str = "info,sqlx=warn";
+pub const FILTER_SETTING: &str = "log_filter";
+
+const LOG_FILE_PREFIX: &str = "wordecho";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7; // One per day
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogFile {
+    pub name: String,
+    pub size: u64, // Bytes
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogSettings {
+    pub filter: String,
+    pub directory: Option<String>,
+    pub files: Vec<LogFile>,
+}
+
+struct Logging {
+    filter: reload::Handle<EnvFilter, Registry>,
+    file: FileWriter,
+    directory: Mutex<Option<PathBuf>>,
+    // Flushes the background writer when dropped, so it lives as long as the process
+    guard: Mutex<Option<WorkerGuard>>,
+}
+
+static LOGGING: OnceLock<Logging> = OnceLock::new();
+
+// Writes to the log file once one is attached, and nowhere before that
+#[derive(Clone, Default)]
+struct FileWriter(std::sync::Arc<Mutex<Option<NonBlocking>>>);
+
+enum FileWriterHandle {
+    Attached(NonBlocking),
+    Detached,
+}
+
+impl std::io::Write for FileWriterHandle {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            FileWriterHandle::Attached(writer) => writer.write(buf),
+            FileWriterHandle::Detached => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            FileWriterHandle::Attached(writer) => writer.flush(),
+            FileWriterHandle::Detached => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for FileWriter {
+    type Writer = FileWriterHandle;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        match self.0.lock().ok().and_then(|w| w.clone()) {
+            Some(writer) => FileWriterHandle::Attached(writer),
+            None => FileWriterHandle::Detached,
+        }
+    }
+}
+
+// Installs the global subscriber. Safe to call more than once.
+pub fn init() {
+    LOGGING.get_or_init(|| {
+        let (filter, handle) = reload::Layer::new(EnvFilter::new(DEFAULT_FILTER));
+        let file = FileWriter::default();
+
+        let file_layer = tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(file.clone());
+
+        // Release builds have no console on Windows and Android discards stdout
+        let stdout_layer = cfg!(debug_assertions).then(tracing_subscriber::fmt::layer);
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(file_layer)
+            .with(stdout_layer)
+            .try_init()
+            .ok();
+
+        Logging {
+            filter: handle,
+            file,
+            directory: Mutex::new(None),
+            guard: Mutex::new(None),
+        }
+    });
+}
+
+// Starts writing log files into `directory`, keeping the last MAX_LOG_FILES days
+pub fn attach_log_dir(directory: &Path) -> AppResult<()> {
+    let logging = LOGGING.get().ok_or(AppError::internal("Logging is not initialized", "logging::init was not called"))?;
+
+    std::fs::create_dir_all(directory).map_err(AppError::io("Failed to create log directory"))?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(directory)
+        .map_err(|e| AppError::internal("Failed to open log file", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    *logging.file.0.lock().unwrap() = Some(writer);
+    *logging.guard.lock().unwrap() = Some(guard);
+    *logging.directory.lock().unwrap() = Some(directory.to_path_buf());
+
+    tracing::info!("Writing log files to {}", directory.display());
+    Ok(())
+}
+
+// Directives use the RUST_LOG syntax, e.g. "info,word_echo_lib::sync=debug"
+pub fn set_filter(directives: &str) -> AppResult<()> {
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| AppError::invalid_input(format!("Invalid log filter {}: {}", directives, e)))?;
+
+    if let Some(logging) = LOGGING.get() {
+        logging
+            .filter
+            .reload(filter)
+            .map_err(|e| AppError::internal("Failed to apply log filter", e))?;
+    }
+
+    Ok(())
+}
+
+pub async fn load_filter(pool: &SqlitePool) -> AppResult<String> {
+    let filter: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(FILTER_SETTING)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(filter.unwrap_or_else(|| DEFAULT_FILTER.to_string()))
+}
+
+// Applies the filter saved in the settings, falling back to the default
+pub async fn apply_saved_filter(pool: &SqlitePool) {
+    let filter = match load_filter(pool).await {
+        Ok(filter) => filter,
+        Err(e) => {
+            tracing::warn!("Failed to load log filter: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = set_filter(&filter) {
+        tracing::warn!("Ignoring saved log filter: {}", e);
+    }
+}
+
+pub async fn save_filter(pool: &SqlitePool, directives: &str) -> AppResult<()> {
+    set_filter(directives)?;
+
+    sqlx::query("INSERT INTO settings (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+        .bind(FILTER_SETTING)
+        .bind(directives)
+        .execute(pool)
+        .await
+        .map_err(AppError::database("Failed to save log filter"))?;
+
+    tracing::info!("Log filter set to {}", directives);
+    Ok(())
+}
+
+pub fn directory() -> Option<PathBuf> {
+    LOGGING.get()?.directory.lock().ok()?.clone()
+}
+
+pub fn list_files() -> AppResult<Vec<LogFile>> {
+    let Some(directory) = directory() else {
+        return Ok(Vec::new());
+    };
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&directory).map_err(AppError::io("Failed to read log directory"))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(LOG_FILE_PREFIX) {
+            continue;
+        }
+        files.push(LogFile {
+            name,
+            size: entry.metadata()?.len(),
+        });
+    }
+
+    // Newest first, the date is part of the file name
+    files.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(files)
+}
@@ -0,0 +1,84 @@
+// Rows shared by the commands, the repository functions and the CLI
+
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct Text {
+    pub id: i64, // SQLite INTEGER maps to i64 in Rust
+    pub title: String,
+    pub content: String,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub metadata: TextMetadata,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub last_opened_at: Option<String>,
+}
+
+// What the learner can set about a text besides its title and content
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, FromRow)]
+pub struct TextMetadata {
+    pub source: Option<String>, // URL or file the text was taken from
+    pub author: Option<String>,
+    pub level: Option<String>,  // CEFR level, A1 to C2
+    pub notes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct Word {
+    pub id: i64,
+    pub word: String,
+    pub status: Option<String>,
+    pub meaning: Option<String>, // Nullable field
+    pub stability: Option<f64>,  // Stability parameter from FSRS
+    pub difficulty: Option<f64>, // Difficulty parameter from FSRS
+    pub last_review_date: Option<String>, // Timestamp of the last review (ISO 8601)
+    pub next_review_date: Option<String>, // Timestamp for the next review (ISO 8601)
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TextWord {
+    pub text_id: i64,
+    pub word_id: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserPreference {
+    pub id: i64,
+    pub country_code: String,
+}
+
+// A group of texts read in order, like the lessons of a course or the chapters of a book
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: String,
+    pub text_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct Tag {
+    pub name: String,
+    pub text_count: i64,
+}
+
+// A text as shown in the library list: everything but the content
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TextSummary {
+    pub id: i64,
+    pub title: String,
+    pub collection_id: Option<i64>,
+    pub position: Option<i64>, // Order inside the collection
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub metadata: TextMetadata,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub last_opened_at: Option<String>,
+    pub new_count: i64,
+    pub seen_count: i64,
+    pub known_count: i64,
+}
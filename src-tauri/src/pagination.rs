@@ -0,0 +1,265 @@
+// Splits long texts into pages so the reader only loads one at a time.
+//
+// Pages are computed from the content when they are read, nothing is stored.
+// A page is made of whole paragraphs, up to the target number of words;
+// paragraphs longer than a page are split between sentences. A single
+// sentence longer than a page stays whole. Page boundaries only fall on
+// whitespace, so word offsets line up with the reading positions of
+// repo::progress.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{AppError, AppResult};
+use crate::models::Word;
+use crate::repo::texts;
+
+pub const DEFAULT_WORDS_PER_PAGE: usize = 250;
+const MIN_WORDS_PER_PAGE: usize = 20;
+const MAX_WORDS_PER_PAGE: usize = 5000;
+
+lazy_static! {
+    // End of a sentence: its punctuation, closing quotes or brackets and the following space
+    static ref SENTENCE_END_REGEX: Regex = Regex::new(r#"[.!?…]+["'»”’)\]]*\s+"#).unwrap();
+}
+
+// Part of the content, as byte positions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRange {
+    pub start: usize,
+    pub end: usize,
+    pub token_offset: i64, // Words before the page
+    pub token_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PageSummary {
+    pub index: usize,
+    pub token_offset: i64,
+    pub token_count: i64,
+    pub new_count: i64,
+    pub seen_count: i64,
+    pub known_count: i64,
+}
+
+// One page with the vocabulary entries of its words, in order of first appearance
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TextPage {
+    pub text_id: i64,
+    pub title: String,
+    pub page_count: usize,
+    #[serde(flatten)]
+    pub summary: PageSummary,
+    pub content: String,
+    pub words: Vec<Word>,
+}
+
+pub fn check_words_per_page(words_per_page: Option<usize>) -> AppResult<usize> {
+    let words_per_page = words_per_page.unwrap_or(DEFAULT_WORDS_PER_PAGE);
+    if !(MIN_WORDS_PER_PAGE..=MAX_WORDS_PER_PAGE).contains(&words_per_page) {
+        return Err(AppError::invalid_input(format!(
+            "Pages have to hold between {} and {} words",
+            MIN_WORDS_PER_PAGE, MAX_WORDS_PER_PAGE
+        )));
+    }
+    Ok(words_per_page)
+}
+
+// Paragraphs (lines) of the content, each ending after its line break
+fn paragraphs(content: &str) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    for (i, _) in content.match_indices('\n') {
+        blocks.push((start, i + 1));
+        start = i + 1;
+    }
+    if start < content.len() {
+        blocks.push((start, content.len()));
+    }
+    blocks
+}
+
+// Sentences of `content[start..end]`, as byte positions in `content`
+pub fn sentences(content: &str, (start, end): (usize, usize)) -> Vec<(usize, usize)> {
+    let mut blocks = Vec::new();
+    let mut sentence_start = start;
+    for mat in SENTENCE_END_REGEX.find_iter(&content[start..end]) {
+        blocks.push((sentence_start, start + mat.end()));
+        sentence_start = start + mat.end();
+    }
+    if sentence_start < end {
+        blocks.push((sentence_start, end));
+    }
+    blocks
+}
+
+pub fn paginate(content: &str, words_per_page: usize) -> Vec<PageRange> {
+    // Blocks that are never split, with their number of words
+    let mut blocks = Vec::new();
+    for paragraph in paragraphs(content) {
+        let words = texts::token_count(&content[paragraph.0..paragraph.1]);
+        if words as usize > words_per_page {
+            for sentence in sentences(content, paragraph) {
+                blocks.push((sentence, texts::token_count(&content[sentence.0..sentence.1])));
+            }
+        } else {
+            blocks.push((paragraph, words));
+        }
+    }
+
+    let mut pages = Vec::new();
+    let mut current: Option<PageRange> = None;
+    let mut token_offset = 0;
+    for ((start, end), words) in blocks {
+        match current.as_mut() {
+            Some(page) if page.token_count == 0 || page.token_count + words <= words_per_page as i64 => {
+                page.end = end;
+                page.token_count += words;
+            }
+            _ => {
+                if let Some(page) = current.take() {
+                    token_offset += page.token_count;
+                    pages.push(page);
+                }
+                current = Some(PageRange { start, end, token_offset, token_count: words });
+            }
+        }
+    }
+    if let Some(page) = current {
+        pages.push(page);
+    }
+
+    // Blank lines around a page belong to the break, not to the page
+    let mut pages: Vec<PageRange> = pages
+        .into_iter()
+        .map(|page| {
+            let slice = &content[page.start..page.end];
+            let start = page.start + (slice.len() - slice.trim_start().len());
+            let end = page.start + slice.trim_end().len();
+            PageRange { start, end: end.max(start), ..page }
+        })
+        .collect();
+
+    if pages.is_empty() {
+        pages.push(PageRange { start: 0, end: 0, token_offset: 0, token_count: 0 });
+    }
+    pages
+}
+
+// Status of every word linked to the text
+async fn word_statuses(pool: &SqlitePool, text_id: i64) -> AppResult<HashMap<String, String>> {
+    let rows: Vec<(String, Option<String>)> = sqlx::query_as(
+        r#"SELECT w.word, w.status FROM text_words tw JOIN words w ON w.id = tw.word_id WHERE tw.text_id = ?"#,
+    )
+    .bind(text_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(word, status)| (word, status.unwrap_or_default()))
+        .collect())
+}
+
+// Counts every distinct word of the page once, like texts::word_counts
+fn summarize(content: &str, index: usize, page: &PageRange, statuses: &HashMap<String, String>) -> PageSummary {
+    let mut seen = HashSet::new();
+    let mut summary = PageSummary {
+        index,
+        token_offset: page.token_offset,
+        token_count: page.token_count,
+        new_count: 0,
+        seen_count: 0,
+        known_count: 0,
+    };
+
+    for token in texts::tokens(&content[page.start..page.end]) {
+        if !seen.insert(token) {
+            continue;
+        }
+        match statuses.get(token).map(String::as_str) {
+            Some("new") => summary.new_count += 1,
+            Some("seen") => summary.seen_count += 1,
+            Some("known") => summary.known_count += 1,
+            _ => {}
+        }
+    }
+    summary
+}
+
+async fn load(pool: &SqlitePool, text_id: i64) -> AppResult<(String, String)> {
+    sqlx::query_as("SELECT title, content FROM texts WHERE id = ?")
+        .bind(text_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::not_found("Text not found"))
+}
+
+pub async fn pages(pool: &SqlitePool, text_id: i64, words_per_page: usize) -> AppResult<Vec<PageSummary>> {
+    let (_, content) = load(pool, text_id).await?;
+    let statuses = word_statuses(pool, text_id).await?;
+
+    Ok(paginate(&content, words_per_page)
+        .iter()
+        .enumerate()
+        .map(|(i, page)| summarize(&content, i, page, &statuses))
+        .collect())
+}
+
+fn find_page(ranges: &[PageRange], index: usize) -> AppResult<&PageRange> {
+    ranges.get(index).ok_or_else(|| {
+        AppError::not_found(format!("Page {} not found, the text has {} pages", index, ranges.len()))
+    })
+}
+
+fn distinct_tokens(content: &str) -> Vec<&str> {
+    let mut tokens = texts::tokens(content);
+    let mut seen = HashSet::new();
+    tokens.retain(|token| seen.insert(*token));
+    tokens
+}
+
+// Distinct words of one page, in order of first appearance
+pub async fn page_words(pool: &SqlitePool, text_id: i64, index: usize, words_per_page: usize) -> AppResult<Vec<String>> {
+    let (_, content) = load(pool, text_id).await?;
+    let ranges = paginate(&content, words_per_page);
+    let range = find_page(&ranges, index)?;
+
+    Ok(distinct_tokens(&content[range.start..range.end])
+        .into_iter()
+        .map(str::to_string)
+        .collect())
+}
+
+pub async fn page(pool: &SqlitePool, text_id: i64, index: usize, words_per_page: usize) -> AppResult<TextPage> {
+    let (title, content) = load(pool, text_id).await?;
+    let ranges = paginate(&content, words_per_page);
+    let range = find_page(&ranges, index)?;
+    let page_content = &content[range.start..range.end];
+    let tokens = distinct_tokens(page_content);
+
+    let tokens_json = serde_json::to_string(&tokens)
+        .map_err(|e| AppError::internal("Failed to serialize words", e))?;
+    let mut words = sqlx::query_as::<_, Word>(
+        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date
+        FROM words WHERE word IN (SELECT value FROM json_each(?))"#,
+    )
+    .bind(tokens_json)
+    .fetch_all(pool)
+    .await?;
+    let order: HashMap<&str, usize> = tokens.iter().enumerate().map(|(i, t)| (*t, i)).collect();
+    words.sort_by_key(|w| order.get(w.word.as_str()).copied().unwrap_or(usize::MAX));
+
+    let statuses = word_statuses(pool, text_id).await?;
+    Ok(TextPage {
+        text_id,
+        title,
+        page_count: ranges.len(),
+        summary: summarize(&content, index, range, &statuses),
+        content: page_content.to_string(),
+        words,
+    })
+}
@@ -0,0 +1,274 @@
+// Learner profiles: each profile has its own database inside the library
+// folder, so words, texts, preferences and sync settings never mix.
+//
+// The default profile uses the library's `db.sqlite`, which keeps libraries
+// from before profiles existed working as is. Other profiles live in
+// `profiles/<id>/db.sqlite`. The list of profiles and the active one are kept
+// in `profiles.json` next to the default database. Switching profiles takes
+// effect on restart since the database pool is opened once at startup.
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::error::{AppError, AppResult};
+use crate::library::DATABASE_FILE;
+use crate::repo;
+
+pub const DEFAULT_PROFILE_ID: &str = "default";
+const DEFAULT_PROFILE_NAME: &str = "Default";
+pub const REGISTRY_FILE: &str = "profiles.json";
+const PROFILES_DIR: &str = "profiles";
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Registry {
+    active: String,
+    profiles: Vec<Profile>,
+}
+
+impl Registry {
+    // A library without profiles.json only has the default profile
+    fn new(now: DateTime<Utc>) -> Self {
+        Registry {
+            active: DEFAULT_PROFILE_ID.to_string(),
+            profiles: vec![Profile {
+                id: DEFAULT_PROFILE_ID.to_string(),
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                created_at: repo::texts::timestamp(now),
+            }],
+        }
+    }
+
+    fn find(&self, id: &str) -> AppResult<&Profile> {
+        self.profiles
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| AppError::not_found(format!("Profile {} not found", id)))
+    }
+
+    fn check_name(&self, name: &str, except_id: Option<&str>) -> AppResult<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::invalid_input("Profile name cannot be empty"));
+        }
+        if name.chars().count() > MAX_NAME_LENGTH {
+            return Err(AppError::invalid_input(format!(
+                "Profile name cannot be longer than {} characters",
+                MAX_NAME_LENGTH
+            )));
+        }
+
+        let taken = self
+            .profiles
+            .iter()
+            .any(|p| Some(p.id.as_str()) != except_id && p.name.to_lowercase() == name.to_lowercase());
+        if taken {
+            return Err(AppError::invalid_input(format!("A profile named {} already exists", name)));
+        }
+
+        Ok(name.to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+// None if the library has no profiles.json yet
+fn parse_registry(library_dir: &Path) -> AppResult<Option<Registry>> {
+    let file = library_dir.join(REGISTRY_FILE);
+    let content = match std::fs::read_to_string(&file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(AppError::Io {
+                message: "Failed to read profiles".to_string(),
+                source: e,
+            })
+        }
+    };
+
+    let registry: Registry = serde_json::from_str(&content)
+        .map_err(|e| AppError::invalid_data(format!("Invalid {}, fix or remove it", file.display()), e))?;
+
+    // A hand-edited file could point at a profile that no longer exists
+    if registry.find(&registry.active).is_err() {
+        warn!("Active profile {} does not exist, using the default profile", registry.active);
+        return Ok(Some(Registry {
+            active: DEFAULT_PROFILE_ID.to_string(),
+            ..registry
+        }));
+    }
+
+    Ok(Some(registry))
+}
+
+// For looking things up only: an unreadable profiles.json counts as missing
+fn load_registry(library_dir: &Path) -> Option<Registry> {
+    parse_registry(library_dir).unwrap_or_else(|e| {
+        warn!("Ignoring the profile registry: {}", e);
+        None
+    })
+}
+
+// `now` is the creation time of the default profile if there is no registry yet
+fn read_registry(library_dir: &Path, now: DateTime<Utc>) -> Registry {
+    load_registry(library_dir).unwrap_or_else(|| Registry::new(now))
+}
+
+// For changes, which write the registry back. Replacing an unreadable file
+// with a fresh one would lose every other profile, so that is an error.
+fn registry_for_update(library_dir: &Path, now: DateTime<Utc>) -> AppResult<Registry> {
+    Ok(parse_registry(library_dir)?.unwrap_or_else(|| Registry::new(now)))
+}
+
+fn write_registry(library_dir: &Path, registry: &Registry) -> AppResult<()> {
+    std::fs::create_dir_all(library_dir).map_err(AppError::io("Failed to create library directory"))?;
+
+    let content = serde_json::to_string_pretty(registry)
+        .map_err(|e| AppError::internal("Failed to serialize profiles", e))?;
+    std::fs::write(library_dir.join(REGISTRY_FILE), content)
+        .map_err(AppError::io("Failed to write profiles"))?;
+
+    Ok(())
+}
+
+// Database of a profile, relative to the library folder
+fn database_file(id: &str) -> PathBuf {
+    if id == DEFAULT_PROFILE_ID {
+        PathBuf::from(DATABASE_FILE)
+    } else {
+        Path::new(PROFILES_DIR).join(id).join(DATABASE_FILE)
+    }
+}
+
+pub fn database_path(library_dir: &Path, id: &str) -> PathBuf {
+    library_dir.join(database_file(id))
+}
+
+// Databases of all profiles, relative to the library folder
+pub fn database_files(library_dir: &Path) -> Vec<PathBuf> {
+    match load_registry(library_dir) {
+        Some(registry) => registry.profiles.iter().map(|p| database_file(&p.id)).collect(),
+        None => vec![database_file(DEFAULT_PROFILE_ID)],
+    }
+}
+
+// Enough to find the database without building a whole profile
+pub fn active_id(library_dir: &Path) -> String {
+    load_registry(library_dir)
+        .map(|registry| registry.active)
+        .unwrap_or_else(|| DEFAULT_PROFILE_ID.to_string())
+}
+
+pub fn active(library_dir: &Path, now: DateTime<Utc>) -> Profile {
+    let registry = read_registry(library_dir, now);
+    registry
+        .find(&registry.active)
+        .cloned()
+        .unwrap_or_else(|_| Registry::new(now).profiles.remove(0))
+}
+
+// Looks a profile up by id or, ignoring case, by name
+pub fn find(library_dir: &Path, id_or_name: &str, now: DateTime<Utc>) -> AppResult<Profile> {
+    read_registry(library_dir, now)
+        .profiles
+        .into_iter()
+        .find(|p| p.id == id_or_name || p.name.to_lowercase() == id_or_name.to_lowercase())
+        .ok_or_else(|| AppError::not_found(format!("Profile {} not found", id_or_name)))
+}
+
+pub fn list(library_dir: &Path, now: DateTime<Utc>) -> ProfileList {
+    let registry = read_registry(library_dir, now);
+    ProfileList {
+        active: registry.active,
+        profiles: registry.profiles,
+    }
+}
+
+// The new profile starts with an empty database, created when it is first opened
+pub fn create(library_dir: &Path, name: &str, now: DateTime<Utc>) -> AppResult<Profile> {
+    let mut registry = registry_for_update(library_dir, now)?;
+    let name = registry.check_name(name, None)?;
+
+    let mut id = format!("p{}", now.timestamp_millis());
+    while registry.find(&id).is_ok() {
+        id.push('0');
+    }
+
+    let profile = Profile {
+        id,
+        name,
+        created_at: repo::texts::timestamp(now),
+    };
+    registry.profiles.push(profile.clone());
+    write_registry(library_dir, &registry)?;
+
+    info!("Created profile {} ({})", profile.name, profile.id);
+    Ok(profile)
+}
+
+pub fn rename(library_dir: &Path, id: &str, name: &str, now: DateTime<Utc>) -> AppResult<Profile> {
+    let mut registry = registry_for_update(library_dir, now)?;
+    registry.find(id)?;
+    let name = registry.check_name(name, Some(id))?;
+
+    let profile = registry
+        .profiles
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| AppError::not_found(format!("Profile {} not found", id)))?;
+    profile.name = name;
+    let profile = profile.clone();
+
+    write_registry(library_dir, &registry)?;
+    Ok(profile)
+}
+
+// Remembers `id` as the active profile; the caller restarts the app to open it
+pub fn switch(library_dir: &Path, id: &str, now: DateTime<Utc>) -> AppResult<Profile> {
+    let mut registry = registry_for_update(library_dir, now)?;
+    let profile = registry.find(id)?.clone();
+
+    registry.active = profile.id.clone();
+    write_registry(library_dir, &registry)?;
+
+    info!("Switching to profile {} ({})", profile.name, profile.id);
+    Ok(profile)
+}
+
+// Deletes a profile and its database. The active profile and the default
+// profile (whose database is the library itself) cannot be deleted.
+pub fn delete(library_dir: &Path, id: &str, now: DateTime<Utc>) -> AppResult<()> {
+    let mut registry = registry_for_update(library_dir, now)?;
+    registry.find(id)?;
+
+    if id == registry.active {
+        return Err(AppError::invalid_input("The active profile cannot be deleted"));
+    }
+    if id == DEFAULT_PROFILE_ID {
+        return Err(AppError::invalid_input("The default profile cannot be deleted"));
+    }
+
+    registry.profiles.retain(|p| p.id != id);
+    write_registry(library_dir, &registry)?;
+
+    let profile_dir = library_dir.join(PROFILES_DIR).join(id);
+    if profile_dir.exists() {
+        std::fs::remove_dir_all(&profile_dir).map_err(AppError::io("Failed to delete profile data"))?;
+    }
+
+    info!("Deleted profile {}", id);
+    Ok(())
+}
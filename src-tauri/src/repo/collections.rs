@@ -0,0 +1,224 @@
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use sqlx::SqliteExecutor;
+use tracing::info;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Collection;
+
+const MAX_NAME_LENGTH: usize = 128;
+
+async fn check_name<'e>(executor: impl SqliteExecutor<'e>, name: &str, except_id: Option<i64>) -> AppResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_input("Collection name cannot be empty"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::invalid_input(format!(
+            "Collection name cannot be longer than {} characters",
+            MAX_NAME_LENGTH
+        )));
+    }
+
+    // Names are unique ignoring case, see the NOCASE collation of the column
+    let taken: Option<i64> = sqlx::query_scalar("SELECT id FROM collections WHERE name = ? AND id IS NOT ?")
+        .bind(name)
+        .bind(except_id)
+        .fetch_optional(executor)
+        .await?;
+    if taken.is_some() {
+        return Err(AppError::invalid_input(format!("A collection named {} already exists", name)));
+    }
+
+    Ok(name.to_string())
+}
+
+// Empty descriptions are stored as NULL
+fn clean_description(description: Option<&str>) -> Option<String> {
+    description
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(str::to_string)
+}
+
+pub async fn list(pool: &SqlitePool) -> AppResult<Vec<Collection>> {
+    let collections = sqlx::query_as::<_, Collection>(
+        r#"
+        SELECT c.id, c.name, c.description, c.created_at, COUNT(t.id) AS text_count
+        FROM collections c
+        LEFT JOIN texts t ON t.collection_id = c.id
+        GROUP BY c.id
+        ORDER BY c.name COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(collections)
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> AppResult<Collection> {
+    find(pool, id).await
+}
+
+async fn find<'e>(executor: impl SqliteExecutor<'e>, id: i64) -> AppResult<Collection> {
+    sqlx::query_as::<_, Collection>(
+        r#"
+        SELECT c.id, c.name, c.description, c.created_at,
+            (SELECT COUNT(*) FROM texts t WHERE t.collection_id = c.id) AS text_count
+        FROM collections c
+        WHERE c.id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::not_found(format!("Collection {} not found", id)))
+}
+
+// `name`, or `name (2)`, `name (3)`, ... if a collection already has it
+pub async fn available_name(pool: &SqlitePool, name: &str) -> AppResult<String> {
+    let name = name.trim();
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    loop {
+        let taken: Option<i64> = sqlx::query_scalar("SELECT id FROM collections WHERE name = ?")
+            .bind(&candidate)
+            .fetch_optional(pool)
+            .await?;
+        if taken.is_none() {
+            return Ok(candidate);
+        }
+        n += 1;
+        candidate = format!("{} ({})", name, n);
+    }
+}
+
+pub async fn create(pool: &SqlitePool, name: &str, description: Option<&str>) -> AppResult<Collection> {
+    create_in(&mut *pool.acquire().await?, name, description).await
+}
+
+// create on the caller's connection, for imports filling the collection in
+// one transaction
+pub async fn create_in(conn: &mut SqliteConnection, name: &str, description: Option<&str>) -> AppResult<Collection> {
+    let name = check_name(&mut *conn, name, None).await?;
+
+    let id: i64 = sqlx::query_scalar("INSERT INTO collections (name, description) VALUES (?, ?) RETURNING id")
+        .bind(&name)
+        .bind(clean_description(description))
+        .fetch_one(&mut *conn)
+        .await?;
+    info!("Created collection {} ({})", name, id);
+
+    find(&mut *conn, id).await
+}
+
+pub async fn update(pool: &SqlitePool, id: i64, name: &str, description: Option<&str>) -> AppResult<Collection> {
+    get(pool, id).await?;
+    let name = check_name(pool, name, Some(id)).await?;
+
+    sqlx::query("UPDATE collections SET name = ?, description = ? WHERE id = ?")
+        .bind(&name)
+        .bind(clean_description(description))
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    get(pool, id).await
+}
+
+// The texts of the collection stay in the library, without a collection
+pub async fn delete(pool: &SqlitePool, id: i64) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE texts SET collection_id = NULL, position = NULL WHERE collection_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    let result = sqlx::query("DELETE FROM collections WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(format!("Collection {} not found", id)));
+    }
+
+    tx.commit().await?;
+    info!("Deleted collection {}", id);
+    Ok(())
+}
+
+// Moves a text into a collection, after the texts already in it. None takes
+// the text out of its collection.
+pub async fn set_text_collection(pool: &SqlitePool, text_id: i64, collection_id: Option<i64>) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    set_text_collection_in(&mut tx, text_id, collection_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// set_text_collection on the caller's connection
+pub async fn set_text_collection_in(
+    conn: &mut SqliteConnection,
+    text_id: i64,
+    collection_id: Option<i64>,
+) -> AppResult<()> {
+    if let Some(collection_id) = collection_id {
+        find(&mut *conn, collection_id).await?;
+    }
+
+    let current: Option<Option<i64>> = sqlx::query_scalar("SELECT collection_id FROM texts WHERE id = ?")
+        .bind(text_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let current = current.ok_or_else(|| AppError::not_found("Text not found"))?;
+    if current == collection_id {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE texts SET
+            collection_id = ?1,
+            position = CASE WHEN ?1 IS NULL THEN NULL
+                ELSE (SELECT COALESCE(MAX(position), 0) + 1 FROM texts WHERE collection_id = ?1) END
+        WHERE id = ?2
+        "#,
+    )
+    .bind(collection_id)
+    .bind(text_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// `text_ids` has to list every text of the collection exactly once, in the new order
+pub async fn reorder(pool: &SqlitePool, collection_id: i64, text_ids: &[i64]) -> AppResult<()> {
+    get(pool, collection_id).await?;
+
+    let mut current: Vec<i64> = sqlx::query_scalar("SELECT id FROM texts WHERE collection_id = ?")
+        .bind(collection_id)
+        .fetch_all(pool)
+        .await?;
+    let mut requested = text_ids.to_vec();
+    current.sort_unstable();
+    requested.sort_unstable();
+    if current != requested {
+        return Err(AppError::invalid_input(
+            "The new order has to contain every text of the collection exactly once",
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    for (i, text_id) in text_ids.iter().enumerate() {
+        sqlx::query("UPDATE texts SET position = ? WHERE id = ?")
+            .bind(i as i64 + 1)
+            .bind(text_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,359 @@
+// Offline dictionaries and their entries, see crate::dictionary for the file
+// formats they are imported from.
+
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::{FromRow, Row, SqliteConnection};
+use std::collections::HashSet;
+use tracing::info;
+
+use crate::dictionary::lookup::{fold_case, plain, stem};
+use crate::error::{AppError, AppResult};
+
+// Entries given their lookup keys at a time, see index_entries
+const INDEX_BATCH_SIZE: i64 = 2000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, FromRow)]
+pub struct Dictionary {
+    pub id: i64,
+    pub name: String,
+    pub format: String,
+    pub language: Option<String>,
+    pub entry_count: i64,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Inflection {
+    pub form: String,
+    pub tags: Vec<String>, // Like ["past", "singular"]
+}
+
+// An entry read from a dictionary file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NewEntry {
+    pub headword: String,
+    pub part_of_speech: Option<String>,
+    pub definitions: Vec<String>,
+    pub inflections: Vec<Inflection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DictionaryEntry {
+    pub id: i64,
+    pub dictionary_id: i64,
+    pub dictionary_name: String,
+    pub headword: String,
+    pub part_of_speech: Option<String>,
+    pub definitions: Vec<String>,
+    pub inflections: Vec<Inflection>,
+}
+
+const ENTRY_SELECT: &str = r#"
+    SELECT e.id, e.dictionary_id, d.name AS dictionary_name, e.headword, e.part_of_speech,
+        e.definitions, e.inflections
+    FROM dictionary_entries e
+    JOIN dictionaries d ON d.id = e.dictionary_id"#;
+
+fn entry_from_row(row: &SqliteRow) -> AppResult<DictionaryEntry> {
+    let definitions: String = row.try_get("definitions")?;
+    let inflections: String = row.try_get("inflections")?;
+    Ok(DictionaryEntry {
+        id: row.try_get("id")?,
+        dictionary_id: row.try_get("dictionary_id")?,
+        dictionary_name: row.try_get("dictionary_name")?,
+        headword: row.try_get("headword")?,
+        part_of_speech: row.try_get("part_of_speech")?,
+        definitions: serde_json::from_str(&definitions)
+            .map_err(|e| AppError::invalid_data("Invalid dictionary definitions", e))?,
+        inflections: serde_json::from_str(&inflections)
+            .map_err(|e| AppError::invalid_data("Invalid dictionary inflections", e))?,
+    })
+}
+
+pub async fn list(pool: &SqlitePool) -> AppResult<Vec<Dictionary>> {
+    let dictionaries = sqlx::query_as::<_, Dictionary>(
+        r#"SELECT id, name, format, language, entry_count, created_at FROM dictionaries ORDER BY name COLLATE NOCASE, id"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(dictionaries)
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> AppResult<Dictionary> {
+    sqlx::query_as::<_, Dictionary>(
+        r#"SELECT id, name, format, language, entry_count, created_at FROM dictionaries WHERE id = ?"#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::not_found(format!("Dictionary {} not found", id)))
+}
+
+// An empty dictionary, filled with add_entries
+pub async fn create(pool: &SqlitePool, name: &str, format: &str, language: Option<&str>) -> AppResult<Dictionary> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_input("Dictionary name cannot be empty"));
+    }
+
+    let id: i64 = sqlx::query_scalar("INSERT INTO dictionaries (name, format, language) VALUES (?, ?, ?) RETURNING id")
+        .bind(name)
+        .bind(format)
+        .bind(language)
+        .fetch_one(pool)
+        .await?;
+
+    get(pool, id).await
+}
+
+// Stores the lookup keys of an entry and its inflected forms
+async fn index_entry(
+    conn: &mut SqliteConnection,
+    entry_id: i64,
+    headword: &str,
+    inflections: &[Inflection],
+    language: Option<&str>,
+) -> AppResult<()> {
+    let plain_headword = plain(headword);
+    sqlx::query("UPDATE dictionary_entries SET folded_headword = ?, plain_headword = ?, stem = ? WHERE id = ?")
+        .bind(fold_case(headword))
+        .bind(&plain_headword)
+        .bind(stem(headword, language))
+        .bind(entry_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // A form is listed once per tag set ("ging": 1st and 3rd person), stored once
+    let mut plain_forms = HashSet::from([plain_headword]);
+    for inflection in inflections {
+        let plain_form = plain(&inflection.form);
+        if plain_form.is_empty() || !plain_forms.insert(plain_form.clone()) {
+            continue;
+        }
+        sqlx::query("INSERT INTO dictionary_forms (entry_id, form, plain_form) VALUES (?, ?, ?)")
+            .bind(entry_id)
+            .bind(inflection.form.trim())
+            .bind(plain_form)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+// Adds a batch of entries in one transaction
+pub async fn add_entries(pool: &SqlitePool, dictionary_id: i64, entries: &[NewEntry]) -> AppResult<()> {
+    let language = get(pool, dictionary_id).await?.language;
+    let mut tx = pool.begin().await?;
+
+    for entry in entries {
+        let definitions = serde_json::to_string(&entry.definitions)
+            .map_err(|e| AppError::internal("Failed to serialize definitions", e))?;
+        let inflections = serde_json::to_string(&entry.inflections)
+            .map_err(|e| AppError::internal("Failed to serialize inflections", e))?;
+        let id: i64 = sqlx::query_scalar(
+            r#"INSERT INTO dictionary_entries (dictionary_id, headword, part_of_speech, definitions, inflections) VALUES (?, ?, ?, ?, ?) RETURNING id"#,
+        )
+        .bind(dictionary_id)
+        .bind(&entry.headword)
+        .bind(&entry.part_of_speech)
+        .bind(definitions)
+        .bind(inflections)
+        .fetch_one(&mut *tx)
+        .await?;
+        index_entry(&mut tx, id, &entry.headword, &entry.inflections, language.as_deref()).await?;
+    }
+
+    sqlx::query("UPDATE dictionaries SET entry_count = entry_count + ? WHERE id = ?")
+        .bind(entries.len() as i64)
+        .bind(dictionary_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+// Gives their lookup keys to entries imported before lookups used them
+pub async fn index_entries(pool: &SqlitePool) -> AppResult<()> {
+    let mut indexed = 0;
+    loop {
+        let rows = sqlx::query(
+            r#"SELECT e.id, e.headword, e.inflections, d.language
+            FROM dictionary_entries e
+            JOIN dictionaries d ON d.id = e.dictionary_id
+            WHERE e.folded_headword IS NULL
+            LIMIT ?"#,
+        )
+        .bind(INDEX_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+        if rows.is_empty() {
+            break;
+        }
+
+        let mut tx = pool.begin().await?;
+        for row in &rows {
+            let inflections: String = row.try_get("inflections")?;
+            let inflections: Vec<Inflection> = serde_json::from_str(&inflections)
+                .map_err(|e| AppError::invalid_data("Invalid dictionary inflections", e))?;
+            let headword: String = row.try_get("headword")?;
+            let language: Option<String> = row.try_get("language")?;
+            index_entry(&mut tx, row.try_get("id")?, &headword, &inflections, language.as_deref()).await?;
+        }
+        tx.commit().await?;
+        indexed += rows.len();
+    }
+
+    if indexed > 0 {
+        info!("Indexed {} dictionary entries for lookups", indexed);
+    }
+    Ok(())
+}
+
+pub async fn delete(pool: &SqlitePool, id: i64) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM dictionary_entries WHERE dictionary_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    let result = sqlx::query("DELETE FROM dictionaries WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(format!("Dictionary {} not found", id)));
+    }
+
+    tx.commit().await?;
+    info!("Deleted dictionary {}", id);
+    Ok(())
+}
+
+async fn entries_where(
+    pool: &SqlitePool,
+    condition: &str,
+    key: &str,
+    dictionary_id: Option<i64>,
+) -> AppResult<Vec<DictionaryEntry>> {
+    let rows = sqlx::query(&format!(
+        "{} WHERE {} AND (?2 IS NULL OR e.dictionary_id = ?2) ORDER BY d.id, e.id",
+        ENTRY_SELECT, condition
+    ))
+    .bind(key)
+    .bind(dictionary_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(entry_from_row).collect()
+}
+
+// Entries with exactly this headword, from one dictionary or all of them
+pub async fn by_headword(pool: &SqlitePool, headword: &str, dictionary_id: Option<i64>) -> AppResult<Vec<DictionaryEntry>> {
+    entries_where(pool, "e.headword = ?1", headword, dictionary_id).await
+}
+
+// Entries by their lowercase headword, see dictionary::lookup::fold_case
+pub async fn by_folded(pool: &SqlitePool, folded: &str, dictionary_id: Option<i64>) -> AppResult<Vec<DictionaryEntry>> {
+    entries_where(pool, "e.folded_headword = ?1", folded, dictionary_id).await
+}
+
+// Entries by their headword without diacritics, see dictionary::lookup::plain
+pub async fn by_plain(pool: &SqlitePool, plain: &str, dictionary_id: Option<i64>) -> AppResult<Vec<DictionaryEntry>> {
+    entries_where(pool, "e.plain_headword = ?1", plain, dictionary_id).await
+}
+
+// Entries of dictionaries in `language` whose headword has this stem
+pub async fn by_stem(
+    pool: &SqlitePool,
+    stem: &str,
+    language: &str,
+    dictionary_id: Option<i64>,
+) -> AppResult<Vec<DictionaryEntry>> {
+    let rows = sqlx::query(&format!(
+        "{} WHERE e.stem = ?1 AND d.language = ?2 AND (?3 IS NULL OR e.dictionary_id = ?3) ORDER BY d.id, e.id",
+        ENTRY_SELECT
+    ))
+    .bind(stem)
+    .bind(language)
+    .bind(dictionary_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(entry_from_row).collect()
+}
+
+// Entries with an inflected form of this plain key, with the form as listed
+pub async fn by_form(
+    pool: &SqlitePool,
+    plain_form: &str,
+    dictionary_id: Option<i64>,
+) -> AppResult<Vec<(DictionaryEntry, String)>> {
+    let rows = sqlx::query(&format!(
+        r#"{}
+        JOIN (SELECT entry_id, MIN(form) AS form FROM dictionary_forms WHERE plain_form = ?1 GROUP BY entry_id) f
+            ON f.entry_id = e.id
+        WHERE ?2 IS NULL OR e.dictionary_id = ?2
+        ORDER BY d.id, e.id"#,
+        ENTRY_SELECT.replace("e.inflections", "e.inflections, f.form")
+    ))
+    .bind(plain_form)
+    .bind(dictionary_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| Ok((entry_from_row(row)?, row.try_get("form")?)))
+        .collect()
+}
+
+// Up to `limit` entries whose plain headword starts with `prefix` and is
+// within `max_distance` of `length` characters long, closest lengths first
+pub async fn by_plain_prefix(
+    pool: &SqlitePool,
+    prefix: &str,
+    length: usize,
+    max_distance: usize,
+    dictionary_id: Option<i64>,
+    limit: i64,
+) -> AppResult<Vec<DictionaryEntry>> {
+    // Keys are compared as bytes, nothing that starts with the prefix sorts after this
+    let end = format!("{}{}", prefix, char::MAX);
+    // Every edit changes the length by at most one
+    let rows = sqlx::query(&format!(
+        r#"{} WHERE e.plain_headword >= ?1 AND e.plain_headword < ?2 AND (?3 IS NULL OR e.dictionary_id = ?3)
+            AND length(e.plain_headword) BETWEEN ?4 - ?5 AND ?4 + ?5
+        ORDER BY abs(length(e.plain_headword) - ?4), d.id, e.id
+        LIMIT ?6"#,
+        ENTRY_SELECT
+    ))
+    .bind(prefix)
+    .bind(end)
+    .bind(dictionary_id)
+    .bind(length as i64)
+    .bind(max_distance as i64)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter().map(entry_from_row).collect()
+}
+
+// Plain keys of the headwords and inflected forms of these entries, the
+// forms that share their lemma
+pub async fn entry_forms(pool: &SqlitePool, entry_ids: &[i64]) -> AppResult<Vec<String>> {
+    let ids = serde_json::to_string(entry_ids).map_err(|e| AppError::internal("Failed to serialize ids", e))?;
+    let forms = sqlx::query_scalar(
+        r#"SELECT plain_headword FROM dictionary_entries
+        WHERE id IN (SELECT value FROM json_each(?1)) AND plain_headword IS NOT NULL
+        UNION
+        SELECT plain_form FROM dictionary_forms WHERE entry_id IN (SELECT value FROM json_each(?1))"#,
+    )
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(forms)
+}
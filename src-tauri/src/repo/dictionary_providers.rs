@@ -0,0 +1,414 @@
+// Websites the reader looks selected words up on (dict.cc, DeepL, ...), see
+// migrations/0010_dictionary_providers.sql. Each one is a URL template that
+// gets the word and the language codes the site uses.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::Row;
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::error::{AppError, AppResult};
+use crate::repo::preferences;
+
+const MAX_NAME_LENGTH: usize = 64;
+const DEFAULT_TARGET_LANGUAGE: &str = "en";
+
+lazy_static! {
+    static ref PLACEHOLDER_REGEX: Regex = Regex::new(r"\{([^{}]*)\}").unwrap();
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DictionaryProvider {
+    pub id: i64,
+    pub uid: String, // Same on every device, for sync
+    pub name: String,
+    pub url_template: String,
+    pub languages: BTreeMap<String, String>, // Language of the app -> code of the site
+    pub target_language: String,
+    pub position: i64,
+    pub embedded: bool,
+    pub created_at: String,
+}
+
+// What the user edits
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProviderInput {
+    pub name: String,
+    pub url_template: String,
+    #[serde(default)]
+    pub languages: BTreeMap<String, String>,
+    #[serde(default)]
+    pub target_language: Option<String>,
+    #[serde(default)]
+    pub embedded: bool,
+}
+
+// A provider's page for one word
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DictionaryLink {
+    pub provider_id: i64,
+    pub name: String,
+    pub url: String,
+    pub embedded: bool,
+}
+
+struct DefaultProvider {
+    uid: &'static str,
+    name: &'static str,
+    url_template: &'static str,
+    languages: &'static [(&'static str, &'static str)],
+    embedded: bool,
+}
+
+// Keyed by the country codes the language picker saves
+const DEFAULT_PROVIDERS: &[DefaultProvider] = &[
+    DefaultProvider {
+        uid: "dict-cc",
+        name: "dict.cc",
+        url_template: "https://m.dict.cc/{src}/{word}.html",
+        languages: &[
+            ("de", "deutsch-englisch"),
+            ("dk", "danish-english"),
+            ("es", "spanish-english"),
+            ("fr", "french-english"),
+            ("it", "italian-english"),
+            ("nl", "dutch-english"),
+            ("no", "norwegian-english"),
+            ("pl", "polish-english"),
+            ("pt", "portuguese-english"),
+            ("ru", "russian-english"),
+            ("se", "swedish-english"),
+            ("tr", "turkish-english"),
+        ],
+        embedded: true,
+    },
+    DefaultProvider {
+        uid: "deepl",
+        name: "DeepL",
+        url_template: "https://www.deepl.com/en/translator#{src}/{dst}/{word}",
+        languages: &[
+            ("de", "de"),
+            ("dk", "da"),
+            ("es", "es"),
+            ("fr", "fr"),
+            ("it", "it"),
+            ("nl", "nl"),
+            ("no", "nb"),
+            ("pl", "pl"),
+            ("pt", "pt"),
+            ("ru", "ru"),
+            ("se", "sv"),
+            ("tr", "tr"),
+        ],
+        embedded: false,
+    },
+    DefaultProvider {
+        uid: "google-translate",
+        name: "Google Translate",
+        url_template: "https://translate.google.com/?sl={src}&tl={dst}&text={word}&op=translate",
+        languages: &[
+            ("de", "de"),
+            ("dk", "da"),
+            ("es", "es"),
+            ("fr", "fr"),
+            ("it", "it"),
+            ("nl", "nl"),
+            ("no", "no"),
+            ("pl", "pl"),
+            ("pt", "pt"),
+            ("ru", "ru"),
+            ("se", "sv"),
+            ("tr", "tr"),
+        ],
+        embedded: false,
+    },
+    // There is no Danish or Swedish edition
+    DefaultProvider {
+        uid: "thefreedictionary",
+        name: "TheFreeDictionary",
+        url_template: "https://{src}.thefreedictionary.com/{word}",
+        languages: &[
+            ("de", "de"),
+            ("es", "es"),
+            ("fr", "fr"),
+            ("it", "it"),
+            ("nl", "nl"),
+            ("no", "no"),
+            ("pl", "pl"),
+            ("pt", "pt"),
+            ("ru", "ru"),
+            ("tr", "tr"),
+        ],
+        embedded: false,
+    },
+];
+
+const PROVIDER_SELECT: &str = r#"
+    SELECT id, uid, name, url_template, languages, target_language, position, embedded, created_at
+    FROM dictionary_providers"#;
+
+fn provider_from_row(row: &SqliteRow) -> AppResult<DictionaryProvider> {
+    let languages: String = row.try_get("languages")?;
+    Ok(DictionaryProvider {
+        id: row.try_get("id")?,
+        uid: row.try_get("uid")?,
+        name: row.try_get("name")?,
+        url_template: row.try_get("url_template")?,
+        languages: serde_json::from_str(&languages)
+            .map_err(|e| AppError::invalid_data("Invalid dictionary provider languages", e))?,
+        target_language: row.try_get("target_language")?,
+        position: row.try_get("position")?,
+        embedded: row.try_get("embedded")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+// The input trimmed, with lowercase language codes
+fn check_input(input: &ProviderInput) -> AppResult<ProviderInput> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_input("Provider name cannot be empty"));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::invalid_input(format!(
+            "Provider name cannot be longer than {} characters",
+            MAX_NAME_LENGTH
+        )));
+    }
+
+    let url_template = input.url_template.trim();
+    if !url_template.starts_with("https://") && !url_template.starts_with("http://") {
+        return Err(AppError::invalid_input("The address has to start with https:// or http://"));
+    }
+    if !url_template.contains("{word}") {
+        return Err(AppError::invalid_input("The address needs a {word} placeholder"));
+    }
+    if let Some(unknown) = PLACEHOLDER_REGEX
+        .captures_iter(url_template)
+        .map(|c| c[1].to_string())
+        .find(|p| !["word", "src", "dst"].contains(&p.as_str()))
+    {
+        return Err(AppError::invalid_input(format!(
+            "Unknown placeholder {{{}}}, use {{word}}, {{src}} or {{dst}}",
+            unknown
+        )));
+    }
+
+    let mut languages = BTreeMap::new();
+    for (language, code) in &input.languages {
+        let (language, code) = (language.trim().to_lowercase(), code.trim());
+        if language.is_empty() || code.is_empty() {
+            return Err(AppError::invalid_input("Language codes cannot be empty"));
+        }
+        languages.insert(language, code.to_string());
+    }
+
+    let target_language = input.target_language.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    Ok(ProviderInput {
+        name: name.to_string(),
+        url_template: url_template.to_string(),
+        languages,
+        target_language: Some(target_language.unwrap_or(DEFAULT_TARGET_LANGUAGE).to_string()),
+        embedded: input.embedded,
+    })
+}
+
+fn languages_json(languages: &BTreeMap<String, String>) -> AppResult<String> {
+    serde_json::to_string(languages).map_err(|e| AppError::internal("Failed to serialize languages", e))
+}
+
+// In the order they are shown in
+pub async fn list(pool: &SqlitePool) -> AppResult<Vec<DictionaryProvider>> {
+    let rows = sqlx::query(&format!("{} ORDER BY position, id", PROVIDER_SELECT))
+        .fetch_all(pool)
+        .await?;
+
+    rows.iter().map(provider_from_row).collect()
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> AppResult<DictionaryProvider> {
+    let row = sqlx::query(&format!("{} WHERE id = ?", PROVIDER_SELECT))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("Dictionary provider {} not found", id)))?;
+
+    provider_from_row(&row)
+}
+
+// Added after the others
+pub async fn create(pool: &SqlitePool, input: &ProviderInput) -> AppResult<DictionaryProvider> {
+    let input = check_input(input)?;
+
+    let id: i64 = sqlx::query_scalar(
+        r#"INSERT INTO dictionary_providers (name, url_template, languages, target_language, position, embedded)
+        VALUES (?, ?, ?, ?, (SELECT COALESCE(MAX(position), 0) + 1 FROM dictionary_providers), ?)
+        RETURNING id"#,
+    )
+    .bind(&input.name)
+    .bind(&input.url_template)
+    .bind(languages_json(&input.languages)?)
+    .bind(&input.target_language)
+    .bind(input.embedded)
+    .fetch_one(pool)
+    .await?;
+    info!("Added dictionary provider {} ({})", input.name, id);
+
+    get(pool, id).await
+}
+
+pub async fn update(pool: &SqlitePool, id: i64, input: &ProviderInput) -> AppResult<DictionaryProvider> {
+    get(pool, id).await?;
+    let input = check_input(input)?;
+
+    sqlx::query(
+        r#"UPDATE dictionary_providers SET name = ?, url_template = ?, languages = ?, target_language = ?, embedded = ?
+        WHERE id = ?"#,
+    )
+    .bind(&input.name)
+    .bind(&input.url_template)
+    .bind(languages_json(&input.languages)?)
+    .bind(&input.target_language)
+    .bind(input.embedded)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    get(pool, id).await
+}
+
+pub async fn delete(pool: &SqlitePool, id: i64) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM dictionary_providers WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(format!("Dictionary provider {} not found", id)));
+    }
+
+    info!("Deleted dictionary provider {}", id);
+    Ok(())
+}
+
+// `provider_ids` has to list every provider exactly once, in the new order
+pub async fn reorder(pool: &SqlitePool, provider_ids: &[i64]) -> AppResult<()> {
+    let mut current: Vec<i64> = sqlx::query_scalar("SELECT id FROM dictionary_providers")
+        .fetch_all(pool)
+        .await?;
+    let mut requested = provider_ids.to_vec();
+    current.sort_unstable();
+    requested.sort_unstable();
+    if current != requested {
+        return Err(AppError::invalid_input(
+            "The new order has to contain every dictionary provider exactly once",
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    for (i, provider_id) in provider_ids.iter().enumerate() {
+        sqlx::query("UPDATE dictionary_providers SET position = ? WHERE id = ?")
+            .bind(i as i64 + 1)
+            .bind(provider_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Adds the shipped providers back, as shipped. Deleted ones go last, the
+// ones added by the user are kept.
+pub async fn restore_defaults(pool: &SqlitePool) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    for provider in DEFAULT_PROVIDERS {
+        let languages: BTreeMap<String, String> = provider
+            .languages
+            .iter()
+            .map(|(language, code)| (language.to_string(), code.to_string()))
+            .collect();
+        sqlx::query(
+            r#"INSERT INTO dictionary_providers (uid, name, url_template, languages, target_language, position, embedded)
+            VALUES (?1, ?2, ?3, ?4, ?5, (SELECT COALESCE(MAX(position), 0) + 1 FROM dictionary_providers), ?6)
+            ON CONFLICT(uid) DO UPDATE SET
+                name = excluded.name,
+                url_template = excluded.url_template,
+                languages = excluded.languages,
+                target_language = excluded.target_language,
+                embedded = excluded.embedded"#,
+        )
+        .bind(provider.uid)
+        .bind(provider.name)
+        .bind(provider.url_template)
+        .bind(languages_json(&languages)?)
+        .bind(DEFAULT_TARGET_LANGUAGE)
+        .bind(provider.embedded)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+// Percent-encodes everything but unreserved characters, so the word fits
+// in a path, a query or a fragment
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// The provider's address for `word` in `language`, None if it does not
+// support the language
+pub fn link_url(provider: &DictionaryProvider, word: &str, language: Option<&str>) -> Option<String> {
+    let language = language.map(|l| l.trim().to_lowercase());
+    let src = if provider.languages.is_empty() {
+        language
+    } else {
+        provider.languages.get(language.as_deref()?).cloned()
+    };
+    if src.is_none() && provider.url_template.contains("{src}") {
+        return None;
+    }
+
+    let url = PLACEHOLDER_REGEX.replace_all(&provider.url_template, |c: &regex::Captures| match &c[1] {
+        "word" => encode(word.trim()),
+        "src" => src.clone().unwrap_or_default(),
+        "dst" => provider.target_language.clone(),
+        _ => c[0].to_string(),
+    });
+    Some(url.into_owned())
+}
+
+// Links for a word in the providers' order, in `language` or the language
+// picked in the app
+pub async fn links(pool: &SqlitePool, word: &str, language: Option<&str>) -> AppResult<Vec<DictionaryLink>> {
+    let language = match language {
+        Some(language) => Some(language.to_string()),
+        None => preferences::country_code(pool).await?,
+    };
+
+    Ok(list(pool)
+        .await?
+        .into_iter()
+        .filter_map(|provider| {
+            let url = link_url(&provider, word, language.as_deref())?;
+            Some(DictionaryLink {
+                provider_id: provider.id,
+                name: provider.name,
+                url,
+                embedded: provider.embedded,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,13 @@
+// Database access that only needs a plain pool, shared by the Tauri
+// commands and the command-line tool.
+
+pub mod collections;
+pub mod dictionaries;
+pub mod dictionary_providers;
+pub mod preferences;
+pub mod progress;
+pub mod reading_events;
+pub mod tags;
+pub mod texts;
+pub mod timestamps;
+pub mod words;
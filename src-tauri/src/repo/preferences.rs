@@ -0,0 +1,32 @@
+use sqlx::sqlite::SqlitePool;
+
+use crate::error::AppResult;
+
+// Only one preference row is kept
+pub async fn save_country_code(pool: &SqlitePool, country_code: &str) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM user_preferences")
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#"INSERT INTO user_preferences (country_code) VALUES (?)"#,
+        country_code
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn country_code(pool: &SqlitePool) -> AppResult<Option<String>> {
+    let preference = sqlx::query!(
+        r#"SELECT country_code FROM user_preferences LIMIT 1"#
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(preference.map(|p| p.country_code))
+}
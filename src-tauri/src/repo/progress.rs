@@ -0,0 +1,215 @@
+// Reading position, completion and bookmarks per text. Positions are word
+// offsets into the content, see texts::token_count.
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use sqlx::FromRow;
+
+use crate::error::{AppError, AppResult};
+use crate::repo::texts;
+
+const MAX_BOOKMARK_NAME_LENGTH: usize = 128;
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ReadingProgress {
+    pub text_id: i64,
+    pub token_offset: i64,
+    pub completed: bool,
+    pub completed_at: Option<String>,
+    pub updated_at: Option<String>, // None until the text is first read
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub token_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct Bookmark {
+    pub id: i64,
+    pub text_id: i64,
+    pub name: String,
+    pub token_offset: i64,
+    pub created_at: String,
+}
+
+// A started but unfinished text, for picking up where the learner stopped
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ContinueReading {
+    pub text_id: i64,
+    pub title: String,
+    pub collection_id: Option<i64>,
+    pub token_offset: i64,
+    pub updated_at: String,
+    #[sqlx(skip)]
+    pub token_count: i64,
+}
+
+async fn text_content(pool: &SqlitePool, text_id: i64) -> AppResult<String> {
+    sqlx::query_scalar("SELECT content FROM texts WHERE id = ?")
+        .bind(text_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::not_found("Text not found"))
+}
+
+// Texts that were never opened are at the start and not completed
+pub async fn get(pool: &SqlitePool, text_id: i64) -> AppResult<ReadingProgress> {
+    let content = text_content(pool, text_id).await?;
+
+    let progress = sqlx::query_as::<_, ReadingProgress>(
+        r#"SELECT text_id, token_offset, completed, completed_at, updated_at FROM reading_progress WHERE text_id = ?"#,
+    )
+    .bind(text_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let mut progress = progress.unwrap_or(ReadingProgress {
+        text_id,
+        token_offset: 0,
+        completed: false,
+        completed_at: None,
+        updated_at: None,
+        token_count: 0,
+    });
+    progress.token_count = texts::token_count(&content);
+    Ok(progress)
+}
+
+// Offsets past the end (the content may have been shortened since) are clamped
+async fn check_offset(pool: &SqlitePool, text_id: i64, token_offset: i64) -> AppResult<i64> {
+    if token_offset < 0 {
+        return Err(AppError::invalid_input("Reading position cannot be negative"));
+    }
+    let token_count = texts::token_count(&text_content(pool, text_id).await?);
+    Ok(token_offset.min((token_count - 1).max(0)))
+}
+
+pub async fn save_position(
+    pool: &SqlitePool,
+    text_id: i64,
+    token_offset: i64,
+    now: DateTime<Utc>,
+) -> AppResult<ReadingProgress> {
+    let token_offset = check_offset(pool, text_id, token_offset).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO reading_progress (text_id, token_offset, updated_at) VALUES (?1, ?2, ?3)
+        ON CONFLICT(text_id) DO UPDATE SET token_offset = excluded.token_offset, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(text_id)
+    .bind(token_offset)
+    .bind(texts::timestamp(now))
+    .execute(pool)
+    .await?;
+
+    get(pool, text_id).await
+}
+
+// Completed texts drop out of continue reading; the position is kept
+pub async fn set_completed(
+    pool: &SqlitePool,
+    text_id: i64,
+    completed: bool,
+    now: DateTime<Utc>,
+) -> AppResult<ReadingProgress> {
+    text_content(pool, text_id).await?;
+    let now = texts::timestamp(now);
+
+    sqlx::query(
+        r#"
+        INSERT INTO reading_progress (text_id, completed, completed_at, updated_at) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(text_id) DO UPDATE SET
+            completed = excluded.completed, completed_at = excluded.completed_at, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(text_id)
+    .bind(completed)
+    .bind(completed.then_some(&now))
+    .bind(&now)
+    .execute(pool)
+    .await?;
+
+    get(pool, text_id).await
+}
+
+// Most recently read unfinished texts first
+pub async fn continue_reading(pool: &SqlitePool, limit: i64) -> AppResult<Vec<ContinueReading>> {
+    let rows = sqlx::query_as::<_, ContinueReading>(
+        r#"
+        SELECT p.text_id, t.title, t.collection_id, p.token_offset, p.updated_at
+        FROM reading_progress p
+        JOIN texts t ON t.id = p.text_id
+        WHERE p.completed = 0
+        ORDER BY p.updated_at DESC
+        LIMIT ?
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let mut texts = Vec::with_capacity(rows.len());
+    for mut row in rows {
+        row.token_count = texts::token_count(&text_content(pool, row.text_id).await?);
+        texts.push(row);
+    }
+    Ok(texts)
+}
+
+pub async fn bookmarks(pool: &SqlitePool, text_id: i64) -> AppResult<Vec<Bookmark>> {
+    let bookmarks = sqlx::query_as::<_, Bookmark>(
+        r#"SELECT id, text_id, name, token_offset, created_at FROM bookmarks WHERE text_id = ? ORDER BY token_offset, id"#,
+    )
+    .bind(text_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(bookmarks)
+}
+
+pub async fn add_bookmark(
+    pool: &SqlitePool,
+    text_id: i64,
+    name: &str,
+    token_offset: i64,
+    now: DateTime<Utc>,
+) -> AppResult<Bookmark> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_input("Bookmark name cannot be empty"));
+    }
+    if name.chars().count() > MAX_BOOKMARK_NAME_LENGTH {
+        return Err(AppError::invalid_input(format!(
+            "Bookmark name cannot be longer than {} characters",
+            MAX_BOOKMARK_NAME_LENGTH
+        )));
+    }
+    let token_offset = check_offset(pool, text_id, token_offset).await?;
+
+    let bookmark = sqlx::query_as::<_, Bookmark>(
+        r#"INSERT INTO bookmarks (text_id, name, token_offset, created_at) VALUES (?, ?, ?, ?)
+        RETURNING id, text_id, name, token_offset, created_at"#,
+    )
+    .bind(text_id)
+    .bind(name)
+    .bind(token_offset)
+    .bind(texts::timestamp(now))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(bookmark)
+}
+
+pub async fn delete_bookmark(pool: &SqlitePool, bookmark_id: i64) -> AppResult<()> {
+    let result = sqlx::query("DELETE FROM bookmarks WHERE id = ?")
+        .bind(bookmark_id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Bookmark not found"));
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+// "Finish page": every word of a page (or text) that is still new when the
+// learner moves on counts as known. The change is recorded as a reading
+// event with the previous state of each word, so it can be undone.
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use sqlx::FromRow;
+use tracing::info;
+
+use crate::error::{AppError, AppResult};
+use crate::pagination;
+use crate::repo::texts;
+
+pub const FINISH_PAGE: &str = "finish_page";
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ReadingEvent {
+    pub id: i64,
+    pub text_id: i64,
+    pub kind: String,
+    pub page: Option<i64>, // None when the whole text was finished
+    pub words_per_page: Option<i64>,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+    pub word_count: i64, // Words the event changed
+}
+
+const EVENT_SELECT: &str = r#"
+    SELECT e.id, e.text_id, e.kind, e.page, e.words_per_page, e.created_at, e.undone_at,
+        (SELECT COUNT(*) FROM reading_event_words ew WHERE ew.event_id = e.id) AS word_count
+    FROM reading_events e"#;
+
+pub async fn get(pool: &SqlitePool, event_id: i64) -> AppResult<ReadingEvent> {
+    sqlx::query_as::<_, ReadingEvent>(&format!("{} WHERE e.id = ?", EVENT_SELECT))
+        .bind(event_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(AppError::not_found("Reading event not found"))
+}
+
+// Most recent first
+pub async fn list(pool: &SqlitePool, text_id: i64) -> AppResult<Vec<ReadingEvent>> {
+    let events = sqlx::query_as::<_, ReadingEvent>(&format!(
+        "{} WHERE e.text_id = ? ORDER BY e.created_at DESC, e.id DESC",
+        EVENT_SELECT
+    ))
+    .bind(text_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(events)
+}
+
+// Marks the still new words of a page (`page` is Some((index, words_per_page)))
+// or of the whole text as known, in one transaction
+pub async fn finish_page(
+    pool: &SqlitePool,
+    text_id: i64,
+    page: Option<(usize, usize)>,
+    now: DateTime<Utc>,
+) -> AppResult<ReadingEvent> {
+    // The words of the page, or None for all words linked to the text
+    let page_words = match page {
+        Some((index, words_per_page)) => {
+            let words = pagination::page_words(pool, text_id, index, words_per_page).await?;
+            Some(serde_json::to_string(&words).map_err(|e| AppError::internal("Failed to serialize words", e))?)
+        }
+        None => {
+            let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM texts WHERE id = ?")
+                .bind(text_id)
+                .fetch_optional(pool)
+                .await?;
+            exists.ok_or(AppError::not_found("Text not found"))?;
+            None
+        }
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let event_id: i64 = sqlx::query_scalar(
+        r#"INSERT INTO reading_events (text_id, kind, page, words_per_page, created_at) VALUES (?, ?, ?, ?, ?) RETURNING id"#,
+    )
+    .bind(text_id)
+    .bind(FINISH_PAGE)
+    .bind(page.map(|(index, _)| index as i64))
+    .bind(page.map(|(_, words_per_page)| words_per_page as i64))
+    .bind(texts::timestamp(now))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO reading_event_words (event_id, word_id, previous_status, previous_next_review_date)
+        SELECT ?1, w.id, w.status, w.next_review_date
+        FROM words w
+        JOIN text_words tw ON tw.word_id = w.id AND tw.text_id = ?2
+        WHERE w.status = 'new'
+            AND (?3 IS NULL OR w.word IN (SELECT value FROM json_each(?3)))
+        "#,
+    )
+    .bind(event_id)
+    .bind(text_id)
+    .bind(&page_words)
+    .execute(&mut *tx)
+    .await?;
+
+    // Same change as mark_word_as_known, for every recorded word
+    sqlx::query(
+        r#"
+        UPDATE words SET status = 'known', next_review_date = NULL
+        WHERE id IN (SELECT word_id FROM reading_event_words WHERE event_id = ?)
+        "#,
+    )
+    .bind(event_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let event = get(pool, event_id).await?;
+    info!("Finished page of text {}: {} words marked as known", text_id, event.word_count);
+    Ok(event)
+}
+
+// Puts the words of the event back the way they were. Words that are not
+// known anymore (given a meaning since, for example) are left alone.
+pub async fn undo(pool: &SqlitePool, event_id: i64, now: DateTime<Utc>) -> AppResult<ReadingEvent> {
+    // Fails with not_found for unknown events
+    get(pool, event_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Claims the event first, so of two concurrent undos only one restores the words
+    let claimed = sqlx::query("UPDATE reading_events SET undone_at = ? WHERE id = ? AND undone_at IS NULL")
+        .bind(texts::timestamp(now))
+        .bind(event_id)
+        .execute(&mut *tx)
+        .await?;
+    if claimed.rows_affected() == 0 {
+        return Err(AppError::invalid_input("This reading event was already undone"));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE words SET
+            status = ew.previous_status,
+            next_review_date = ew.previous_next_review_date
+        FROM reading_event_words ew
+        WHERE ew.event_id = ? AND ew.word_id = words.id AND words.status = 'known'
+        "#,
+    )
+    .bind(event_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!("Undid reading event {}", event_id);
+    get(pool, event_id).await
+}
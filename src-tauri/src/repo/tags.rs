@@ -0,0 +1,102 @@
+use sqlx::sqlite::SqlitePool;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Tag;
+
+const MAX_TAG_LENGTH: usize = 64;
+
+// Trims the tags and drops empty ones and duplicates (ignoring the case of
+// ASCII letters, like the NOCASE collation of the column)
+fn clean_tags(tags: &[String]) -> AppResult<Vec<String>> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(AppError::invalid_input(format!(
+                "Tags cannot be longer than {} characters",
+                MAX_TAG_LENGTH
+            )));
+        }
+        if !cleaned.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    Ok(cleaned)
+}
+
+// Every tag in use with the number of texts that have it
+pub async fn list(pool: &SqlitePool) -> AppResult<Vec<Tag>> {
+    let tags = sqlx::query_as::<_, Tag>(
+        r#"
+        SELECT g.name, COUNT(tt.text_id) AS text_count
+        FROM tags g
+        JOIN text_tags tt ON tt.tag_id = g.id
+        GROUP BY g.id
+        ORDER BY g.name COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags)
+}
+
+pub async fn for_text(pool: &SqlitePool, text_id: i64) -> AppResult<Vec<String>> {
+    let tags = sqlx::query_scalar(
+        r#"
+        SELECT g.name FROM tags g
+        JOIN text_tags tt ON tt.tag_id = g.id
+        WHERE tt.text_id = ?
+        ORDER BY g.name COLLATE NOCASE
+        "#,
+    )
+    .bind(text_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags)
+}
+
+// Replaces the tags of a text. A tag that matches an existing one apart from
+// case reuses it, tags no text has anymore are removed.
+pub async fn set_text_tags(pool: &SqlitePool, text_id: i64, tags: &[String]) -> AppResult<Vec<String>> {
+    let tags = clean_tags(tags)?;
+
+    let exists: Option<i64> = sqlx::query_scalar("SELECT id FROM texts WHERE id = ?")
+        .bind(text_id)
+        .fetch_optional(pool)
+        .await?;
+    if exists.is_none() {
+        return Err(AppError::not_found("Text not found"));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM text_tags WHERE text_id = ?")
+        .bind(text_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for tag in &tags {
+        sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+            .bind(tag)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO text_tags (text_id, tag_id) SELECT ?, id FROM tags WHERE name = ?")
+            .bind(text_id)
+            .bind(tag)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM text_tags)")
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    for_text(pool, text_id).await
+}
@@ -0,0 +1,483 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use sqlx::Row;
+use tracing::{trace, debug, info, error};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{Text, TextMetadata, TextSummary, Word};
+
+lazy_static! {
+    // Matches whole words with Unicode letters
+    static ref WORD_REGEX: Regex = Regex::new(r"\b\p{L}+\b").unwrap();
+}
+
+// Words of `content` in reading order, as they are linked to the vocabulary
+pub fn tokens(content: &str) -> Vec<&str> {
+    WORD_REGEX.find_iter(content).map(|mat| mat.as_str()).collect()
+}
+
+// Number of words in `content`, the unit reading positions are counted in
+pub fn token_count(content: &str) -> i64 {
+    WORD_REGEX.find_iter(content).count() as i64
+}
+
+const TEXT_COLUMNS: &str =
+    "id, title, content, source, author, level, notes, created_at, updated_at, last_opened_at";
+// Stored timestamps are written like the SQL triggers write them for legacy
+// rows (UTC, milliseconds, `Z`), so sorting by them compares like strings
+pub(crate) fn timestamp(now: DateTime<Utc>) -> String {
+    now.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub const LEVELS: [&str; 6] = ["A1", "A2", "B1", "B2", "C1", "C2"];
+
+// Trims every field, stores empty ones as NULL and checks the level
+pub fn clean_metadata(metadata: &TextMetadata) -> AppResult<TextMetadata> {
+    let clean = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+
+    let level = clean(&metadata.level).map(|l| l.to_uppercase());
+    if let Some(level) = &level {
+        if !LEVELS.contains(&level.as_str()) {
+            return Err(AppError::invalid_input(format!(
+                "Unknown level {}, expected one of {}",
+                level,
+                LEVELS.join(", ")
+            )));
+        }
+    }
+
+    Ok(TextMetadata {
+        source: clean(&metadata.source),
+        author: clean(&metadata.author),
+        level,
+        notes: clean(&metadata.notes),
+    })
+}
+
+pub async fn list(pool: &SqlitePool) -> AppResult<Vec<Text>> {
+    let texts = sqlx::query_as::<_, Text>(&format!("SELECT {} FROM texts", TEXT_COLUMNS))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(texts)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSort {
+    Title,
+    Created,
+    LastOpened,
+    Updated,
+    Position, // Grouped by collection, in reading order
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TextFilter {
+    pub collection_id: Option<i64>,
+    pub tag: Option<String>,
+    pub level: Option<String>,
+    pub sort: Option<TextSort>, // By position inside a collection, by created date otherwise
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl TextSort {
+    fn order_by(self, descending: bool) -> String {
+        let direction = if descending { "DESC" } else { "ASC" };
+        match self {
+            TextSort::Title => format!("t.title COLLATE NOCASE {0}, t.id {0}", direction),
+            TextSort::Created => format!("t.created_at {0}, t.id {0}", direction),
+            TextSort::Updated => format!("t.updated_at {0}, t.id {0}", direction),
+            // Texts that were never opened come last either way
+            TextSort::LastOpened => format!("t.last_opened_at IS NULL, t.last_opened_at {0}, t.id {0}", direction),
+            TextSort::Position => format!(
+                "t.collection_id IS NULL, t.collection_id, t.position {0}, t.id {0}",
+                direction
+            ),
+        }
+    }
+}
+
+// The library list: texts without their content, with tags and word counts
+pub async fn list_summaries(pool: &SqlitePool, filter: &TextFilter) -> AppResult<Vec<TextSummary>> {
+    let sort = filter.sort.unwrap_or(if filter.collection_id.is_some() {
+        TextSort::Position
+    } else {
+        TextSort::Created
+    });
+
+    let query = format!(
+        r#"
+        SELECT t.id, t.title, t.collection_id, t.position,
+            t.source, t.author, t.level, t.notes, t.created_at, t.updated_at, t.last_opened_at,
+            (SELECT json_group_array(name) FROM (
+                SELECT g.name FROM text_tags tt JOIN tags g ON g.id = tt.tag_id
+                WHERE tt.text_id = t.id ORDER BY g.name COLLATE NOCASE
+            )) AS tags,
+            COALESCE(c.new_count, 0) AS new_count,
+            COALESCE(c.seen_count, 0) AS seen_count,
+            COALESCE(c.known_count, 0) AS known_count
+        FROM texts t
+        LEFT JOIN (
+            SELECT tw.text_id,
+                SUM(CASE WHEN w.status = 'new' THEN 1 ELSE 0 END) AS new_count,
+                SUM(CASE WHEN w.status = 'seen' THEN 1 ELSE 0 END) AS seen_count,
+                SUM(CASE WHEN w.status = 'known' THEN 1 ELSE 0 END) AS known_count
+            FROM text_words tw
+            JOIN words w ON w.id = tw.word_id
+            GROUP BY tw.text_id
+        ) c ON c.text_id = t.id
+        WHERE (?1 IS NULL OR t.collection_id = ?1)
+            AND (?2 IS NULL OR EXISTS (
+                SELECT 1 FROM text_tags tt JOIN tags g ON g.id = tt.tag_id
+                WHERE tt.text_id = t.id AND g.name = ?2
+            ))
+            AND (?3 IS NULL OR t.level = ?3)
+        ORDER BY {}
+        "#,
+        sort.order_by(filter.descending)
+    );
+
+    let rows = sqlx::query(&query)
+        .bind(filter.collection_id)
+        .bind(filter.tag.as_deref().map(str::trim))
+        .bind(filter.level.as_deref().map(|l| l.trim().to_uppercase()))
+        .fetch_all(pool)
+        .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let tags: String = row.try_get("tags")?;
+            Ok(TextSummary {
+                id: row.try_get("id")?,
+                title: row.try_get("title")?,
+                collection_id: row.try_get("collection_id")?,
+                position: row.try_get("position")?,
+                tags: serde_json::from_str(&tags)
+                    .map_err(|e| AppError::invalid_data("Invalid tags", e))?,
+                metadata: TextMetadata {
+                    source: row.try_get("source")?,
+                    author: row.try_get("author")?,
+                    level: row.try_get("level")?,
+                    notes: row.try_get("notes")?,
+                },
+                created_at: row.try_get("created_at")?,
+                updated_at: row.try_get("updated_at")?,
+                last_opened_at: row.try_get("last_opened_at")?,
+                new_count: row.try_get("new_count")?,
+                seen_count: row.try_get("seen_count")?,
+                known_count: row.try_get("known_count")?,
+            })
+        })
+        .collect()
+}
+
+// Remembers when a text was last opened, for sorting the library list
+pub async fn mark_opened(pool: &SqlitePool, id: i64, now: DateTime<Utc>) -> AppResult<()> {
+    sqlx::query("UPDATE texts SET last_opened_at = ? WHERE id = ?")
+        .bind(timestamp(now))
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn get(pool: &SqlitePool, id: i64) -> AppResult<Option<Text>> {
+    let text = sqlx::query_as::<_, Text>(&format!("SELECT {} FROM texts WHERE id = ?", TEXT_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(text)
+}
+
+// The text with the vocabulary entry of every word in it, in reading order
+pub async fn get_with_words(pool: &SqlitePool, id: i64) -> AppResult<(Text, Vec<Word>)> {
+    let text = get(pool, id)
+        .await?
+        .ok_or(AppError::not_found("Text not found"))?;
+
+    // Fetch the words the way they were linked, so positions match reading offsets
+    let mut word_data = Vec::new();
+    for word in tokens(&text.content) {
+        let word_record = sqlx::query_as::<_, Word>(
+            r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date 
+            FROM words 
+            WHERE word = ?"#,
+        )
+        .bind(word)
+        .fetch_optional(pool)
+        .await?;
+        if let Some(w) = word_record {
+            word_data.push(w);
+        }
+    }
+
+    Ok((text, word_data))
+}
+
+pub async fn create(pool: &SqlitePool, title: &str, content: &str, now: DateTime<Utc>) -> AppResult<Text> {
+    create_with_metadata(pool, title, content, &TextMetadata::default(), now).await
+}
+
+pub async fn create_with_metadata(
+    pool: &SqlitePool,
+    title: &str,
+    content: &str,
+    metadata: &TextMetadata,
+    now: DateTime<Utc>,
+) -> AppResult<Text> {
+    let mut tx = pool.begin().await?;
+    let text = create_with_metadata_in(&mut tx, title, content, metadata, now).await?;
+    tx.commit().await?;
+    Ok(text)
+}
+
+// create_with_metadata on the caller's connection, for imports adding several
+// texts in one transaction
+pub async fn create_with_metadata_in(
+    conn: &mut SqliteConnection,
+    title: &str,
+    content: &str,
+    metadata: &TextMetadata,
+    now: DateTime<Utc>,
+) -> AppResult<Text> {
+    let metadata = clean_metadata(metadata)?;
+    let now = timestamp(now);
+    let id: i64 = sqlx::query_scalar(
+        r#"INSERT INTO texts (title, content, source, author, level, notes, created_at, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"#,
+    )
+    .bind(title)
+    .bind(content)
+    .bind(&metadata.source)
+    .bind(&metadata.author)
+    .bind(&metadata.level)
+    .bind(&metadata.notes)
+    .bind(&now)
+    .bind(&now)
+    .fetch_one(&mut *conn)
+    .await?;
+    info!("Inserted text with ID: {}", id);
+
+    link_words(&mut *conn, id, content).await?;
+
+    // Read back with the cleaned metadata as stored
+    let text = sqlx::query_as::<_, Text>(&format!("SELECT {} FROM texts WHERE id = ?", TEXT_COLUMNS))
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+    Ok(text)
+}
+
+// Adds every word of `content` to the vocabulary (as new) and links it to the text
+pub async fn link_words(conn: &mut SqliteConnection, text_id: i64, content: &str) -> AppResult<()> {
+    let words = tokens(content);
+
+    trace!("Extracted words: {:?}", words);
+
+    for word in words {
+        // Check if the word already exists in the database
+        let existing_word = sqlx::query_as::<_, Word>(
+            r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words WHERE word = ?"#,
+        )
+        .bind(word)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        let word_id = if let Some(existing) = existing_word {
+            trace!("Found existing word: {} (ID: {})", word, existing.id);
+            existing.id // Use the existing word's ID
+        } else {
+            // Insert the word if it doesn't exist
+            trace!("Inserting new word: {}", word);
+            let inserted_word = sqlx::query!(
+                r#"INSERT INTO words (word, status, meaning, stability, difficulty, last_review_date, next_review_date) 
+                VALUES (?, 'new', NULL, 1.0, 5.0, NULL, NULL) RETURNING id"#,
+                word
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            trace!("Inserted word: {} (ID: {})", word, inserted_word.id);
+            inserted_word.id
+        };
+
+        // Link the word to the text in the `text_words` table
+        trace!("Linking word ID {} to text ID {}", word_id, text_id);
+        sqlx::query!(
+            r#"INSERT OR IGNORE INTO text_words (text_id, word_id) VALUES (?, ?)"#,
+            text_id,
+            word_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn update(pool: &SqlitePool, id: i64, title: &str, content: &str, now: DateTime<Utc>) -> AppResult<()> {
+    update_with_metadata(pool, id, title, content, None, now).await
+}
+
+// Words that were removed from the content stay linked, like they always have.
+// Media timestamps only fit the content they were made for and are dropped
+// when it changes. Without `metadata` the metadata is left alone. Nothing is
+// changed if any of it fails.
+pub async fn update_with_metadata(
+    pool: &SqlitePool,
+    id: i64,
+    title: &str,
+    content: &str,
+    metadata: Option<&TextMetadata>,
+    now: DateTime<Utc>,
+) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    let current: Option<String> = sqlx::query_scalar("SELECT content FROM texts WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if current.is_some_and(|current| current != content) {
+        sqlx::query("DELETE FROM text_timestamps WHERE text_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Update the text's title and content
+    let result = sqlx::query(
+        r#"UPDATE texts SET title = ?, content = ?, updated_at = ? WHERE id = ?"#,
+    )
+    .bind(title)
+    .bind(content)
+    .bind(timestamp(now))
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Text not found"));
+    }
+
+    if let Some(metadata) = metadata {
+        update_metadata_in(&mut tx, id, metadata, now).await?;
+    }
+    link_words(&mut tx, id, content).await?;
+
+    tx.commit().await?;
+    info!("Updated text with ID: {}", id);
+    Ok(())
+}
+
+// Replaces all metadata of a text, fields left out are cleared
+pub async fn update_metadata(pool: &SqlitePool, id: i64, metadata: &TextMetadata, now: DateTime<Utc>) -> AppResult<()> {
+    update_metadata_in(&mut *pool.acquire().await?, id, metadata, now).await
+}
+
+async fn update_metadata_in(
+    conn: &mut SqliteConnection,
+    id: i64,
+    metadata: &TextMetadata,
+    now: DateTime<Utc>,
+) -> AppResult<()> {
+    let metadata = clean_metadata(metadata)?;
+    let result = sqlx::query(
+        r#"UPDATE texts SET source = ?, author = ?, level = ?, notes = ?, updated_at = ?
+        WHERE id = ?"#,
+    )
+    .bind(&metadata.source)
+    .bind(&metadata.author)
+    .bind(&metadata.level)
+    .bind(&metadata.notes)
+    .bind(timestamp(now))
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Text not found"));
+    }
+    Ok(())
+}
+
+// Words stay in the vocabulary, only their links to the text go
+pub async fn delete(pool: &SqlitePool, text_id: i64) -> AppResult<()> {
+    debug!("Attempting to delete text with ID: {}", text_id);
+
+    // Begin a transaction to ensure atomicity
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Error beginning transaction: {}", e);
+        AppError::from(e)
+    })?;
+
+    // Delete related rows from the text_words table
+    sqlx::query!("DELETE FROM text_words WHERE text_id = ?", text_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Error deleting from text_words: {}", e);
+            AppError::from(e)
+        })?;
+    debug!("Deleted related rows from text_words for text ID: {}", text_id);
+
+    // Drop its tags, and tags no other text has
+    sqlx::query("DELETE FROM text_tags WHERE text_id = ?")
+        .bind(text_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM text_tags)")
+        .execute(&mut *tx)
+        .await?;
+
+    // Delete the text from the texts table
+    sqlx::query!("DELETE FROM texts WHERE id = ?", text_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Error deleting from texts: {}", e);
+            AppError::from(e)
+        })?;
+    info!("Deleted text with ID: {}", text_id);
+
+    // Commit the transaction
+    tx.commit().await.map_err(|e| {
+        error!("Error committing transaction: {}", e);
+        AppError::from(e)
+    })?;
+
+    Ok(())
+}
+
+// Number of (new, seen, known) words in a text
+pub async fn word_counts(pool: &SqlitePool, text_id: i64) -> AppResult<(i64, i64, i64)> {
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            COALESCE(SUM(CASE WHEN w.status = 'new' THEN 1 ELSE 0 END), 0) AS new_count,
+            COALESCE(SUM(CASE WHEN w.status = 'seen' THEN 1 ELSE 0 END), 0) AS seen_count,
+            COALESCE(SUM(CASE WHEN w.status = 'known' THEN 1 ELSE 0 END), 0) AS known_count
+        FROM text_words tw
+        JOIN words w ON tw.word_id = w.id
+        WHERE tw.text_id = ?
+        "#,
+        text_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((
+        counts.new_count,
+        counts.seen_count,
+        counts.known_count,
+    ))
+}
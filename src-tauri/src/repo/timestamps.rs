@@ -0,0 +1,74 @@
+// Where each sentence of a text is in the media it was transcribed from,
+// e.g. the subtitles of an episode.
+
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use sqlx::FromRow;
+
+use crate::error::AppResult;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, FromRow)]
+pub struct SentenceTimestamp {
+    pub token_offset: i64, // First word of the sentence
+    pub token_count: i64,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+// Replaces the timestamps of a text
+pub async fn save(pool: &SqlitePool, text_id: i64, timestamps: &[SentenceTimestamp]) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+    save_in(&mut tx, text_id, timestamps).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// save on the caller's connection
+pub async fn save_in(conn: &mut SqliteConnection, text_id: i64, timestamps: &[SentenceTimestamp]) -> AppResult<()> {
+    sqlx::query("DELETE FROM text_timestamps WHERE text_id = ?")
+        .bind(text_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for timestamp in timestamps {
+        sqlx::query(
+            r#"INSERT INTO text_timestamps (text_id, token_offset, token_count, start_ms, end_ms) VALUES (?, ?, ?, ?, ?)"#,
+        )
+        .bind(text_id)
+        .bind(timestamp.token_offset)
+        .bind(timestamp.token_count)
+        .bind(timestamp.start_ms)
+        .bind(timestamp.end_ms)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn list(pool: &SqlitePool, text_id: i64) -> AppResult<Vec<SentenceTimestamp>> {
+    let timestamps = sqlx::query_as::<_, SentenceTimestamp>(
+        r#"SELECT token_offset, token_count, start_ms, end_ms FROM text_timestamps WHERE text_id = ? ORDER BY token_offset"#,
+    )
+    .bind(text_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(timestamps)
+}
+
+// The sentence holding the word at `token_offset`
+pub async fn at_offset(pool: &SqlitePool, text_id: i64, token_offset: i64) -> AppResult<Option<SentenceTimestamp>> {
+    let timestamp = sqlx::query_as::<_, SentenceTimestamp>(
+        r#"
+        SELECT token_offset, token_count, start_ms, end_ms FROM text_timestamps
+        WHERE text_id = ?1 AND token_offset <= ?2 AND token_offset + token_count > ?2
+        "#,
+    )
+    .bind(text_id)
+    .bind(token_offset)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(timestamp)
+}
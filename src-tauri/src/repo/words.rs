@@ -0,0 +1,235 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+use tracing::{debug, info, error};
+
+use crate::error::{AppError, AppResult};
+use crate::models::Word;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReviewStats {
+    pub total: i64,
+    pub new: i64,
+    pub seen: i64,
+    pub known: i64,
+    pub due_now: i64,
+    pub due_today: i64, // Including the ones due now
+    pub reviewed_today: i64,
+}
+
+pub async fn list(pool: &SqlitePool) -> AppResult<Vec<Word>> {
+    let words = sqlx::query_as::<_, Word>(
+        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words"#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(words)
+}
+
+pub async fn get(pool: &SqlitePool, word_id: i64) -> AppResult<Option<Word>> {
+    let word = sqlx::query_as::<_, Word>(
+        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words WHERE id = ?"#,
+    )
+    .bind(word_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(word)
+}
+
+// Words other than `except_id` that have a meaning, e.g. to reuse it for other forms
+pub async fn with_meaning(pool: &SqlitePool, except_id: i64) -> AppResult<Vec<Word>> {
+    let words = sqlx::query_as::<_, Word>(
+        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words
+        WHERE id != ? AND TRIM(COALESCE(meaning, '')) != ''"#,
+    )
+    .bind(except_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(words)
+}
+
+pub async fn mark_known(pool: &SqlitePool, word_id: i64) -> AppResult<()> {
+    // Update the status to "known" and clear the `next_review_date`
+    let result = sqlx::query!(
+        r#"UPDATE words SET status = 'known', next_review_date = NULL WHERE id = ?"#,
+        word_id
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Word ID not found"));
+    }
+    Ok(())
+}
+
+// Saving a meaning makes the word seen and due for review right away
+pub async fn update_meaning(pool: &SqlitePool, word_id: i64, meaning: &str, now: DateTime<Utc>) -> AppResult<()> {
+    let now_iso = now.to_rfc3339(); // Current timestamp as ISO 8601
+
+    // Update both `meaning`, `status`, and `next_review_date`
+    let result = sqlx::query!(
+        r#"UPDATE words SET meaning = ?, status = 'seen', next_review_date = ? WHERE id = ?"#,
+        meaning,
+        now_iso, // Set `next_review_date` to the current time
+        word_id
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Word ID not found"));
+    }
+    Ok(())
+}
+
+// Moves a new word into review, due right away, without touching its meaning
+pub async fn start_learning(pool: &SqlitePool, word_id: i64, now: DateTime<Utc>) -> AppResult<()> {
+    let result = sqlx::query("UPDATE words SET status = 'seen', next_review_date = ? WHERE id = ?")
+        .bind(now.to_rfc3339())
+        .bind(word_id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Word ID not found"));
+    }
+    Ok(())
+}
+
+// Stores the outcome of a review
+pub async fn save_review(
+    pool: &SqlitePool,
+    word_id: i64,
+    stability: f64,
+    difficulty: f64,
+    reviewed_at: DateTime<Utc>,
+    next_review: DateTime<Utc>,
+) -> AppResult<()> {
+    let now_iso = reviewed_at.to_rfc3339();
+    let next_review_iso = next_review.to_rfc3339();
+
+    sqlx::query!(
+        r#"
+        UPDATE words
+        SET
+            stability = ?,
+            difficulty = ?,
+            last_review_date = ?,
+            next_review_date = ?
+        WHERE id = ?
+        "#,
+        stability,
+        difficulty,
+        now_iso,         // Current timestamp as ISO 8601
+        next_review_iso, // Next review date as ISO 8601
+        word_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete(pool: &SqlitePool, word_id: i64) -> AppResult<()> {
+    debug!("Attempting to delete word with ID: {}", word_id);
+
+    // Begin a transaction to ensure atomicity
+    let mut tx = pool.begin().await.map_err(|e| {
+        error!("Error beginning transaction: {}", e);
+        AppError::from(e)
+    })?;
+
+    // Delete related rows from the text_words table
+    sqlx::query!("DELETE FROM text_words WHERE word_id = ?", word_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Error deleting from text_words: {}", e);
+            AppError::from(e)
+        })?;
+    debug!("Deleted related rows from text_words for word ID: {}", word_id);
+
+    // Delete the word from the words table
+    let result = sqlx::query!("DELETE FROM words WHERE id = ?", word_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            error!("Error deleting from words: {}", e);
+            AppError::from(e)
+        })?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Word ID not found"));
+    }
+
+    info!("Deleted word with ID: {}", word_id);
+
+    // Commit the transaction
+    tx.commit().await.map_err(|e| {
+        error!("Error committing transaction: {}", e);
+        AppError::from(e)
+    })?;
+
+    Ok(())
+}
+
+// Words due for review at `now`, most overdue first
+pub async fn due(pool: &SqlitePool, now: DateTime<Utc>, limit: i64) -> AppResult<Vec<Word>> {
+    let words = sqlx::query_as::<_, Word>(
+        r#"
+        SELECT 
+            id, word, status, meaning, stability, difficulty, 
+            last_review_date, next_review_date 
+        FROM words 
+        WHERE 
+            status = 'seen' AND 
+            next_review_date <= ? 
+        ORDER BY next_review_date ASC
+        LIMIT ?
+        "#,
+    )
+    .bind(now.to_rfc3339())
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(words)
+}
+
+// "Today" is the UTC day of `now`, like the stored review dates
+pub async fn stats(pool: &SqlitePool, now: DateTime<Utc>) -> AppResult<ReviewStats> {
+    let day_start = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let day_end = day_start + chrono::Duration::days(1);
+
+    let row = sqlx::query(
+        r#"
+        SELECT
+            COUNT(*) AS total,
+            COALESCE(SUM(CASE WHEN status = 'new' THEN 1 ELSE 0 END), 0) AS new_count,
+            COALESCE(SUM(CASE WHEN status = 'seen' THEN 1 ELSE 0 END), 0) AS seen_count,
+            COALESCE(SUM(CASE WHEN status = 'known' THEN 1 ELSE 0 END), 0) AS known_count,
+            COALESCE(SUM(CASE WHEN status = 'seen' AND next_review_date <= ?1 THEN 1 ELSE 0 END), 0) AS due_now,
+            COALESCE(SUM(CASE WHEN status = 'seen' AND next_review_date < ?2 THEN 1 ELSE 0 END), 0) AS due_today,
+            COALESCE(SUM(CASE WHEN last_review_date >= ?3 AND last_review_date < ?2 THEN 1 ELSE 0 END), 0) AS reviewed_today
+        FROM words
+        "#,
+    )
+    .bind(now.to_rfc3339())
+    .bind(day_end.to_rfc3339())
+    .bind(day_start.to_rfc3339())
+    .fetch_one(pool)
+    .await?;
+
+    Ok(ReviewStats {
+        total: row.get("total"),
+        new: row.get("new_count"),
+        seen: row.get("seen_count"),
+        known: row.get("known_count"),
+        due_now: row.get("due_now"),
+        due_today: row.get("due_today"),
+        reviewed_today: row.get("reviewed_today"),
+    })
+}
@@ -0,0 +1,210 @@
+// Review scheduling: an FSRS-style model that tracks stability and
+// difficulty per word and schedules the next review for TARGET_RETENTION.
+
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqlitePool;
+use tracing::debug;
+
+use crate::error::{AppError, AppResult};
+use crate::models::Word;
+use crate::repo;
+
+pub const RATINGS: [&str; 4] = ["Again", "Hard", "Good", "Easy"];
+
+const BASE_STABILITY: f64 = 0.5; // Initial stability for new words
+const BASE_DIFFICULTY: f64 = 0.3; // Initial difficulty (0-1 scale, lower means harder)
+
+const EASY_BONUS: f64 = 1.3; // Additional boost for "Easy" rating
+const HARD_PENALTY: f64 = 0.5; // Penalty for "Hard" rating
+const AGAIN_RESET_PCT: f64 = 0.2; // How much stability is retained after "Again"
+const MIN_INTERVAL: i64 = 1; // Minimum interval in days
+const MAX_INTERVAL: i64 = 365 * 10; // Maximum interval (10 years)
+const INTERVAL_MODIFIER: f64 = 1.0; // Global scaling factor
+
+pub const TARGET_RETENTION: f64 = 0.85; // Target probability of recall
+
+// Picks the word that is most overdue (lowest retention probability)
+pub async fn next_due(pool: &SqlitePool, now: DateTime<Utc>) -> AppResult<Word> {
+    repo::words::due(pool, now, 1)
+        .await?
+        .pop()
+        .ok_or(AppError::not_found("No words are due for review"))
+}
+
+fn parse_review_date(date: &str) -> AppResult<DateTime<Utc>> {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(dt) => Ok(dt.with_timezone(&Utc)),
+        Err(e) => Err(AppError::invalid_data("Invalid last review date format", e)),
+    }
+}
+
+// Probability that the word is still remembered at `now`
+pub async fn estimate_retention(pool: &SqlitePool, word_id: i64, now: DateTime<Utc>) -> AppResult<f64> {
+    let word = repo::words::get(pool, word_id)
+        .await?
+        .ok_or(AppError::not_found("Word not found"))?;
+
+    retention(&word, now)
+}
+
+pub fn retention(word: &Word, now: DateTime<Utc>) -> AppResult<f64> {
+    // If we don't have stability data, return default
+    let stability = match word.stability {
+        Some(s) => s,
+        None => return Ok(0.0), // No retention data
+    };
+
+    // If no last review, assume 100% retention
+    let last_review_date = match &word.last_review_date {
+        Some(date) => date,
+        None => return Ok(1.0), // No decay yet
+    };
+
+    // Calculate days since last review
+    let last_review = parse_review_date(last_review_date)?;
+    let days_elapsed = now.signed_duration_since(last_review).num_days().max(0) as f64;
+
+    // Calculate current retention using exponential forgetting curve
+    // R = e^(-d/S) where d is days since last review and S is stability
+    let retention = (-days_elapsed / stability).exp();
+
+    Ok(retention)
+}
+
+// Applies a rating to a word and schedules its next review, returns the next review date
+pub async fn review(pool: &SqlitePool, word_id: i64, rating: &str, now: DateTime<Utc>) -> AppResult<DateTime<Utc>> {
+    // Parse the rating
+    if !RATINGS.contains(&rating) {
+        return Err(AppError::invalid_input(format!("Invalid rating: {}", rating)));
+    }
+
+    let word = repo::words::get(pool, word_id)
+        .await?
+        .ok_or(AppError::not_found("Word not found"))?;
+
+    // Make sure we have valid stability and difficulty values
+    let stability = word.stability.unwrap_or(BASE_STABILITY);
+    let difficulty = word.difficulty.unwrap_or(BASE_DIFFICULTY);
+
+    // Calculate days since last review (default to 1 if first review)
+    let days_since_last_review = match &word.last_review_date {
+        Some(last_date) => {
+            let last_review = parse_review_date(last_date)?;
+            now.signed_duration_since(last_review).num_days().max(1) // At least 1 day
+        }
+        None => 1, // First review
+    };
+
+    debug!(
+        "Review - Word: {}, Initial Stability: {}, Difficulty: {}, Days Since Last: {}",
+        word.word,
+        stability,
+        difficulty,
+        days_since_last_review
+    );
+
+    // Update stability, difficulty, and scheduled days
+    let (updated_stability, updated_difficulty, scheduled_days, predicted_retention) = 
+        update_word_parameters(
+            stability,
+            difficulty,
+            Some(days_since_last_review),
+            rating,
+        );
+
+    debug!(
+        "Updated Stability: {}, Difficulty: {}, Scheduled Days: {}, Predicted Retention: {:.1}%",
+        updated_stability, 
+        updated_difficulty, 
+        scheduled_days,
+        predicted_retention * 100.0
+    );
+
+    // Calculate the next review date
+    let next_review = now + chrono::Duration::days(scheduled_days);
+    repo::words::save_review(pool, word_id, updated_stability, updated_difficulty, now, next_review).await?;
+
+    Ok(next_review)
+}
+
+pub fn update_word_parameters(
+    stability: f64,
+    difficulty: f64,
+    last_interval: Option<i64>,
+    rating: &str,
+) -> (f64, f64, i64, f64) {
+    let mut new_stability = stability;
+    let mut new_difficulty = difficulty;
+    let default_last_interval = 1;
+    let last_interval = last_interval.unwrap_or(default_last_interval);
+    
+    // Calculate retrievability (theoretical probability of recall)
+    // R = e^(-d/S) where d is days since last review and S is stability
+    let retrievability = (-(last_interval as f64) / stability).exp();
+    
+    // Update difficulty based on performance vs expected
+    match rating {
+        "Again" => {
+            // Lapse: reset stability but keep some memory trace
+            new_stability = stability * AGAIN_RESET_PCT;
+            // If you failed despite high retrievability, increase difficulty
+            let expected_success = retrievability;
+            let actual_success = 0.0; // Failed
+            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.2)
+                .clamp(0.1, 1.0);
+        }
+        "Hard" => {
+            // Succeeded but with difficulty
+            let memory_strength = HARD_PENALTY;
+            new_stability = stability * (1.0 + memory_strength * (1.0 - retrievability));
+            
+            // Adjust difficulty (if it was hard despite high retrievability, increase difficulty)
+            let expected_success = retrievability;
+            let actual_success = 0.6; // Partial success
+            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.15)
+                .clamp(0.1, 1.0);
+        }
+        "Good" => {
+            // Standard success
+            let memory_strength = 1.0;
+            new_stability = stability * (1.0 + memory_strength * (1.0 - retrievability));
+            
+            // Adjust difficulty
+            let expected_success = retrievability;
+            let actual_success = 1.0; // Full success
+            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.1)
+                .clamp(0.1, 1.0);
+        }
+        "Easy" => {
+            // Easy success
+            let memory_strength = EASY_BONUS;
+            new_stability = stability * (1.0 + memory_strength * (1.0 - retrievability));
+            
+            // Reduce difficulty
+            let expected_success = retrievability;
+            let actual_success = 1.0; // Very easy success
+            new_difficulty = (new_difficulty + (expected_success - actual_success) * 0.08)
+                .clamp(0.1, 1.0);
+        }
+        _ => {}
+    }
+    
+    // Calculate optimal interval based on stability, target retention and difficulty
+    // Solve for t in: e^(-t/S) = R where R is target retention
+    // t = -S * ln(R)
+    let optimal_days = -new_stability * TARGET_RETENTION.ln();
+    
+    // Apply difficulty modifier (higher difficulty = shorter intervals)
+    let difficulty_modifier = 1.0 - (new_difficulty - 0.3) * 0.5;
+    
+    // Calculate scheduled days with global interval modifier
+    let scheduled_days = (optimal_days * difficulty_modifier * INTERVAL_MODIFIER).round() as i64;
+    
+    // Ensure scheduled days is within bounds
+    let scheduled_days = scheduled_days.clamp(MIN_INTERVAL, MAX_INTERVAL);
+    
+    // For debugging: calculate actual predicted retention at review time
+    let predicted_retention = (-(scheduled_days as f64) / new_stability).exp();
+    
+    (new_stability, new_difficulty, scheduled_days, predicted_retention)
+}
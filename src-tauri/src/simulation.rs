@@ -0,0 +1,192 @@
+// Replays a synthetic learner against the real scheduler to project the
+// review workload and retention of the current library over the next days.
+//
+// The simulation runs on an in-memory copy of the vocabulary driven by a
+// ManualClock, so the library itself is never touched. Each due word is
+// recalled with the probability the forgetting curve predicts and rated
+// Good, otherwise Again. The dice are seeded, the same input gives the same
+// projection.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::collections::BTreeMap;
+
+use crate::bootstrap::init_db;
+use crate::clock::{Clock, ManualClock};
+use crate::error::{AppError, AppResult};
+use crate::models::Word;
+use crate::{repo, review};
+
+pub const MAX_DAYS: u32 = 3650;
+const DEFAULT_SEED: u64 = 0x5eed;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SimulationDay {
+    pub day: u32,
+    pub date: String,
+    pub reviews: i64,
+    pub lapses: i64, // Reviews rated Again
+    pub new_words: i64,
+    pub average_retention: f64, // Over all words in review at the end of the day
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SimulationReport {
+    pub target_retention: f64,
+    pub total_reviews: i64,
+    pub peak_daily_reviews: i64,
+    pub average_daily_reviews: f64,
+    pub final_average_retention: f64,
+    pub days: Vec<SimulationDay>,
+}
+
+#[derive(Debug, Default)]
+pub struct SimulationOptions {
+    pub days: u32,
+    pub new_words_per_day: u32, // Taken from the words still marked new
+    pub seed: Option<u64>,
+}
+
+// xorshift64*, good enough for dice and keeps runs reproducible
+struct Dice(u64);
+
+impl Dice {
+    fn roll(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+async fn copy_vocabulary(source: &SqlitePool) -> AppResult<SqlitePool> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true))
+        .await
+        .map_err(AppError::database("Failed to open simulation database"))?;
+    init_db(&pool).await?;
+
+    let mut tx = pool.begin().await?;
+    for word in repo::words::list(source).await? {
+        sqlx::query(
+            r#"INSERT INTO words (id, word, status, meaning, stability, difficulty, last_review_date, next_review_date)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(word.id)
+        .bind(&word.word)
+        .bind(&word.status)
+        .bind(&word.meaning)
+        .bind(word.stability)
+        .bind(word.difficulty)
+        .bind(&word.last_review_date)
+        .bind(&word.next_review_date)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(pool)
+}
+
+fn is_in_review(word: &Word) -> bool {
+    word.status.as_deref() == Some("seen")
+}
+
+// Reads back a word the simulation changed, so `in_review` follows the
+// database without loading the whole vocabulary every day
+async fn refresh(pool: &SqlitePool, in_review: &mut BTreeMap<i64, Word>, word_id: i64) -> AppResult<()> {
+    match repo::words::get(pool, word_id).await? {
+        Some(word) if is_in_review(&word) => in_review.insert(word_id, word),
+        _ => in_review.remove(&word_id),
+    };
+    Ok(())
+}
+
+fn average_retention(in_review: &BTreeMap<i64, Word>, now: DateTime<Utc>) -> AppResult<f64> {
+    if in_review.is_empty() {
+        return Ok(0.0);
+    }
+    let mut total = 0.0;
+    for word in in_review.values() {
+        total += review::retention(word, now)?;
+    }
+    Ok(total / in_review.len() as f64)
+}
+
+pub async fn simulate(source: &SqlitePool, start: DateTime<Utc>, options: &SimulationOptions) -> AppResult<SimulationReport> {
+    if options.days == 0 || options.days > MAX_DAYS {
+        return Err(AppError::invalid_input(format!(
+            "Days must be between 1 and {}",
+            MAX_DAYS
+        )));
+    }
+
+    let pool = copy_vocabulary(source).await?;
+    let mut in_review: BTreeMap<i64, Word> = repo::words::list(&pool)
+        .await?
+        .into_iter()
+        .filter(is_in_review)
+        .map(|word| (word.id, word))
+        .collect();
+    let clock = ManualClock::new(start);
+    // xorshift gets stuck on zero
+    let mut dice = Dice(options.seed.unwrap_or(DEFAULT_SEED).max(1));
+    let mut days = Vec::with_capacity(options.days as usize);
+
+    for day in 0..options.days {
+        if day > 0 {
+            clock.advance(Duration::days(1));
+        }
+        let now = clock.now();
+
+        let new_words = sqlx::query_scalar::<_, i64>(
+            "SELECT id FROM words WHERE status = 'new' ORDER BY id LIMIT ?",
+        )
+        .bind(options.new_words_per_day as i64)
+        .fetch_all(&pool)
+        .await?;
+        for word_id in &new_words {
+            repo::words::start_learning(&pool, *word_id, now).await?;
+            refresh(&pool, &mut in_review, *word_id).await?;
+        }
+
+        let mut reviews = 0;
+        let mut lapses = 0;
+        for word in repo::words::due(&pool, now, i64::MAX).await? {
+            let recalled = dice.roll() < review::retention(&word, now)?;
+            let rating = if recalled { "Good" } else { "Again" };
+            review::review(&pool, word.id, rating, now).await?;
+            refresh(&pool, &mut in_review, word.id).await?;
+
+            reviews += 1;
+            if !recalled {
+                lapses += 1;
+            }
+        }
+
+        days.push(SimulationDay {
+            day: day + 1,
+            date: now.date_naive().to_string(),
+            reviews,
+            lapses,
+            new_words: new_words.len() as i64,
+            average_retention: average_retention(&in_review, now + Duration::days(1))?,
+        });
+    }
+
+    pool.close().await;
+
+    let total_reviews: i64 = days.iter().map(|d| d.reviews).sum();
+    Ok(SimulationReport {
+        target_retention: review::TARGET_RETENTION,
+        total_reviews,
+        peak_daily_reviews: days.iter().map(|d| d.reviews).max().unwrap_or(0),
+        average_daily_reviews: total_reviews as f64 / days.len() as f64,
+        final_average_retention: days.last().map(|d| d.average_retention).unwrap_or(0.0),
+        days,
+    })
+}
@@ -0,0 +1,237 @@
+// Splitting a sync key into a sequence of QR codes and putting it back together.
+//
+// Every chunk is a self-describing line:
+//
+//     WE1:<index>/<total>:<key checksum>:<chunk checksum>:<data>
+//
+// Indexes start at 1, checksums are CRC32 in hex. The key checksum identifies
+// which sync key a chunk belongs to, so chunks of an older key are rejected
+// instead of being mixed in, and it verifies the reassembled key.
+
+use serde::{Serialize, Deserialize};
+use base64::{Engine as _, engine::general_purpose};
+use qrcode::{QrCode, EcLevel, Color};
+use qrcode::render::svg;
+use std::collections::BTreeMap;
+
+use crate::error::{AppError, AppResult};
+
+const CHUNK_PREFIX: &str = "WE1";
+pub const DEFAULT_CHUNK_SIZE: usize = 800; // Characters of the key per QR code
+const MIN_CHUNK_SIZE: usize = 100;
+const MAX_CHUNK_SIZE: usize = 2000; // Stays well below the QR capacity at EC level M
+// Nobody scans more, and it bounds what a misread position can make us allocate
+const MAX_CHUNKS: usize = 1000;
+
+const PNG_MODULE_SIZE: usize = 6; // Pixels per QR module
+const QUIET_ZONE: usize = 4; // Blank modules around the code
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Svg,
+    Png,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SyncKeyChunk {
+    pub index: usize,
+    pub total: usize,
+    pub text: String, // What the QR code encodes
+    pub image: String, // SVG markup or a PNG data URL
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChunkAssembly {
+    pub key_checksum: String,
+    pub total: usize,
+    pub received: Vec<usize>,
+    pub missing: Vec<usize>,
+    pub sync_key: Option<String>, // Set once every chunk is present
+}
+
+struct ParsedChunk<'a> {
+    index: usize,
+    total: usize,
+    key_checksum: &'a str,
+    data: &'a str,
+}
+
+fn checksum(data: &str) -> String {
+    format!("{:08x}", crc32fast::hash(data.as_bytes()))
+}
+
+pub fn split_sync_key(sync_key: &str, chunk_size: usize) -> AppResult<Vec<String>> {
+    let chunk_size = chunk_size.clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE);
+    let key_checksum = checksum(sync_key);
+
+    // Sync keys are URL-safe base64, so byte offsets are char boundaries
+    if !sync_key.is_ascii() {
+        return Err(AppError::invalid_input("Sync key contains characters that are not base64"));
+    }
+    let mut pieces: Vec<&str> = (0..sync_key.len())
+        .step_by(chunk_size)
+        .map(|start| &sync_key[start..(start + chunk_size).min(sync_key.len())])
+        .collect();
+    if pieces.is_empty() {
+        pieces.push("");
+    }
+    let total = pieces.len();
+    if total > MAX_CHUNKS {
+        return Err(AppError::invalid_input(format!(
+            "Sync key is too large for {} QR codes, use a sync server instead",
+            MAX_CHUNKS
+        )));
+    }
+
+    Ok(pieces
+        .iter()
+        .enumerate()
+        .map(|(i, data)| {
+            format!(
+                "{}:{}/{}:{}:{}:{}",
+                CHUNK_PREFIX,
+                i + 1,
+                total,
+                key_checksum,
+                checksum(data),
+                data
+            )
+        })
+        .collect())
+}
+
+fn parse_chunk(chunk: &str) -> AppResult<ParsedChunk<'_>> {
+    let mut parts = chunk.trim().splitn(5, ':');
+    let (Some(prefix), Some(position), Some(key_checksum), Some(chunk_checksum), Some(data)) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(AppError::invalid_data("Not a WordEcho sync key chunk", chunk));
+    };
+
+    if prefix != CHUNK_PREFIX {
+        return Err(AppError::invalid_data("Not a WordEcho sync key chunk", chunk));
+    }
+
+    let (index, total) = position
+        .split_once('/')
+        .and_then(|(i, t)| Some((i.parse::<usize>().ok()?, t.parse::<usize>().ok()?)))
+        .filter(|(i, t)| *i >= 1 && i <= t)
+        .ok_or(AppError::invalid_data("Invalid chunk position", position))?;
+
+    // The total is not covered by a checksum
+    if total > MAX_CHUNKS {
+        return Err(AppError::invalid_data(
+            "Invalid chunk position",
+            format!("{} chunks, at most {} are supported", total, MAX_CHUNKS),
+        ));
+    }
+
+    if checksum(data) != chunk_checksum {
+        return Err(AppError::invalid_data(
+            format!("Chunk {} of {} is damaged, scan it again", index, total),
+            format!("expected checksum {}, got {}", chunk_checksum, checksum(data)),
+        ));
+    }
+
+    Ok(ParsedChunk {
+        index,
+        total,
+        key_checksum,
+        data,
+    })
+}
+
+// Accepts chunks in any order (duplicates are fine) and reports what is
+// still missing. All chunks must belong to the same sync key.
+pub fn assemble_sync_key(chunks: &[String]) -> AppResult<ChunkAssembly> {
+    let mut parsed: BTreeMap<usize, ParsedChunk> = BTreeMap::new();
+    let mut expected: Option<(usize, &str)> = None;
+
+    for chunk in chunks.iter().filter(|c| !c.trim().is_empty()) {
+        let chunk = parse_chunk(chunk)?;
+
+        match expected {
+            None => expected = Some((chunk.total, chunk.key_checksum)),
+            Some((total, key_checksum)) => {
+                if total != chunk.total || key_checksum != chunk.key_checksum {
+                    return Err(AppError::invalid_input("Chunks belong to different sync keys"));
+                }
+            }
+        }
+
+        parsed.insert(chunk.index, chunk);
+    }
+
+    let (total, key_checksum) = expected.ok_or(AppError::invalid_input("No chunks given"))?;
+    let received: Vec<usize> = parsed.keys().copied().collect();
+    let missing: Vec<usize> = (1..=total).filter(|i| !parsed.contains_key(i)).collect();
+
+    let sync_key = if missing.is_empty() {
+        let sync_key: String = parsed.values().map(|c| c.data).collect();
+        if checksum(&sync_key) != key_checksum {
+            return Err(AppError::invalid_data("Reassembled sync key does not match its checksum", key_checksum));
+        }
+        Some(sync_key)
+    } else {
+        None
+    };
+
+    Ok(ChunkAssembly {
+        key_checksum: key_checksum.to_string(),
+        total,
+        received,
+        missing,
+        sync_key,
+    })
+}
+
+pub fn render_chunk(text: &str, format: ImageFormat) -> AppResult<String> {
+    let code = QrCode::with_error_correction_level(text.as_bytes(), EcLevel::M)
+        .map_err(|e| AppError::internal("Failed to encode QR code", e))?;
+
+    match format {
+        ImageFormat::Svg => Ok(code
+            .render::<svg::Color>()
+            .min_dimensions(256, 256)
+            .quiet_zone(true)
+            .build()),
+        ImageFormat::Png => {
+            let png = render_png(&code)?;
+            Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(png)))
+        }
+    }
+}
+
+fn render_png(code: &QrCode) -> AppResult<Vec<u8>> {
+    let width = code.width();
+    let colors = code.to_colors();
+    let size = (width + 2 * QUIET_ZONE) * PNG_MODULE_SIZE;
+
+    // 8-bit grayscale, white background
+    let mut pixels = vec![0xFF_u8; size * size];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let x0 = (i % width + QUIET_ZONE) * PNG_MODULE_SIZE;
+        let y0 = (i / width + QUIET_ZONE) * PNG_MODULE_SIZE;
+        for y in y0..y0 + PNG_MODULE_SIZE {
+            pixels[y * size + x0..y * size + x0 + PNG_MODULE_SIZE].fill(0x00);
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| AppError::internal("Failed to write PNG", e))?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| AppError::internal("Failed to write PNG", e))?;
+    writer.finish().map_err(|e| AppError::internal("Failed to write PNG", e))?;
+
+    Ok(png)
+}
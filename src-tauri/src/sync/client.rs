@@ -0,0 +1,470 @@
+// Sync client used by the app. Local changes are recorded into
+// `sync_changes` by triggers (see migrations/0001_sync.sql), pushed to the
+// server and deleted once stored; remote changes are pulled from the saved
+// cursor and applied with the triggers switched off.
+
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::{SqlitePool, SqliteConnection};
+use sqlx::Row;
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+
+use crate::error::{AppError, AppResult};
+use super::protocol::{
+    change_bytes, read_message, write_message, Change, ChangeOp, RemoteChange, SyncRequest, SyncResponse,
+    DEFAULT_PORT, ENTITY_DICTIONARY_PROVIDERS, ENTITY_TEXTS, ENTITY_TEXT_WORDS, ENTITY_USER_PREFERENCES, ENTITY_WORDS,
+    MAX_BATCH_BYTES, MAX_BATCH_SIZE, PROTOCOL_VERSION,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SyncStatus {
+    pub device_id: Option<String>,
+    pub server_url: Option<String>,
+    pub cursor: i64,
+    pub pending_changes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SyncReport {
+    pub pushed: usize,
+    pub pulled: usize,
+    pub skipped: usize, // Remote changes overridden by unpushed local ones
+    pub cursor: i64,
+}
+
+// Queues every existing row so a freshly configured device uploads its library
+const SNAPSHOT_SQL: &str = r#"
+INSERT INTO sync_changes (entity, entity_key, op, payload)
+SELECT 'words', word, 'upsert', json_object(
+    'word', word, 'status', status, 'meaning', meaning,
+    'stability', stability, 'difficulty', difficulty,
+    'last_review_date', last_review_date, 'next_review_date', next_review_date)
+FROM words;
+
+INSERT INTO sync_changes (entity, entity_key, op, payload)
+SELECT 'texts', uid, 'upsert', json_object('uid', uid, 'title', title, 'content', content)
+FROM texts WHERE uid IS NOT NULL;
+
+INSERT INTO sync_changes (entity, entity_key, op, payload)
+SELECT 'text_words', t.uid || ':' || w.word, 'upsert', json_object('text_uid', t.uid, 'word', w.word)
+FROM text_words tw
+JOIN texts t ON t.id = tw.text_id
+JOIN words w ON w.id = tw.word_id
+WHERE t.uid IS NOT NULL;
+
+INSERT INTO sync_changes (entity, entity_key, op, payload)
+SELECT 'user_preferences', 'country_code', 'upsert', json_object('country_code', country_code)
+FROM user_preferences;
+
+INSERT INTO sync_changes (entity, entity_key, op, payload)
+SELECT 'dictionary_providers', uid, 'upsert', json_object(
+    'uid', uid, 'name', name, 'url_template', url_template,
+    'languages', json(languages), 'target_language', target_language,
+    'position', position, 'embedded', embedded)
+FROM dictionary_providers WHERE uid IS NOT NULL;
+"#;
+
+async fn get_state(pool: &SqlitePool, key: &str) -> AppResult<Option<String>> {
+    let row = sqlx::query("SELECT value FROM sync_state WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.map(|r| r.get("value")))
+}
+
+async fn set_state(conn: &mut SqliteConnection, key: &str, value: &str) -> AppResult<()> {
+    sqlx::query("INSERT INTO sync_state (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+        .bind(key)
+        .bind(value)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+// Accepts "host", "host:port" or "wordecho://host:port"
+fn normalize_server_url(server_url: &str) -> AppResult<String> {
+    let address = server_url.trim().trim_start_matches("wordecho://").trim_end_matches('/');
+    if address.is_empty() {
+        return Err(AppError::invalid_input("Server address is empty"));
+    }
+
+    if address.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+        Ok(address.to_string())
+    } else {
+        Ok(format!("{}:{}", address, DEFAULT_PORT))
+    }
+}
+
+pub async fn status(pool: &SqlitePool) -> AppResult<SyncStatus> {
+    let pending_changes: i64 = sqlx::query("SELECT COUNT(*) FROM sync_changes")
+        .fetch_one(pool)
+        .await?
+        .get(0);
+
+    Ok(SyncStatus {
+        device_id: get_state(pool, "device_id").await?,
+        server_url: get_state(pool, "server_url").await?,
+        cursor: get_state(pool, "pull_cursor").await?
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(0),
+        pending_changes,
+    })
+}
+
+// Points this device at a server, which only answers requests carrying its
+// secret. Switching servers starts over from an empty cursor and re-uploads
+// the whole library.
+pub async fn configure(pool: &SqlitePool, server_url: &str, secret: &str) -> AppResult<SyncStatus> {
+    let server_url = normalize_server_url(server_url)?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(AppError::invalid_input("Sync secret is empty"));
+    }
+    let current = get_state(pool, "server_url").await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("INSERT OR IGNORE INTO sync_state (key, value) VALUES ('device_id', lower(hex(randomblob(16))))")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to create device id"))?;
+
+    if current.as_deref() != Some(server_url.as_str()) {
+        sqlx::query("DELETE FROM sync_changes")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::database("Failed to clear pending changes"))?;
+
+        sqlx::query(SNAPSHOT_SQL)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::database("Failed to queue library for upload"))?;
+
+        set_state(&mut tx, "server_url", &server_url).await?;
+        set_state(&mut tx, "pull_cursor", "0").await?;
+    }
+    set_state(&mut tx, "secret", secret).await?;
+
+    tx.commit().await.map_err(AppError::database("Failed to commit transaction"))?;
+
+    status(pool).await
+}
+
+// Stops recording changes and forgets the server, keeping the device id
+pub async fn disconnect(pool: &SqlitePool) -> AppResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM sync_state WHERE key IN ('server_url', 'pull_cursor', 'secret')")
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM sync_changes")
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await.map_err(AppError::database("Failed to commit transaction"))?;
+
+    Ok(())
+}
+
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    async fn open(server_url: &str) -> AppResult<Connection> {
+        let stream = TcpStream::connect(server_url)
+            .await
+            .map_err(|e| AppError::sync(format!("Failed to connect to sync server {}", server_url), Some(e.to_string())))?;
+        let (reader, writer) = stream.into_split();
+
+        Ok(Connection {
+            reader: BufReader::new(reader),
+            writer,
+        })
+    }
+
+    async fn request(&mut self, request: &SyncRequest) -> AppResult<SyncResponse> {
+        write_message(&mut self.writer, request)
+            .await
+            .map_err(|e| AppError::sync("Failed to send sync request", Some(e.to_string())))?;
+
+        match read_message(&mut self.reader).await {
+            Ok(Some(SyncResponse::Error { message })) => Err(AppError::sync("Sync server rejected the request", Some(message))),
+            Ok(Some(response)) => Ok(response),
+            Ok(None) => Err(AppError::sync("Sync server closed the connection", None)),
+            Err(e) => Err(AppError::sync("Failed to read sync response", Some(e.to_string()))),
+        }
+    }
+}
+
+// Pulls remote changes first so unpushed local edits can take precedence,
+// then pushes the local changes on top.
+pub async fn sync_now(pool: &SqlitePool) -> AppResult<SyncReport> {
+    let server_url = get_state(pool, "server_url")
+        .await?
+        .ok_or(AppError::invalid_input("No sync server configured"))?;
+    let device_id = get_state(pool, "device_id")
+        .await?
+        .ok_or(AppError::invalid_input("No device id, configure the sync server first"))?;
+    let secret = get_state(pool, "secret")
+        .await?
+        .ok_or(AppError::invalid_input("No sync secret, configure the sync server first"))?;
+
+    let mut connection = Connection::open(&server_url).await?;
+    let mut report = SyncReport {
+        pushed: 0,
+        pulled: 0,
+        skipped: 0,
+        cursor: 0,
+    };
+
+    pull(pool, &mut connection, &secret, &device_id, &mut report).await?;
+    push(pool, &mut connection, &secret, &device_id, &mut report).await?;
+
+    Ok(report)
+}
+
+async fn pull(
+    pool: &SqlitePool,
+    connection: &mut Connection,
+    secret: &str,
+    device_id: &str,
+    report: &mut SyncReport,
+) -> AppResult<()> {
+    let mut cursor: i64 = get_state(pool, "pull_cursor")
+        .await?
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0);
+
+    loop {
+        let response = connection
+            .request(&SyncRequest::Pull {
+                version: PROTOCOL_VERSION,
+                secret: secret.to_string(),
+                device_id: device_id.to_string(),
+                cursor,
+                limit: MAX_BATCH_SIZE,
+            })
+            .await?;
+
+        let (changes, next_cursor, has_more) = match response {
+            SyncResponse::Changes { changes, cursor, has_more } => (changes, cursor, has_more),
+            other => return Err(AppError::sync("Unexpected response to pull", Some(format!("{:?}", other)))),
+        };
+
+        let mut tx = pool.begin().await?;
+        set_state(&mut tx, "applying_remote", "1").await?;
+
+        for remote in &changes {
+            if has_pending_change(&mut tx, &remote.change).await? {
+                report.skipped += 1;
+                continue;
+            }
+            apply_change(&mut tx, remote).await?;
+            report.pulled += 1;
+        }
+
+        sqlx::query("DELETE FROM sync_state WHERE key = 'applying_remote'")
+            .execute(&mut *tx)
+            .await?;
+        set_state(&mut tx, "pull_cursor", &next_cursor.to_string()).await?;
+
+        tx.commit().await.map_err(AppError::database("Failed to commit pulled changes"))?;
+
+        cursor = next_cursor;
+        if !has_more {
+            break;
+        }
+    }
+
+    report.cursor = cursor;
+    Ok(())
+}
+
+async fn push(
+    pool: &SqlitePool,
+    connection: &mut Connection,
+    secret: &str,
+    device_id: &str,
+    report: &mut SyncReport,
+) -> AppResult<()> {
+    loop {
+        let rows = sqlx::query(
+            r#"SELECT id, entity, entity_key, op, payload, changed_at
+            FROM sync_changes
+            ORDER BY id ASC
+            LIMIT ?"#,
+        )
+        .bind(MAX_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        if rows.is_empty() {
+            break;
+        }
+
+        let mut changes = Vec::with_capacity(rows.len());
+        let mut last_id = 0;
+        let mut bytes = 0;
+        for row in &rows {
+            // Large payloads leave the rest of the batch for the next push
+            let payload: Option<String> = row.get("payload");
+            let size = change_bytes(payload.as_deref());
+            if bytes > 0 && bytes + size > MAX_BATCH_BYTES {
+                break;
+            }
+            bytes += size;
+            last_id = row.get("id");
+
+            let op: String = row.get("op");
+            changes.push(Change {
+                entity: row.get("entity"),
+                key: row.get("entity_key"),
+                op: ChangeOp::parse(&op).ok_or(AppError::invalid_data("Invalid recorded operation", &op))?,
+                payload: payload
+                    .map(|p| serde_json::from_str(&p))
+                    .transpose()
+                    .map_err(|e| AppError::invalid_data("Invalid recorded payload", e))?,
+                changed_at: row.get("changed_at"),
+            });
+        }
+
+        let pushed = changes.len();
+        let response = connection
+            .request(&SyncRequest::Push {
+                version: PROTOCOL_VERSION,
+                secret: secret.to_string(),
+                device_id: device_id.to_string(),
+                changes,
+            })
+            .await?;
+
+        if !matches!(response, SyncResponse::Pushed { .. }) {
+            return Err(AppError::sync("Unexpected response to push", Some(format!("{:?}", response))));
+        }
+
+        // Only forget changes once the server has stored them
+        sqlx::query("DELETE FROM sync_changes WHERE id <= ?")
+            .bind(last_id)
+            .execute(pool)
+            .await?;
+
+        report.pushed += pushed;
+    }
+
+    Ok(())
+}
+
+async fn has_pending_change(conn: &mut SqliteConnection, change: &Change) -> AppResult<bool> {
+    let row = sqlx::query("SELECT 1 FROM sync_changes WHERE entity = ? AND entity_key = ? LIMIT 1")
+        .bind(&change.entity)
+        .bind(&change.key)
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+// What a statement in `apply_change` binds, in order
+#[derive(Clone, Copy)]
+enum Param {
+    Payload,
+    Key,
+}
+
+async fn apply_change(conn: &mut SqliteConnection, remote: &RemoteChange) -> AppResult<()> {
+    use Param::{Key, Payload};
+    let change = &remote.change;
+    let payload = change.payload.as_ref().map(|p| p.to_string());
+
+    let statements: &[(&str, &[Param])] = match (change.entity.as_str(), change.op) {
+        (ENTITY_WORDS, ChangeOp::Upsert) => &[(
+            r#"INSERT INTO words (word, status, meaning, stability, difficulty, last_review_date, next_review_date)
+            VALUES (?1, json_extract(?2, '$.status'), json_extract(?2, '$.meaning'),
+                json_extract(?2, '$.stability'), json_extract(?2, '$.difficulty'),
+                json_extract(?2, '$.last_review_date'), json_extract(?2, '$.next_review_date'))
+            ON CONFLICT(word) DO UPDATE SET
+                status = excluded.status,
+                meaning = excluded.meaning,
+                stability = excluded.stability,
+                difficulty = excluded.difficulty,
+                last_review_date = excluded.last_review_date,
+                next_review_date = excluded.next_review_date"#,
+            &[Key, Payload],
+        )],
+        (ENTITY_WORDS, ChangeOp::Delete) => &[
+            ("DELETE FROM text_words WHERE word_id IN (SELECT id FROM words WHERE word = ?)", &[Key]),
+            ("DELETE FROM words WHERE word = ?", &[Key]),
+        ],
+        (ENTITY_TEXTS, ChangeOp::Upsert) => &[(
+            r#"INSERT INTO texts (uid, title, content)
+            VALUES (?1, json_extract(?2, '$.title'), json_extract(?2, '$.content'))
+            ON CONFLICT(uid) DO UPDATE SET
+                title = excluded.title,
+                content = excluded.content"#,
+            &[Key, Payload],
+        )],
+        (ENTITY_TEXTS, ChangeOp::Delete) => &[
+            ("DELETE FROM text_words WHERE text_id IN (SELECT id FROM texts WHERE uid = ?)", &[Key]),
+            ("DELETE FROM texts WHERE uid = ?", &[Key]),
+        ],
+        // Keyed by "text uid:word", the payload has both parts
+        (ENTITY_TEXT_WORDS, ChangeOp::Upsert) => &[(
+            r#"INSERT OR IGNORE INTO text_words (text_id, word_id)
+            SELECT t.id, w.id FROM texts t, words w
+            WHERE t.uid = json_extract(?1, '$.text_uid') AND w.word = json_extract(?1, '$.word')"#,
+            &[Payload],
+        )],
+        (ENTITY_TEXT_WORDS, ChangeOp::Delete) => &[(
+            r#"DELETE FROM text_words
+            WHERE text_id = (SELECT id FROM texts WHERE uid = json_extract(?1, '$.text_uid'))
+                AND word_id = (SELECT id FROM words WHERE word = json_extract(?1, '$.word'))"#,
+            &[Payload],
+        )],
+        (ENTITY_USER_PREFERENCES, ChangeOp::Upsert) => &[
+            ("DELETE FROM user_preferences", &[]),
+            ("INSERT INTO user_preferences (country_code) VALUES (json_extract(?, '$.country_code'))", &[Payload]),
+        ],
+        (ENTITY_USER_PREFERENCES, ChangeOp::Delete) => &[],
+        (ENTITY_DICTIONARY_PROVIDERS, ChangeOp::Upsert) => &[(
+            r#"INSERT INTO dictionary_providers (uid, name, url_template, languages, target_language, position, embedded)
+            VALUES (?1, json_extract(?2, '$.name'), json_extract(?2, '$.url_template'),
+                json_extract(?2, '$.languages'), json_extract(?2, '$.target_language'),
+                json_extract(?2, '$.position'), json_extract(?2, '$.embedded'))
+            ON CONFLICT(uid) DO UPDATE SET
+                name = excluded.name,
+                url_template = excluded.url_template,
+                languages = excluded.languages,
+                target_language = excluded.target_language,
+                position = excluded.position,
+                embedded = excluded.embedded"#,
+            &[Key, Payload],
+        )],
+        (ENTITY_DICTIONARY_PROVIDERS, ChangeOp::Delete) => &[("DELETE FROM dictionary_providers WHERE uid = ?", &[Key])],
+        (entity, _) => return Err(AppError::invalid_data("Unknown sync entity", entity)),
+    };
+
+    for (statement, params) in statements {
+        let mut query = sqlx::query(statement);
+        for param in params.iter() {
+            query = match param {
+                Payload => query.bind(&payload),
+                Key => query.bind(&change.key),
+            };
+        }
+        query
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| AppError::Database {
+                message: format!("Failed to apply change {} from {}", remote.seq, remote.device_id),
+                source: e,
+            })?;
+    }
+
+    Ok(())
+}
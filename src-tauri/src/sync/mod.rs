@@ -0,0 +1,9 @@
+// Continuous sync between devices through a self-hosted sync server.
+// `protocol` is shared by both sides, `server` backs the `sync_server`
+// binary and `client` is what the app's commands use. `chunks` moves a
+// whole sync key between devices as a sequence of QR codes instead.
+
+pub mod protocol;
+pub mod server;
+pub mod client;
+pub mod chunks;
@@ -0,0 +1,143 @@
+// Wire format shared by the sync client and the reference sync server.
+//
+// A connection carries newline-delimited JSON: the client writes one
+// `SyncRequest` per line and the server answers each with one `SyncResponse`.
+// Devices push the changes they recorded locally and pull everything other
+// devices pushed after their cursor (the last server sequence number seen).
+// Every request carries the secret the server was started with. Nothing is
+// encrypted, so a server belongs on localhost or a trusted LAN.
+
+use serde::{Serialize, Deserialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, AsyncRead};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7878;
+pub const MAX_BATCH_SIZE: i64 = 500; // Upper bound for changes per push/pull
+// Upper bound for the changes of one push/pull in bytes; a single larger
+// change still travels alone as long as it fits in a message
+pub const MAX_BATCH_BYTES: usize = 8 * 1024 * 1024;
+pub const MAX_MESSAGE_BYTES: u64 = 16 * 1024 * 1024; // Longer lines are rejected unread
+const CHANGE_OVERHEAD_BYTES: usize = 256; // Entity, key, timestamps and JSON around the payload
+
+// Entities that take part in sync, named after their tables
+pub const ENTITY_WORDS: &str = "words";
+pub const ENTITY_TEXTS: &str = "texts";
+pub const ENTITY_TEXT_WORDS: &str = "text_words";
+pub const ENTITY_USER_PREFERENCES: &str = "user_preferences";
+pub const ENTITY_DICTIONARY_PROVIDERS: &str = "dictionary_providers";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOp {
+    Upsert,
+    Delete,
+}
+
+impl ChangeOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeOp::Upsert => "upsert",
+            ChangeOp::Delete => "delete",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ChangeOp> {
+        match value {
+            "upsert" => Some(ChangeOp::Upsert),
+            "delete" => Some(ChangeOp::Delete),
+            _ => None,
+        }
+    }
+}
+
+// A single row-level change. Rows are identified by natural keys
+// (the word itself, the text uid, ...) because local ids differ per device.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Change {
+    pub entity: String,
+    pub key: String,
+    pub op: ChangeOp,
+    pub payload: Option<serde_json::Value>, // Full row for upserts
+    pub changed_at: String, // ISO 8601, as recorded on the device
+}
+
+// A change as stored by the server, with its position in the global order
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteChange {
+    pub seq: i64,
+    pub device_id: String,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncRequest {
+    Push {
+        version: u32,
+        secret: String,
+        device_id: String,
+        changes: Vec<Change>,
+    },
+    Pull {
+        version: u32,
+        secret: String,
+        device_id: String,
+        cursor: i64,
+        limit: i64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncResponse {
+    Pushed {
+        cursor: i64, // Sequence number of the last stored change
+    },
+    Changes {
+        changes: Vec<RemoteChange>,
+        cursor: i64, // Cursor to send with the next pull
+        has_more: bool,
+    },
+    Error {
+        message: String,
+    },
+}
+
+// Roughly what a change adds to a message, for keeping batches under MAX_BATCH_BYTES
+pub fn change_bytes(payload: Option<&str>) -> usize {
+    CHANGE_OVERHEAD_BYTES + payload.map_or(0, str::len)
+}
+
+pub async fn write_message<W, T>(writer: &mut W, message: &T) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    writer.flush().await
+}
+
+// Returns `None` once the other side has closed the connection
+pub async fn read_message<R, T>(reader: &mut BufReader<R>) -> std::io::Result<Option<T>>
+where
+    R: AsyncRead + Unpin,
+    T: for<'de> Deserialize<'de>,
+{
+    let mut line = String::new();
+    let read = (&mut *reader).take(MAX_MESSAGE_BYTES).read_line(&mut line).await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read as u64 == MAX_MESSAGE_BYTES && !line.ends_with('\n') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Message exceeds {} bytes", MAX_MESSAGE_BYTES),
+        ));
+    }
+
+    let message = serde_json::from_str(line.trim_end())?;
+    Ok(Some(message))
+}
@@ -0,0 +1,235 @@
+// Reference sync server: stores every pushed change in SQLite and hands
+// them out in order. It keeps no per-device state besides what the
+// clients send, so any number of devices can share one server. Only
+// requests carrying the server's secret are answered.
+
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions, SqliteConnectOptions};
+use sqlx::Row;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+
+use super::protocol::{
+    change_bytes, read_message, write_message, Change, ChangeOp, RemoteChange, SyncRequest, SyncResponse,
+    MAX_BATCH_BYTES, MAX_BATCH_SIZE, PROTOCOL_VERSION,
+};
+
+const MAX_CONNECTIONS: usize = 64;
+// A whole request line has to arrive within this, idle connections are closed
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+const SERVER_SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    device_id TEXT NOT NULL,
+    entity TEXT NOT NULL,
+    entity_key TEXT NOT NULL,
+    op TEXT NOT NULL,
+    payload TEXT,
+    changed_at TEXT NOT NULL,
+    received_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+"#;
+
+pub async fn open_database(path: &str) -> Result<SqlitePool, sqlx::Error> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(
+            SqliteConnectOptions::new()
+                .filename(path)
+                .create_if_missing(true),
+        )
+        .await?;
+
+    sqlx::query(SERVER_SCHEMA).execute(&pool).await?;
+    Ok(pool)
+}
+
+// Accepts connections until the listener fails
+pub async fn serve(listener: TcpListener, pool: SqlitePool, secret: String) -> std::io::Result<()> {
+    if secret.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "The sync secret is empty"));
+    }
+
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        // At the limit further peers wait in the listen backlog
+        let permit = connections.clone().acquire_owned().await.map_err(std::io::Error::other)?;
+        let (stream, peer) = listener.accept().await?;
+        let pool = pool.clone();
+        let secret = secret.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &pool, &secret).await {
+                tracing::warn!("Sync connection from {} failed: {}", peer, e);
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn handle_connection(stream: TcpStream, pool: &SqlitePool, secret: &str) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let request = tokio::time::timeout(READ_TIMEOUT, read_message::<_, SyncRequest>(&mut reader))
+            .await
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "No request within the read timeout"))??;
+        let Some(request) = request else {
+            break;
+        };
+
+        let response = match handle_request(pool, secret, request).await {
+            Ok(response) => response,
+            Err(message) => SyncResponse::Error { message },
+        };
+        write_message(&mut writer, &response).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn handle_request(pool: &SqlitePool, secret: &str, request: SyncRequest) -> Result<SyncResponse, String> {
+    match request {
+        SyncRequest::Push { version, secret: sent, device_id, changes } => {
+            check_version(version)?;
+            check_secret(secret, &sent)?;
+            let cursor = store_changes(pool, &device_id, &changes).await?;
+            Ok(SyncResponse::Pushed { cursor })
+        }
+        SyncRequest::Pull { version, secret: sent, device_id, cursor, limit } => {
+            check_version(version)?;
+            check_secret(secret, &sent)?;
+            let limit = limit.clamp(1, MAX_BATCH_SIZE);
+            fetch_changes(pool, &device_id, cursor, limit).await
+        }
+    }
+}
+
+fn check_version(version: u32) -> Result<(), String> {
+    if version != PROTOCOL_VERSION {
+        return Err(format!(
+            "Unsupported protocol version {} (server speaks {})",
+            version, PROTOCOL_VERSION
+        ));
+    }
+    Ok(())
+}
+
+// Compares every byte so the time taken does not tell how much matched
+fn check_secret(secret: &str, sent: &str) -> Result<(), String> {
+    let matches = secret.len() == sent.len()
+        && secret.bytes().zip(sent.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0;
+    if secret.is_empty() || !matches {
+        return Err("Wrong sync secret".to_string());
+    }
+    Ok(())
+}
+
+async fn store_changes(pool: &SqlitePool, device_id: &str, changes: &[Change]) -> Result<i64, String> {
+    if device_id.is_empty() {
+        return Err("Missing device id".to_string());
+    }
+    if changes.len() as i64 > MAX_BATCH_SIZE {
+        return Err(format!("Too many changes in one push (at most {})", MAX_BATCH_SIZE));
+    }
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    for change in changes {
+        let payload = change.payload.as_ref().map(|p| p.to_string());
+        sqlx::query(
+            r#"INSERT INTO changes (device_id, entity, entity_key, op, payload, changed_at)
+            VALUES (?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(device_id)
+        .bind(&change.entity)
+        .bind(&change.key)
+        .bind(change.op.as_str())
+        .bind(payload)
+        .bind(&change.changed_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to store change: {}", e))?;
+    }
+
+    let cursor: i64 = sqlx::query("SELECT COALESCE(MAX(seq), 0) FROM changes")
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .get(0);
+
+    tx.commit().await.map_err(|e| format!("Failed to commit changes: {}", e))?;
+
+    Ok(cursor)
+}
+
+async fn fetch_changes(
+    pool: &SqlitePool,
+    device_id: &str,
+    cursor: i64,
+    limit: i64,
+) -> Result<SyncResponse, String> {
+    // Fetch one extra row to know whether another pull is needed
+    let rows = sqlx::query(
+        r#"SELECT seq, device_id, entity, entity_key, op, payload, changed_at
+        FROM changes
+        WHERE seq > ?
+        ORDER BY seq ASC
+        LIMIT ?"#,
+    )
+    .bind(cursor)
+    .bind(limit + 1)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut has_more = rows.len() as i64 > limit;
+    let mut next_cursor = cursor;
+    let mut changes = Vec::new();
+    let mut bytes = 0;
+
+    for row in rows.into_iter().take(limit as usize) {
+        // Large payloads leave the rest of the batch for the next pull
+        let payload: Option<String> = row.get("payload");
+        let size = change_bytes(payload.as_deref());
+        if bytes > 0 && bytes + size > MAX_BATCH_BYTES {
+            has_more = true;
+            break;
+        }
+        bytes += size;
+
+        let seq: i64 = row.get("seq");
+        next_cursor = seq;
+
+        // A device never needs its own changes back, but the cursor still moves past them
+        let origin: String = row.get("device_id");
+        if origin == device_id {
+            continue;
+        }
+
+        let op: String = row.get("op");
+        changes.push(RemoteChange {
+            seq,
+            device_id: origin,
+            change: Change {
+                entity: row.get("entity"),
+                key: row.get("entity_key"),
+                op: ChangeOp::parse(&op).ok_or(format!("Invalid stored operation: {}", op))?,
+                payload: payload
+                    .map(|p| serde_json::from_str(&p))
+                    .transpose()
+                    .map_err(|e| format!("Invalid stored payload: {}", e))?,
+                changed_at: row.get("changed_at"),
+            },
+        });
+    }
+
+    Ok(SyncResponse::Changes {
+        changes,
+        cursor: next_cursor,
+        has_more,
+    })
+}
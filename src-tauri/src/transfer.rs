@@ -0,0 +1,592 @@
+// Whole-library export and import, used by sync keys and by the
+// command-line tool.
+
+use base64::{Engine as _, engine::general_purpose};
+use flate2::write::GzEncoder;
+use flate2::read::GzDecoder;
+use flate2::Compression;
+use serde::{Serialize, Deserialize};
+use sqlx::sqlite::SqlitePool;
+use sqlx::FromRow;
+use std::collections::HashMap;
+use std::io::{Write, Read};
+
+use crate::error::{AppError, AppResult};
+use crate::models::{TextMetadata, TextWord, UserPreference, Word};
+use crate::repo::dictionary_providers::{self, DictionaryProvider};
+use crate::repo::progress::{Bookmark, ReadingProgress};
+
+// Sync keys from before collections, tags and metadata existed only have id, title and content
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ExportedText {
+    pub id: i64,
+    // Keeps a text the same sync entity on every device set up from the key,
+    // None in older keys, which gives the text a fresh uid
+    #[serde(default)]
+    pub uid: Option<String>,
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub collection_id: Option<i64>,
+    #[serde(default)]
+    pub position: Option<i64>,
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub metadata: TextMetadata,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub last_opened_at: Option<String>,
+    #[serde(default)]
+    #[sqlx(skip)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ExportedCollection {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ExportedTimestamp {
+    pub text_id: i64,
+    pub token_offset: i64,
+    pub token_count: i64,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+// Finish-page events, kept so they can still be undone after an import
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ExportedReadingEvent {
+    pub id: i64,
+    pub text_id: i64,
+    pub kind: String,
+    pub page: Option<i64>,
+    pub words_per_page: Option<i64>,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, FromRow)]
+pub struct ExportedReadingEventWord {
+    pub event_id: i64,
+    pub word_id: i64,
+    pub previous_status: Option<String>,
+    pub previous_next_review_date: Option<String>,
+}
+
+// Structure to hold all database content for export/import
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DatabaseExport {
+    pub texts: Vec<ExportedText>,
+    #[serde(default)]
+    pub collections: Vec<ExportedCollection>,
+    pub words: Vec<Word>,
+    pub text_words: Vec<TextWord>,
+    pub user_preferences: Vec<UserPreference>,
+    #[serde(default)]
+    pub reading_progress: Vec<ReadingProgress>,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub text_timestamps: Vec<ExportedTimestamp>,
+    #[serde(default)]
+    pub reading_events: Vec<ExportedReadingEvent>,
+    #[serde(default)]
+    pub reading_event_words: Vec<ExportedReadingEventWord>,
+    // None in sync keys from before providers were stored, which keeps the local ones
+    #[serde(default)]
+    pub dictionary_providers: Option<Vec<DictionaryProvider>>,
+    pub version: String, // For future compatibility
+}
+
+pub async fn build_sync_key(state: &SqlitePool) -> AppResult<String> {
+    // 1. Extract all data from the database
+    let db_export = export_database(state).await?;
+    
+    // 2. Serialize to JSON
+    let json_data = serde_json::to_string(&db_export)
+        .map_err(|e| AppError::internal("Failed to serialize database", e))?;
+    
+    // 3. Compress and encode
+    let sync_key = compress_and_encode(&json_data)
+        .map_err(AppError::io("Failed to compress data"))?;
+    
+    Ok(sync_key)
+}
+
+// Replaces everything in the database with the content of the sync key
+pub async fn apply_sync_key(state: &SqlitePool, sync_key: &str) -> AppResult<()> {
+    // 1. Decode and decompress
+    let json_data = decode_and_decompress(sync_key)
+        .map_err(|e| AppError::invalid_data("Failed to decode sync key", e))?;
+    
+    // 2. Deserialize
+    let db_export: DatabaseExport = serde_json::from_str(&json_data)
+        .map_err(|e| AppError::invalid_data("Failed to deserialize database", e))?;
+    
+    // 3. Import into database (this will overwrite existing data)
+    import_database(state, db_export).await?;
+    
+    Ok(())
+}
+
+pub async fn export_database(state: &SqlitePool) -> AppResult<DatabaseExport> {
+    // Fetch all texts with their tags
+    let mut texts = sqlx::query_as::<_, ExportedText>(
+        r#"SELECT id, uid, title, content, collection_id, position, source, author, level, notes,
+            created_at, updated_at, last_opened_at
+        FROM texts"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch texts"))?;
+
+    let text_tags: Vec<(i64, String)> = sqlx::query_as(
+        r#"SELECT tt.text_id, g.name FROM text_tags tt JOIN tags g ON g.id = tt.tag_id ORDER BY g.name"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch tags"))?;
+    let mut tags_by_text: HashMap<i64, Vec<String>> = HashMap::new();
+    for (text_id, tag) in text_tags {
+        tags_by_text.entry(text_id).or_default().push(tag);
+    }
+    for text in &mut texts {
+        text.tags = tags_by_text.remove(&text.id).unwrap_or_default();
+    }
+
+    // Fetch all collections
+    let collections = sqlx::query_as::<_, ExportedCollection>(
+        r#"SELECT id, name, description, created_at FROM collections"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch collections"))?;
+
+    // Fetch all words
+    let words = sqlx::query_as!(
+        Word,
+        r#"SELECT id, word, status, meaning, stability, difficulty, last_review_date, next_review_date FROM words"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch words"))?;
+
+    // Fetch all text_words relationships
+    let text_words = sqlx::query_as!(
+        TextWord,
+        r#"SELECT text_id, word_id FROM text_words"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch text_words"))?;
+
+    // Fetch user preferences
+    let user_preferences = sqlx::query_as!(
+        UserPreference,
+        r#"SELECT id, country_code FROM user_preferences"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch user preferences"))?;
+
+    // Fetch reading positions and bookmarks
+    let reading_progress = sqlx::query_as::<_, ReadingProgress>(
+        r#"SELECT text_id, token_offset, completed, completed_at, updated_at FROM reading_progress"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch reading progress"))?;
+
+    let bookmarks = sqlx::query_as::<_, Bookmark>(
+        r#"SELECT id, text_id, name, token_offset, created_at FROM bookmarks"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch bookmarks"))?;
+
+    // Fetch the sentence times of subtitle texts
+    let text_timestamps = sqlx::query_as::<_, ExportedTimestamp>(
+        r#"SELECT text_id, token_offset, token_count, start_ms, end_ms FROM text_timestamps"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch text timestamps"))?;
+
+    // Fetch finish-page events with the previous state of their words
+    let reading_events = sqlx::query_as::<_, ExportedReadingEvent>(
+        r#"SELECT id, text_id, kind, page, words_per_page, created_at, undone_at FROM reading_events"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch reading events"))?;
+
+    let reading_event_words = sqlx::query_as::<_, ExportedReadingEventWord>(
+        r#"SELECT event_id, word_id, previous_status, previous_next_review_date FROM reading_event_words"#
+    )
+    .fetch_all(state)
+    .await
+    .map_err(AppError::database("Failed to fetch reading event words"))?;
+
+    let dictionary_providers = dictionary_providers::list(state).await?;
+
+    // Create the export structure
+    let export = DatabaseExport {
+        texts,
+        collections,
+        words,
+        text_words,
+        user_preferences,
+        reading_progress,
+        bookmarks,
+        text_timestamps,
+        reading_events,
+        reading_event_words,
+        dictionary_providers: Some(dictionary_providers),
+        version: "1.0".to_string(), // For future compatibility
+    };
+
+    Ok(export)
+}
+
+pub async fn import_database(state: &SqlitePool, db_export: DatabaseExport) -> AppResult<()> {
+    // Begin a transaction
+    let mut tx = state.begin().await?;
+
+    // Clear existing tables
+    sqlx::query!("DELETE FROM text_words")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear text_words"))?;
+
+    sqlx::query("DELETE FROM reading_progress")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear reading progress"))?;
+
+    sqlx::query("DELETE FROM bookmarks")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear bookmarks"))?;
+
+    sqlx::query("DELETE FROM text_timestamps")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear text timestamps"))?;
+
+    sqlx::query("DELETE FROM reading_event_words")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear reading event words"))?;
+
+    sqlx::query("DELETE FROM reading_events")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear reading events"))?;
+
+    sqlx::query("DELETE FROM text_tags")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear text_tags"))?;
+
+    sqlx::query("DELETE FROM tags")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear tags"))?;
+
+    sqlx::query!("DELETE FROM texts")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear texts"))?;
+
+    sqlx::query("DELETE FROM collections")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear collections"))?;
+
+    sqlx::query!("DELETE FROM words")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear words"))?;
+
+    sqlx::query!("DELETE FROM user_preferences")
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to clear user_preferences"))?;
+
+    // Insert collections before the texts that belong to them
+    for collection in &db_export.collections {
+        sqlx::query(r#"INSERT INTO collections (id, name, description, created_at) VALUES (?, ?, ?, ?)"#)
+            .bind(collection.id)
+            .bind(&collection.name)
+            .bind(&collection.description)
+            .bind(&collection.created_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::database("Failed to insert collection"))?;
+    }
+
+    // Insert texts
+    for text in &db_export.texts {
+        sqlx::query(
+            r#"INSERT INTO texts (id, uid, title, content, collection_id, position, source, author, level, notes,
+                created_at, updated_at, last_opened_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(text.id)
+        .bind(&text.uid)
+        .bind(&text.title)
+        .bind(&text.content)
+        .bind(text.collection_id)
+        .bind(text.position)
+        .bind(&text.metadata.source)
+        .bind(&text.metadata.author)
+        .bind(&text.metadata.level)
+        .bind(&text.metadata.notes)
+        .bind(&text.created_at)
+        .bind(&text.updated_at)
+        .bind(&text.last_opened_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert text"))?;
+
+        for tag in &text.tags {
+            sqlx::query("INSERT OR IGNORE INTO tags (name) VALUES (?)")
+                .bind(tag)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::database("Failed to insert tag"))?;
+            sqlx::query("INSERT OR IGNORE INTO text_tags (text_id, tag_id) SELECT ?, id FROM tags WHERE name = ?")
+                .bind(text.id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::database("Failed to insert text tag"))?;
+        }
+    }
+
+    // Insert words
+    for word in &db_export.words {
+        sqlx::query!(
+            r#"INSERT INTO words (id, word, status, meaning, stability, difficulty, last_review_date, next_review_date) 
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+            word.id,
+            word.word,
+            word.status,
+            word.meaning,
+            word.stability,
+            word.difficulty,
+            word.last_review_date,
+            word.next_review_date
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert word"))?;
+    }
+
+    // Insert text_words relationships
+    for text_word in &db_export.text_words {
+        sqlx::query!(
+            r#"INSERT INTO text_words (text_id, word_id) VALUES (?, ?)"#,
+            text_word.text_id,
+            text_word.word_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert text_word relationship"))?;
+    }
+
+    // Insert user preferences
+    for pref in &db_export.user_preferences {
+        sqlx::query!(
+            r#"INSERT INTO user_preferences (id, country_code) VALUES (?, ?)"#,
+            pref.id,
+            pref.country_code
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert user preference"))?;
+    }
+
+    // Insert reading positions and bookmarks
+    for progress in &db_export.reading_progress {
+        sqlx::query(
+            r#"INSERT INTO reading_progress (text_id, token_offset, completed, completed_at, updated_at)
+            VALUES (?, ?, ?, ?, ?)"#,
+        )
+        .bind(progress.text_id)
+        .bind(progress.token_offset)
+        .bind(progress.completed)
+        .bind(&progress.completed_at)
+        .bind(&progress.updated_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert reading progress"))?;
+    }
+
+    for bookmark in &db_export.bookmarks {
+        sqlx::query(r#"INSERT INTO bookmarks (id, text_id, name, token_offset, created_at) VALUES (?, ?, ?, ?, ?)"#)
+            .bind(bookmark.id)
+            .bind(bookmark.text_id)
+            .bind(&bookmark.name)
+            .bind(bookmark.token_offset)
+            .bind(&bookmark.created_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::database("Failed to insert bookmark"))?;
+    }
+
+    // Insert the sentence times of subtitle texts
+    for timestamp in &db_export.text_timestamps {
+        sqlx::query(
+            r#"INSERT INTO text_timestamps (text_id, token_offset, token_count, start_ms, end_ms) VALUES (?, ?, ?, ?, ?)"#,
+        )
+        .bind(timestamp.text_id)
+        .bind(timestamp.token_offset)
+        .bind(timestamp.token_count)
+        .bind(timestamp.start_ms)
+        .bind(timestamp.end_ms)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert text timestamp"))?;
+    }
+
+    // Insert finish-page events after the texts and words they point to
+    for event in &db_export.reading_events {
+        sqlx::query(
+            r#"INSERT INTO reading_events (id, text_id, kind, page, words_per_page, created_at, undone_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(event.id)
+        .bind(event.text_id)
+        .bind(&event.kind)
+        .bind(event.page)
+        .bind(event.words_per_page)
+        .bind(&event.created_at)
+        .bind(&event.undone_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert reading event"))?;
+    }
+
+    for event_word in &db_export.reading_event_words {
+        sqlx::query(
+            r#"INSERT INTO reading_event_words (event_id, word_id, previous_status, previous_next_review_date)
+            VALUES (?, ?, ?, ?)"#,
+        )
+        .bind(event_word.event_id)
+        .bind(event_word.word_id)
+        .bind(&event_word.previous_status)
+        .bind(&event_word.previous_next_review_date)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::database("Failed to insert reading event word"))?;
+    }
+
+    if let Some(providers) = &db_export.dictionary_providers {
+        sqlx::query("DELETE FROM dictionary_providers")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::database("Failed to clear dictionary providers"))?;
+
+        for provider in providers {
+            let languages = serde_json::to_string(&provider.languages)
+                .map_err(|e| AppError::internal("Failed to serialize languages", e))?;
+            sqlx::query(
+                r#"INSERT INTO dictionary_providers (id, uid, name, url_template, languages, target_language, position,
+                    embedded, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+            )
+            .bind(provider.id)
+            .bind(&provider.uid)
+            .bind(&provider.name)
+            .bind(&provider.url_template)
+            .bind(languages)
+            .bind(&provider.target_language)
+            .bind(provider.position)
+            .bind(provider.embedded)
+            .bind(&provider.created_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::database("Failed to insert dictionary provider"))?;
+        }
+    }
+
+    // Commit the transaction
+    tx.commit().await.map_err(AppError::database("Failed to commit transaction"))?;
+
+    // Reset SQLite sequences
+    sqlx::query!("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM texts) WHERE name = 'texts'")
+        .execute(state)
+        .await
+        .map_err(AppError::database("Failed to reset texts sequence"))?;
+
+    sqlx::query("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM collections) WHERE name = 'collections'")
+        .execute(state)
+        .await
+        .map_err(AppError::database("Failed to reset collections sequence"))?;
+
+    sqlx::query("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM bookmarks) WHERE name = 'bookmarks'")
+        .execute(state)
+        .await
+        .map_err(AppError::database("Failed to reset bookmarks sequence"))?;
+
+    sqlx::query("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM reading_events) WHERE name = 'reading_events'")
+        .execute(state)
+        .await
+        .map_err(AppError::database("Failed to reset reading events sequence"))?;
+
+    sqlx::query("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM dictionary_providers) WHERE name = 'dictionary_providers'")
+        .execute(state)
+        .await
+        .map_err(AppError::database("Failed to reset dictionary providers sequence"))?;
+
+    sqlx::query!("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM words) WHERE name = 'words'")
+        .execute(state)
+        .await
+        .map_err(AppError::database("Failed to reset words sequence"))?;
+
+    sqlx::query!("UPDATE sqlite_sequence SET seq = (SELECT MAX(id) FROM user_preferences) WHERE name = 'user_preferences'")
+        .execute(state)
+        .await
+        .map_err(AppError::database("Failed to reset user_preferences sequence"))?;
+
+    Ok(())
+}
+
+fn compress_and_encode(data: &str) -> Result<String, std::io::Error> {
+    // Create a gzip encoder with high compression level
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    
+    // Write data to the encoder
+    encoder.write_all(data.as_bytes())?;
+    
+    // Finish compression and get the compressed data
+    let compressed_data = encoder.finish()?;
+    
+    // Encode to base64 for easy transfer
+    let encoded = general_purpose::URL_SAFE.encode(compressed_data);
+    
+    Ok(encoded)
+}
+
+fn decode_and_decompress(encoded: &str) -> Result<String, Box<dyn std::error::Error>> {
+    // Decode from base64
+    let compressed_data = general_purpose::URL_SAFE.decode(encoded)?;
+    
+    // Create a gzip decoder
+    let mut decoder = GzDecoder::new(&compressed_data[..]);
+    
+    // Read decompressed data
+    let mut decompressed_data = String::new();
+    decoder.read_to_string(&mut decompressed_data)?;
+    
+    Ok(decompressed_data)
+}
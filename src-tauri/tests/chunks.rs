@@ -0,0 +1,85 @@
+use word_echo_lib::sync::chunks::{assemble_sync_key, split_sync_key};
+
+// A URL-safe base64 looking key of `len` characters
+fn sync_key(len: usize) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    (0..len).map(|i| alphabet[(i * 7) % alphabet.len()] as char).collect()
+}
+
+#[test]
+fn split_chunks_assemble_to_the_key() {
+    let key = sync_key(250);
+
+    let chunks = split_sync_key(&key, 100).unwrap();
+    assert_eq!(chunks.len(), 3);
+    assert!(chunks[0].starts_with("WE1:1/3:"));
+
+    let assembly = assemble_sync_key(&chunks).unwrap();
+    assert_eq!(assembly.total, 3);
+    assert_eq!(assembly.received, [1, 2, 3]);
+    assert!(assembly.missing.is_empty());
+    assert_eq!(assembly.sync_key.as_deref(), Some(key.as_str()));
+}
+
+#[test]
+fn chunks_assemble_in_any_order_and_repeated() {
+    let key = sync_key(450);
+    let chunks = split_sync_key(&key, 100).unwrap();
+
+    let scanned = vec![
+        chunks[3].clone(),
+        chunks[1].clone(),
+        chunks[4].clone(),
+        chunks[1].clone(),
+        chunks[0].clone(),
+        chunks[2].clone(),
+        chunks[3].clone(),
+    ];
+
+    let assembly = assemble_sync_key(&scanned).unwrap();
+    assert_eq!(assembly.received, [1, 2, 3, 4, 5]);
+    assert_eq!(assembly.sync_key.as_deref(), Some(key.as_str()));
+}
+
+#[test]
+fn missing_chunks_are_reported() {
+    let chunks = split_sync_key(&sync_key(450), 100).unwrap();
+
+    let assembly = assemble_sync_key(&[chunks[3].clone(), chunks[0].clone()]).unwrap();
+
+    assert_eq!(assembly.total, 5);
+    assert_eq!(assembly.received, [1, 4]);
+    assert_eq!(assembly.missing, [2, 3, 5]);
+    assert_eq!(assembly.sync_key, None);
+}
+
+#[test]
+fn damaged_chunks_are_rejected() {
+    let chunks = split_sync_key(&sync_key(250), 100).unwrap();
+    let mut damaged = chunks[1].clone();
+    let last = damaged.pop().unwrap();
+    damaged.push(if last == 'A' { 'B' } else { 'A' });
+
+    let error = assemble_sync_key(&[chunks[0].clone(), damaged]).unwrap_err();
+
+    assert_eq!(error.code(), "invalid_data");
+}
+
+#[test]
+fn chunks_of_different_keys_are_rejected() {
+    let first = split_sync_key(&sync_key(250), 100).unwrap();
+    let second = split_sync_key(&sync_key(260), 100).unwrap();
+
+    let error = assemble_sync_key(&[first[0].clone(), second[1].clone()]).unwrap_err();
+
+    assert_eq!(error.code(), "invalid_input");
+}
+
+#[test]
+fn implausible_totals_are_rejected() {
+    let error = assemble_sync_key(&["WE1:1/18446744073709551615:00000000:00000000:".to_string()]).unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+
+    let error = split_sync_key("Schlüssel", 100).unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+}
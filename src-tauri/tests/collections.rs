@@ -0,0 +1,154 @@
+mod common;
+
+use common::{at, count, memory_pool, CREATED};
+use word_echo_lib::models::TextMetadata;
+use word_echo_lib::repo;
+use word_echo_lib::repo::texts::{TextFilter, TextSort};
+use word_echo_lib::repo::timestamps::SentenceTimestamp;
+use word_echo_lib::transfer;
+
+fn titles(texts: Vec<word_echo_lib::models::TextSummary>) -> Vec<String> {
+    texts.into_iter().map(|t| t.title).collect()
+}
+
+#[tokio::test]
+async fn texts_keep_their_order_in_a_collection() {
+    let pool = memory_pool().await;
+    let book = repo::collections::create(&pool, "Kafka", Some("Erzählungen")).await.unwrap();
+    let mut ids = Vec::new();
+    for title in ["Kapitel 1", "Kapitel 2", "Kapitel 3"] {
+        let text = repo::texts::create(&pool, title, "es war einmal", at(CREATED)).await.unwrap();
+        repo::collections::set_text_collection(&pool, text.id, Some(book.id)).await.unwrap();
+        ids.push(text.id);
+    }
+    repo::texts::create(&pool, "Loose", "allein", at(CREATED)).await.unwrap();
+
+    let filter = TextFilter { collection_id: Some(book.id), ..Default::default() };
+    assert_eq!(titles(repo::texts::list_summaries(&pool, &filter).await.unwrap()), ["Kapitel 1", "Kapitel 2", "Kapitel 3"]);
+
+    repo::collections::reorder(&pool, book.id, &[ids[2], ids[0], ids[1]]).await.unwrap();
+    assert_eq!(titles(repo::texts::list_summaries(&pool, &filter).await.unwrap()), ["Kapitel 3", "Kapitel 1", "Kapitel 2"]);
+
+    // Leaving a text out of the new order is a mistake, not a removal
+    let error = repo::collections::reorder(&pool, book.id, &[ids[0], ids[1]]).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+
+    assert_eq!(repo::collections::list(&pool).await.unwrap()[0].text_count, 3);
+}
+
+#[tokio::test]
+async fn deleting_a_collection_keeps_its_texts() {
+    let pool = memory_pool().await;
+    let course = repo::collections::create(&pool, "Kurs", None).await.unwrap();
+    let text = repo::texts::create(&pool, "Lektion", "Guten Tag", at(CREATED)).await.unwrap();
+    repo::collections::set_text_collection(&pool, text.id, Some(course.id)).await.unwrap();
+
+    repo::collections::delete(&pool, course.id).await.unwrap();
+
+    let texts = repo::texts::list_summaries(&pool, &TextFilter::default()).await.unwrap();
+    assert_eq!(texts.len(), 1);
+    assert_eq!(texts[0].collection_id, None);
+    assert_eq!(texts[0].position, None);
+}
+
+#[tokio::test]
+async fn collection_names_are_unique_ignoring_case() {
+    let pool = memory_pool().await;
+    repo::collections::create(&pool, "Märchen", None).await.unwrap();
+
+    let error = repo::collections::create(&pool, "märchen", None).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    let error = repo::collections::create(&pool, "  ", None).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+}
+
+#[tokio::test]
+async fn texts_are_filtered_by_tag() {
+    let pool = memory_pool().await;
+    let news = repo::texts::create(&pool, "Nachrichten", "heute", at(CREATED)).await.unwrap();
+    let poem = repo::texts::create(&pool, "Gedicht", "Mond", at(CREATED)).await.unwrap();
+
+    let tags = repo::tags::set_text_tags(&pool, news.id, &["News".into(), "b1".into(), "news ".into()]).await.unwrap();
+    assert_eq!(tags, ["b1", "News"]);
+    repo::tags::set_text_tags(&pool, poem.id, &["b1".into()]).await.unwrap();
+
+    let filter = TextFilter { tag: Some("news".into()), ..Default::default() };
+    assert_eq!(titles(repo::texts::list_summaries(&pool, &filter).await.unwrap()), ["Nachrichten"]);
+
+    // Tags no text has anymore disappear
+    repo::tags::set_text_tags(&pool, news.id, &[]).await.unwrap();
+    let tags = repo::tags::list(&pool).await.unwrap();
+    assert_eq!(tags.len(), 1);
+    assert_eq!((tags[0].name.as_str(), tags[0].text_count), ("b1", 1));
+}
+
+#[tokio::test]
+async fn tags_ignore_the_case_of_ascii_letters_only() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Übungen", "eins", at(CREATED)).await.unwrap();
+
+    let tags = ["UEBUNG".into(), "uebung".into(), "Übung".into(), "übung".into()];
+    let tags = repo::tags::set_text_tags(&pool, text.id, &tags).await.unwrap();
+
+    assert_eq!(tags, ["UEBUNG", "Übung", "übung"]);
+}
+
+#[tokio::test]
+async fn summaries_sort_by_title_and_last_opened_with_counts() {
+    let pool = memory_pool().await;
+    let first = repo::texts::create(&pool, "beta", "eins zwei", at(CREATED)).await.unwrap();
+    let second = repo::texts::create(&pool, "Alpha", "drei", at(CREATED)).await.unwrap();
+    repo::texts::create(&pool, "Gamma", "vier", at(CREATED)).await.unwrap();
+    repo::texts::mark_opened(&pool, first.id, at("2024-03-01T10:00:00Z")).await.unwrap();
+    repo::texts::mark_opened(&pool, second.id, at("2024-03-02T10:00:00Z")).await.unwrap();
+
+    let by_title = TextFilter { sort: Some(TextSort::Title), ..Default::default() };
+    assert_eq!(titles(repo::texts::list_summaries(&pool, &by_title).await.unwrap()), ["Alpha", "beta", "Gamma"]);
+
+    // Never opened texts come last
+    let recent = TextFilter { sort: Some(TextSort::LastOpened), descending: true, ..Default::default() };
+    let texts = repo::texts::list_summaries(&pool, &recent).await.unwrap();
+    assert_eq!(texts[1].new_count, 2);
+    assert!(texts[1].created_at.is_some());
+    assert_eq!(titles(texts), ["Alpha", "beta", "Gamma"]);
+}
+
+#[tokio::test]
+async fn sync_key_keeps_collections_tags_and_metadata() {
+    let source = memory_pool().await;
+    let book = repo::collections::create(&source, "Buch", None).await.unwrap();
+    let metadata = TextMetadata { author: Some("Fontane".into()), ..Default::default() };
+    let text = repo::texts::create_with_metadata(&source, "Kapitel", "Anfang", &metadata, at(CREATED)).await.unwrap();
+    repo::collections::set_text_collection(&source, text.id, Some(book.id)).await.unwrap();
+    repo::tags::set_text_tags(&source, text.id, &["roman".into()]).await.unwrap();
+
+    let target = memory_pool().await;
+    let sync_key = transfer::build_sync_key(&source).await.unwrap();
+    transfer::apply_sync_key(&target, &sync_key).await.unwrap();
+
+    let texts = repo::texts::list_summaries(&target, &TextFilter::default()).await.unwrap();
+    assert_eq!(texts[0].collection_id, Some(book.id));
+    assert_eq!(texts[0].position, Some(1));
+    assert_eq!(texts[0].tags, ["roman"]);
+    assert_eq!(texts[0].metadata, metadata);
+    assert_eq!(count(&target, "collections").await, 1);
+}
+
+#[tokio::test]
+async fn sync_key_keeps_sentence_timestamps() {
+    let source = memory_pool().await;
+    let text = repo::texts::create(&source, "Folge 1", "Hallo. Wie geht es dir?", at(CREATED)).await.unwrap();
+    let timestamps = vec![
+        SentenceTimestamp { token_offset: 0, token_count: 1, start_ms: 1000, end_ms: 2500 },
+        SentenceTimestamp { token_offset: 1, token_count: 4, start_ms: 3000, end_ms: 5200 },
+    ];
+    repo::timestamps::save(&source, text.id, &timestamps).await.unwrap();
+
+    let target = memory_pool().await;
+    repo::texts::create(&target, "Alt", "Wird ersetzt", at(CREATED)).await.unwrap();
+    let sync_key = transfer::build_sync_key(&source).await.unwrap();
+    transfer::apply_sync_key(&target, &sync_key).await.unwrap();
+
+    assert_eq!(repo::timestamps::list(&target, text.id).await.unwrap(), timestamps);
+    assert_eq!(count(&target, "text_timestamps").await, 2);
+}
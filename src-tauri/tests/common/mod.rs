@@ -0,0 +1,56 @@
+// Shared setup for the integration tests: a fresh in-memory library per test
+
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use word_echo_lib::models::Word;
+use word_echo_lib::repo;
+
+// A single connection that is never recycled, or the in-memory database would be lost
+pub async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(SqliteConnectOptions::new().in_memory(true).foreign_keys(true))
+        .await
+        .expect("Failed to open in-memory database");
+
+    word_echo_lib::init_db(&pool).await.expect("Failed to initialize database");
+    pool
+}
+
+// When the tests create their texts, unless the time matters
+pub const CREATED: &str = "2025-02-01T08:00:00+00:00";
+
+pub fn at(timestamp: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .expect("Invalid test timestamp")
+        .with_timezone(&Utc)
+}
+
+pub async fn word(pool: &SqlitePool, word: &str) -> Word {
+    repo::words::list(pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|w| w.word == word)
+        .unwrap_or_else(|| panic!("Word {} not found", word))
+}
+
+pub async fn count(pool: &SqlitePool, table: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table))
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+// Writes `content` to a file only this test process uses, `name` may have folders
+pub fn temp_file(name: &str, content: &[u8]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("wordecho-tests-{}", std::process::id()));
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, content).unwrap();
+    path
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use common::{at, memory_pool, temp_file, word, CREATED};
+use std::io::Write;
+use word_echo_lib::dictionary;
+use word_echo_lib::dictionary::lookup::{self, MatchKind};
+use word_echo_lib::dictionary::suggestions::{self, SuggestionSource};
+use word_echo_lib::repo;
+use word_echo_lib::repo::dictionary_providers::{self as providers, ProviderInput};
+
+// A StarDict dictionary in a folder of its own. Without a type sequence
+// every field of an article starts with its type.
+fn stardict(name: &str, same_type_sequence: Option<&str>, articles: &[(&str, &[u8])]) -> std::path::PathBuf {
+    let mut index = Vec::new();
+    let mut dict = Vec::new();
+    for (word, article) in articles {
+        index.extend_from_slice(word.as_bytes());
+        index.push(0);
+        index.extend_from_slice(&(dict.len() as u32).to_be_bytes());
+        index.extend_from_slice(&(article.len() as u32).to_be_bytes());
+        dict.extend_from_slice(article);
+    }
+    let mut ifo = format!("StarDict's dict ifo file\nversion=2.4.2\nwordcount={}\nbookname=Deutsch-Englisch\n", articles.len());
+    if let Some(sequence) = same_type_sequence {
+        ifo.push_str(&format!("sametypesequence={}\n", sequence));
+    }
+
+    let mut gzipped = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzipped.write_all(&dict).unwrap();
+    temp_file(&format!("{}/de-en.idx", name), &index);
+    temp_file(&format!("{}/de-en.dict.dz", name), &gzipped.finish().unwrap());
+    temp_file(&format!("{}/de-en.ifo", name), ifo.as_bytes())
+}
+
+#[tokio::test]
+async fn stardict_articles_become_entries() {
+    let pool = memory_pool().await;
+    let path = stardict("stardict-m", Some("m"), &[("Haus", b"house\nhome"), ("Hund", b"dog")]);
+
+    let imported = dictionary::import(&pool, &path, None, None).await.unwrap();
+    assert_eq!((imported.name.as_str(), imported.format.as_str(), imported.entry_count), ("Deutsch-Englisch", "stardict", 2));
+
+    let entries = repo::dictionaries::by_headword(&pool, "Haus", None).await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].definitions, ["house", "home"]);
+    assert_eq!(entries[0].dictionary_name, "Deutsch-Englisch");
+
+    // Typed fields: phonetics are left out, HTML loses its markup
+    let path = stardict("stardict-typed", None, &[("Katze", b"t[katse]\0h<b>cat</b>, <i>feline</i>\0")]);
+    let imported = dictionary::import(&pool, &path, Some("Tiere"), None).await.unwrap();
+    let entries = repo::dictionaries::by_headword(&pool, "Katze", Some(imported.id)).await.unwrap();
+    assert_eq!(entries[0].definitions, ["cat, feline"]);
+    assert!(repo::dictionaries::by_headword(&pool, "Haus", Some(imported.id)).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn tsv_lines_with_the_same_headword_are_merged() {
+    let pool = memory_pool().await;
+    let tsv = "word\tdefinition\tpos\n# Grundwortschatz\nlaufen\tto run\tverb\nlaufen\tto walk\tverb\nLauf\trun, course\tnoun\nkaputt\n";
+    let path = temp_file("grundwortschatz.tsv", tsv.as_bytes());
+
+    let imported = dictionary::import(&pool, &path, None, None).await.unwrap();
+    assert_eq!((imported.name.as_str(), imported.entry_count), ("grundwortschatz", 2));
+    let entries = repo::dictionaries::by_headword(&pool, "laufen", None).await.unwrap();
+    assert_eq!(entries[0].definitions, ["to run", "to walk"]);
+    assert_eq!(entries[0].part_of_speech.as_deref(), Some("verb"));
+
+    let error = dictionary::import(&pool, &temp_file("leer.tsv", b"# nichts\n"), None, None).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+    assert_eq!(repo::dictionaries::list(&pool).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn wiktionary_entries_keep_part_of_speech_and_inflections() {
+    let pool = memory_pool().await;
+    let jsonl = [
+        r#"{"word": "gehen", "pos": "verb", "lang_code": "de", "senses": [{"glosses": ["to go", "to walk"]}, {"glosses": ["to leave"]}], "forms": [{"form": "strong", "tags": ["class"]}, {"form": "ging", "tags": ["past"]}, {"form": "gegangen", "tags": ["participle", "past"]}]}"#,
+        r#"{"word": "ging", "pos": "verb", "lang_code": "de", "senses": [{"glosses": ["first/third-person singular preterite of gehen"]}]}"#,
+        r#"{"word": "Gehen", "pos": "noun", "lang_code": "de", "senses": [{"tags": ["no-gloss"]}]}"#,
+    ]
+    .join("\n");
+    let path = temp_file("de-extract.jsonl", jsonl.as_bytes());
+
+    let imported = dictionary::import(&pool, &path, Some("Wiktionary"), None).await.unwrap();
+    assert_eq!((imported.language.as_deref(), imported.entry_count), (Some("de"), 2));
+    let entries = repo::dictionaries::by_headword(&pool, "gehen", None).await.unwrap();
+    assert_eq!(entries[0].definitions, ["to walk", "to leave"]);
+    assert_eq!(entries[0].part_of_speech.as_deref(), Some("verb"));
+    let forms: Vec<&str> = entries[0].inflections.iter().map(|i| i.form.as_str()).collect();
+    assert_eq!(forms, ["ging", "gegangen"]);
+
+    repo::dictionaries::delete(&pool, imported.id).await.unwrap();
+    assert!(repo::dictionaries::by_headword(&pool, "gehen", None).await.unwrap().is_empty());
+    assert_eq!(common::count(&pool, "dictionary_entries").await, 0);
+
+    let error = dictionary::import(&pool, &temp_file("kaputt.jsonl", b"{\"word\": 1}"), None, None).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+}
+
+#[tokio::test]
+async fn lookups_fall_back_to_looser_matches() {
+    let pool = memory_pool().await;
+    let tsv = "Haus\thouse\tnoun\ncafé\tcafé\tnoun\nmaison\thouse\tnoun\nStraße\tstreet\tnoun\n";
+    let path = temp_file("woerter.tsv", tsv.as_bytes());
+    dictionary::import(&pool, &path, None, Some("FR")).await.unwrap();
+    let jsonl = r#"{"word": "gehen", "pos": "verb", "lang_code": "de", "senses": [{"glosses": ["to go"]}], "forms": [{"form": "ging", "tags": ["past"]}, {"form": "ging", "tags": ["past", "third-person"]}]}"#;
+    dictionary::import(&pool, &temp_file("verben.jsonl", jsonl.as_bytes()), None, None).await.unwrap();
+
+    let matches = |results: &[lookup::LookupResult]| -> Vec<(String, MatchKind, String, usize)> {
+        results
+            .iter()
+            .map(|r| (r.entry.headword.clone(), r.match_kind, r.matched_form.clone(), r.distance))
+            .collect()
+    };
+    let found = |headword: &str, kind: MatchKind, form: &str, distance: usize| {
+        vec![(headword.to_string(), kind, form.to_string(), distance)]
+    };
+
+    let results = lookup::lookup_word(&pool, "Haus", None).await.unwrap();
+    assert_eq!(matches(&results), found("Haus", MatchKind::Exact, "Haus", 0));
+    let results = lookup::lookup_word(&pool, "haus", None).await.unwrap();
+    assert_eq!(matches(&results), found("Haus", MatchKind::Case, "Haus", 0));
+    let results = lookup::lookup_word(&pool, "Cafe", None).await.unwrap();
+    assert_eq!(matches(&results), found("café", MatchKind::Diacritics, "café", 0));
+    let results = lookup::lookup_word(&pool, "strasse", None).await.unwrap();
+    assert_eq!(matches(&results), found("Straße", MatchKind::Diacritics, "Straße", 0));
+    let results = lookup::lookup_word(&pool, "Ging", None).await.unwrap();
+    assert_eq!(matches(&results), found("gehen", MatchKind::Inflection, "ging", 0));
+    let results = lookup::lookup_word(&pool, "maisons", None).await.unwrap();
+    assert_eq!(matches(&results), found("maison", MatchKind::Stem, "maison", 0));
+
+    // Fuzzy candidates only when nothing else matches, closest first
+    let results = lookup::lookup_word(&pool, "Hauss", None).await.unwrap();
+    assert_eq!(matches(&results), found("Haus", MatchKind::Fuzzy, "Haus", 1));
+    assert!(lookup::lookup_word(&pool, "Hose", None).await.unwrap().is_empty());
+    assert!(lookup::lookup_word(&pool, "Zeitung", None).await.unwrap().is_empty());
+    assert_eq!(lookup::lookup_word(&pool, " ", None).await.unwrap_err().code(), "invalid_input");
+}
+
+#[tokio::test]
+async fn entries_imported_before_lookup_keys_are_indexed() {
+    let pool = memory_pool().await;
+    let path = temp_file("alt.tsv", "Bäume\ttrees\n".as_bytes());
+    dictionary::import(&pool, &path, None, None).await.unwrap();
+    sqlx::query("UPDATE dictionary_entries SET folded_headword = NULL, plain_headword = NULL, stem = NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(lookup::lookup_word(&pool, "baume", None).await.unwrap().is_empty());
+
+    repo::dictionaries::index_entries(&pool).await.unwrap();
+    let results = lookup::lookup_word(&pool, "baume", None).await.unwrap();
+    assert_eq!(results[0].entry.headword, "Bäume");
+    assert_eq!(results[0].match_kind, MatchKind::Diacritics);
+}
+
+#[tokio::test]
+async fn shipped_providers_link_to_the_word_in_the_picked_language() {
+    let pool = memory_pool().await;
+    let names: Vec<String> = providers::list(&pool).await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, ["dict.cc", "DeepL", "Google Translate", "TheFreeDictionary"]);
+
+    repo::preferences::save_country_code(&pool, "de").await.unwrap();
+    let links = providers::links(&pool, "Häuser", None).await.unwrap();
+    let urls: Vec<&str> = links.iter().map(|l| l.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        [
+            "https://m.dict.cc/deutsch-englisch/H%C3%A4user.html",
+            "https://www.deepl.com/en/translator#de/en/H%C3%A4user",
+            "https://translate.google.com/?sl=de&tl=en&text=H%C3%A4user&op=translate",
+            "https://de.thefreedictionary.com/H%C3%A4user",
+        ]
+    );
+    assert!(links[0].embedded && !links[1].embedded);
+
+    // No Swedish edition of TheFreeDictionary, and no made up fallback for unknown languages
+    let links = providers::links(&pool, "hus", Some("se")).await.unwrap();
+    assert_eq!(links.len(), 3);
+    assert_eq!(links[1].url, "https://www.deepl.com/en/translator#sv/en/hus");
+    assert!(providers::links(&pool, "ev", Some("xx")).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn providers_can_be_added_edited_reordered_and_restored() {
+    let pool = memory_pool().await;
+    let mut input = ProviderInput {
+        name: " Wiktionary ".to_string(),
+        url_template: "https://{src}.wiktionary.org/wiki/{word}".to_string(),
+        ..Default::default()
+    };
+    let wiktionary = providers::create(&pool, &input).await.unwrap();
+    assert_eq!((wiktionary.name.as_str(), wiktionary.position, wiktionary.target_language.as_str()), ("Wiktionary", 5, "en"));
+    // Without language codes {src} is the picked language itself
+    let links = providers::links(&pool, "gehen", Some("de")).await.unwrap();
+    assert_eq!(links[4].url, "https://de.wiktionary.org/wiki/gehen");
+
+    input.languages = [("DK".to_string(), "da".to_string())].into();
+    let wiktionary = providers::update(&pool, wiktionary.id, &input).await.unwrap();
+    assert_eq!(wiktionary.languages.get("dk").map(String::as_str), Some("da"));
+    assert_eq!(providers::links(&pool, "gå", Some("dk")).await.unwrap()[3].url, "https://da.wiktionary.org/wiki/g%C3%A5");
+
+    for (template, reason) in [("ftp://example.com/{word}", "https"), ("https://example.com/", "{word}"), ("https://example.com/{word}?l={lang}", "{lang}")] {
+        let input = ProviderInput { name: "X".to_string(), url_template: template.to_string(), ..Default::default() };
+        let error = providers::create(&pool, &input).await.unwrap_err();
+        assert_eq!(error.code(), "invalid_input");
+        assert!(error.to_string().contains(reason), "{}", error);
+    }
+
+    let mut ids: Vec<i64> = providers::list(&pool).await.unwrap().iter().map(|p| p.id).collect();
+    ids.rotate_right(1);
+    providers::reorder(&pool, &ids).await.unwrap();
+    assert_eq!(providers::list(&pool).await.unwrap()[0].name, "Wiktionary");
+    assert_eq!(providers::reorder(&pool, &ids[1..]).await.unwrap_err().code(), "invalid_input");
+
+    let dict_cc = providers::list(&pool).await.unwrap().remove(1);
+    providers::delete(&pool, dict_cc.id).await.unwrap();
+    assert_eq!(providers::delete(&pool, dict_cc.id).await.unwrap_err().code(), "not_found");
+    providers::restore_defaults(&pool).await.unwrap();
+    let names: Vec<String> = providers::list(&pool).await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, ["Wiktionary", "DeepL", "Google Translate", "TheFreeDictionary", "dict.cc"]);
+}
+
+#[tokio::test]
+async fn meanings_are_suggested_from_related_words_then_dictionaries() {
+    let pool = memory_pool().await;
+    let jsonl = r#"{"word": "gehen", "pos": "verb", "lang_code": "de", "senses": [{"glosses": ["to go"]}, {"glosses": ["to walk"]}], "forms": [{"form": "ging", "tags": ["past"]}, {"form": "gegangen", "tags": ["participle"]}]}"#;
+    dictionary::import(&pool, &temp_file("gehen.jsonl", jsonl.as_bytes()), Some("Verben"), None).await.unwrap();
+    repo::preferences::save_country_code(&pool, "de").await.unwrap();
+    repo::texts::create(&pool, "Wörter", "gehen ging gegangen Haus haus Zeitung Zeitungen", at(CREATED)).await.unwrap();
+    for (w, meaning) in [("gehen", "to go"), ("gegangen", "gone"), ("Haus", "house"), ("Zeitung", "newspaper")] {
+        repo::words::update_meaning(&pool, word(&pool, w).await.id, meaning, chrono::Utc::now()).await.unwrap();
+    }
+
+    let suggest = |w: &'static str| {
+        let pool = pool.clone();
+        async move {
+            let suggestions = suggestions::suggest_meanings(&pool, word(&pool, w).await.id).await.unwrap();
+            suggestions.into_iter().map(|s| (s.meaning, s.source, s.match_kind)).collect::<Vec<_>>()
+        }
+    };
+    let vocabulary = |meaning: &str, kind| (meaning.to_string(), SuggestionSource::Vocabulary, kind);
+    let dictionary = |meaning: &str, kind| (meaning.to_string(), SuggestionSource::Dictionary, kind);
+
+    // Other forms of the lemma first, a definition the user already wrote only once
+    assert_eq!(
+        suggest("ging").await,
+        [
+            vocabulary("gone", MatchKind::Inflection),
+            vocabulary("to go", MatchKind::Inflection),
+            dictionary("to walk", MatchKind::Inflection),
+        ]
+    );
+    // Not the meaning the word already has
+    assert_eq!(
+        suggest("gehen").await,
+        [vocabulary("gone", MatchKind::Inflection), dictionary("to walk", MatchKind::Exact)]
+    );
+    assert_eq!(suggest("haus").await, [vocabulary("house", MatchKind::Case)]);
+    assert_eq!(suggest("Zeitungen").await, [vocabulary("newspaper", MatchKind::Stem)]);
+
+    assert_eq!(suggestions::suggest_meanings(&pool, 999).await.unwrap_err().code(), "not_found");
+}
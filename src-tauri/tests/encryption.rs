@@ -0,0 +1,111 @@
+mod common;
+
+use common::{at, count, temp_file, CREATED};
+use std::path::{Path, PathBuf};
+use word_echo_lib::{bootstrap, encryption, repo};
+
+// A library database file with one text, `name` must be unique per test
+async fn library(name: &str) -> PathBuf {
+    let path = temp_file(name, b"");
+    let pool = bootstrap::open_database(&path, None).await.unwrap();
+    word_echo_lib::init_db(&pool).await.unwrap();
+    repo::texts::create(&pool, "Tagebuch", "streng geheim", at(CREATED)).await.unwrap();
+    pool.close().await;
+    path
+}
+
+// Opens the database like the app does and counts its texts
+async fn text_count(path: &Path, passphrase: Option<&str>) -> i64 {
+    let pool = bootstrap::open_database(path, passphrase).await.unwrap();
+    let texts = count(&pool, "texts").await;
+    pool.close().await;
+    texts
+}
+
+async fn user_version(path: &Path, passphrase: Option<&str>) -> i64 {
+    let pool = bootstrap::open_database(path, passphrase).await.unwrap();
+    let version = sqlx::query_scalar("PRAGMA user_version").fetch_one(&pool).await.unwrap();
+    pool.close().await;
+    version
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path.display(), suffix))
+}
+
+#[tokio::test]
+async fn encryption_is_enabled_changed_and_removed() {
+    let path = library("verschluesselt.sqlite").await;
+    let version = user_version(&path, None).await;
+
+    let pool = bootstrap::open_database(&path, None).await.unwrap();
+    encryption::rewrite(&pool, &path, Some("erstes Passwort")).await.unwrap();
+    assert!(encryption::is_encrypted(&path));
+    encryption::verify_passphrase(&path, "erstes Passwort").await.unwrap();
+    let error = encryption::verify_passphrase(&path, "falsches Passwort").await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    assert_eq!(text_count(&path, Some("erstes Passwort")).await, 1);
+
+    let pool = bootstrap::open_database(&path, Some("erstes Passwort")).await.unwrap();
+    encryption::rewrite(&pool, &path, Some("zweites Passwort")).await.unwrap();
+    assert!(encryption::is_encrypted(&path));
+    let error = encryption::verify_passphrase(&path, "erstes Passwort").await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    assert_eq!(text_count(&path, Some("zweites Passwort")).await, 1);
+
+    let pool = bootstrap::open_database(&path, Some("zweites Passwort")).await.unwrap();
+    encryption::rewrite(&pool, &path, None).await.unwrap();
+    assert!(!encryption::is_encrypted(&path));
+    assert_eq!(text_count(&path, None).await, 1);
+    // Or the migrations would run again on the next start
+    assert_eq!(user_version(&path, None).await, version);
+
+    assert!(!sibling(&path, "rewrite").exists());
+    assert!(!sibling(&path, "bak").exists());
+}
+
+#[tokio::test]
+async fn an_interrupted_rewrite_brings_the_backup_back() {
+    let path = library("unterbrochen.sqlite").await;
+    // Crashed after the old database was moved away, before the new one took its place
+    std::fs::rename(&path, sibling(&path, "bak")).unwrap();
+    std::fs::write(sibling(&path, "rewrite"), b"halb geschrieben").unwrap();
+
+    encryption::recover_interrupted_rewrite(&path);
+
+    assert!(!sibling(&path, "bak").exists());
+    assert!(!encryption::is_encrypted(&path));
+    assert_eq!(text_count(&path, None).await, 1);
+
+    // The leftover rewrite file does not get in the way of the next rewrite
+    let pool = bootstrap::open_database(&path, None).await.unwrap();
+    encryption::rewrite(&pool, &path, Some("neues Passwort")).await.unwrap();
+    assert_eq!(text_count(&path, Some("neues Passwort")).await, 1);
+    assert!(!sibling(&path, "rewrite").exists());
+}
+
+#[tokio::test]
+async fn a_database_in_place_is_not_replaced_by_a_backup() {
+    let path = library("vorhanden.sqlite").await;
+    std::fs::write(sibling(&path, "bak"), b"alte Kopie").unwrap();
+
+    encryption::recover_interrupted_rewrite(&path);
+
+    assert_eq!(text_count(&path, None).await, 1);
+    assert!(sibling(&path, "bak").exists());
+}
+
+#[tokio::test]
+async fn a_failed_rewrite_keeps_the_database_and_closes_the_pool() {
+    let path = library("fehlgeschlagen.sqlite").await;
+    // A directory in the way of the rewrite file cannot be removed like a file
+    std::fs::create_dir(sibling(&path, "rewrite")).unwrap();
+
+    let pool = bootstrap::open_database(&path, None).await.unwrap();
+    assert!(encryption::rewrite(&pool, &path, Some("neues Passwort")).await.is_err());
+
+    // The app restarts after every rewrite, nothing may write to the old pool
+    assert!(pool.is_closed());
+    assert!(!encryption::is_encrypted(&path));
+    assert_eq!(text_count(&path, None).await, 1);
+}
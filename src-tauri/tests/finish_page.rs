@@ -0,0 +1,72 @@
+mod common;
+
+use common::{at, memory_pool, word, CREATED};
+use word_echo_lib::{repo, transfer};
+
+fn status(w: &word_echo_lib::models::Word) -> &str {
+    w.status.as_deref().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn finishing_a_text_marks_remaining_new_words_known() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Kurz", "Der Hund bellt laut", at(CREATED)).await.unwrap();
+    repo::words::update_meaning(&pool, word(&pool, "Hund").await.id, "dog", at("2024-06-01T08:00:00Z")).await.unwrap();
+
+    let event = repo::reading_events::finish_page(&pool, text.id, None, at("2024-06-01T09:00:00Z")).await.unwrap();
+
+    assert_eq!(event.word_count, 3);
+    assert_eq!(event.page, None);
+    assert_eq!(status(&word(&pool, "bellt").await), "known");
+    // Words with a meaning are being learned, not known
+    assert_eq!(status(&word(&pool, "Hund").await), "seen");
+    assert_eq!(repo::texts::word_counts(&pool, text.id).await.unwrap(), (0, 1, 3));
+}
+
+#[tokio::test]
+async fn finishing_a_page_leaves_other_pages_alone() {
+    let pool = memory_pool().await;
+    let first: Vec<String> = (0..20).map(|i| format!("erst{}", char::from(b'a' + i))).collect();
+    let second: Vec<String> = (0..20).map(|i| format!("zweit{}", char::from(b'a' + i))).collect();
+    let content = format!("{}.\n{}.", first.join(" "), second.join(" "));
+    let text = repo::texts::create(&pool, "Zwei Seiten", &content, at(CREATED)).await.unwrap();
+
+    let event = repo::reading_events::finish_page(&pool, text.id, Some((0, 20)), at("2024-06-01T09:00:00Z")).await.unwrap();
+
+    assert_eq!((event.page, event.word_count), (Some(0), 20));
+    assert_eq!(status(&word(&pool, "ersta").await), "known");
+    assert_eq!(status(&word(&pool, "zweita").await), "new");
+}
+
+#[tokio::test]
+async fn undo_restores_words_that_were_not_changed_since() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Zurück", "eins zwei drei", at(CREATED)).await.unwrap();
+    let event = repo::reading_events::finish_page(&pool, text.id, None, at("2024-06-01T09:00:00Z")).await.unwrap();
+    repo::words::update_meaning(&pool, word(&pool, "zwei").await.id, "two", at("2024-06-01T09:05:00Z")).await.unwrap();
+
+    let undone = repo::reading_events::undo(&pool, event.id, at("2024-06-01T09:10:00Z")).await.unwrap();
+
+    assert!(undone.undone_at.is_some());
+    assert_eq!(status(&word(&pool, "eins").await), "new");
+    assert_eq!(status(&word(&pool, "zwei").await), "seen");
+
+    let error = repo::reading_events::undo(&pool, event.id, at("2024-06-01T09:11:00Z")).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    assert_eq!(repo::reading_events::list(&pool, text.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn events_can_be_undone_after_a_sync_key() {
+    let source = memory_pool().await;
+    let text = repo::texts::create(&source, "Mitgenommen", "eins zwei drei", at(CREATED)).await.unwrap();
+    let event = repo::reading_events::finish_page(&source, text.id, None, at("2024-06-01T09:00:00Z")).await.unwrap();
+
+    let target = memory_pool().await;
+    let sync_key = transfer::build_sync_key(&source).await.unwrap();
+    transfer::apply_sync_key(&target, &sync_key).await.unwrap();
+
+    assert_eq!(repo::reading_events::list(&target, text.id).await.unwrap()[0].word_count, 3);
+    repo::reading_events::undo(&target, event.id, at("2024-06-01T09:10:00Z")).await.unwrap();
+    assert_eq!(status(&word(&target, "eins").await), "new");
+}
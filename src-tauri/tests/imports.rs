@@ -0,0 +1,401 @@
+mod common;
+
+use common::{at, memory_pool, temp_file, CREATED};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Bookmark, Object, Stream};
+use std::io::Write;
+use word_echo_lib::import;
+use word_echo_lib::models::TextMetadata;
+use word_echo_lib::repo;
+use word_echo_lib::repo::texts::TextFilter;
+use zip::write::SimpleFileOptions;
+
+fn epub(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+</container>"#;
+
+const OPF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Die Verwandlung</dc:title>
+    <dc:creator>Franz Kafka</dc:creator>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="cover" href="cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c1" href="text/kapitel%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="text/kapitel2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="notes" href="notes.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="cover"/>
+    <itemref idref="c1"/>
+    <itemref idref="c2"/>
+    <itemref idref="notes" linear="no"/>
+  </spine>
+</package>"#;
+
+const NAV: &str = r#"<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops"><body>
+  <nav epub:type="toc"><ol><li><a href="text/kapitel%201.xhtml#start">Erstes Kapitel</a></li></ol></nav>
+</body></html>"#;
+
+const CHAPTER_1: &str = r#"<html><head><title>ignored</title><style>p { margin: 0 }</style></head><body>
+  <h1>I</h1>
+  <p>Als Gregor Samsa eines Morgens
+     aus unruhigen Träumen erwachte,</p>
+  <p>fand er sich in seinem Bett&nbsp;zu einem <i>ungeheueren</i> Ungeziefer verwandelt.</p>
+</body></html>"#;
+
+const CHAPTER_2: &str = r#"<html><body><h2>Zweites Kapitel</h2><p>Erst in der Abenddämmerung erwachte Gregor.</p></body></html>"#;
+
+fn book() -> Vec<u8> {
+    epub(&[
+        ("mimetype", "application/epub+zip"),
+        ("META-INF/container.xml", CONTAINER),
+        ("OEBPS/content.opf", OPF),
+        ("OEBPS/nav.xhtml", NAV),
+        ("OEBPS/cover.xhtml", r#"<html><body><img src="cover.jpg"/></body></html>"#),
+        ("OEBPS/text/kapitel 1.xhtml", CHAPTER_1),
+        ("OEBPS/text/kapitel2.xhtml", CHAPTER_2),
+        ("OEBPS/notes.xhtml", "<html><body><p>Anmerkung</p></body></html>"),
+    ])
+}
+
+#[test]
+fn epub_chapters_follow_the_spine() {
+    let book = import::epub::parse(&book()).unwrap();
+
+    assert_eq!(book.title.as_deref(), Some("Die Verwandlung"));
+    assert_eq!(book.author.as_deref(), Some("Franz Kafka"));
+    let titles: Vec<Option<&str>> = book.chapters.iter().map(|c| c.title.as_deref()).collect();
+    assert_eq!(titles, [Some("Erstes Kapitel"), Some("Zweites Kapitel")]);
+    assert_eq!(
+        book.chapters[0].content,
+        "I\n\nAls Gregor Samsa eines Morgens aus unruhigen Träumen erwachte,\n\n\
+         fand er sich in seinem Bett zu einem ungeheueren Ungeziefer verwandelt."
+    );
+}
+
+#[test]
+fn broken_epubs_are_rejected() {
+    let error = import::epub::parse(b"not a zip file").unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+
+    let error = import::epub::parse(&epub(&[("mimetype", "application/epub+zip")])).unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+}
+
+#[tokio::test]
+async fn epub_import_creates_a_collection_of_chapters() {
+    let pool = memory_pool().await;
+    let path = temp_file("verwandlung.epub", &book());
+
+    let report = import::epub::import(&pool, &path, at(CREATED)).await.unwrap();
+    let second = import::epub::import(&pool, &path, at(CREATED)).await.unwrap();
+
+    let collection = report.collection.unwrap();
+    assert_eq!((collection.name.as_str(), collection.text_count), ("Die Verwandlung", 2));
+    assert_eq!(second.collection.unwrap().name, "Die Verwandlung (2)");
+
+    let filter = TextFilter { collection_id: Some(collection.id), ..Default::default() };
+    let texts = repo::texts::list_summaries(&pool, &filter).await.unwrap();
+    let titles: Vec<&str> = texts.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["Erstes Kapitel", "Zweites Kapitel"]);
+    assert_eq!(texts[0].metadata.author.as_deref(), Some("Franz Kafka"));
+    assert_eq!(texts[0].metadata.source.as_deref(), Some("verwandlung.epub"));
+    assert!(texts[0].new_count > 0);
+}
+
+const SRT: &str = "1\r\n00:00:01,000 --> 00:00:03,500\r\n<i>Guten Morgen, Anna.</i> Wie geht\r\n\r\n2\r\n00:00:03,600 --> 00:00:05,000\r\n{\\an8}es dir?\r\n\r\n3\r\n00:00:05,100 --> 00:00:06,000\r\n[Musik]\r\n\r\n4\r\n00:00:10,000 --> 00:00:12,250\r\n- Gut, danke.\r\n- Und dir?\r\n";
+
+const VTT: &str = "WEBVTT\n\nNOTE Erste Folge\n\nSTYLE\n::cue { color: yellow }\n\nintro\n00:01.000 --> 00:02.500 align:start position:10%\n<v Anna>Hallo &amp; willkommen!</v>\n\n01:00:02.500 --> 01:00:04.000\n<c.yellow>Bis</c> <00:00:03.000>bald.\n";
+
+#[test]
+fn subtitle_cues_are_cleaned() {
+    let cues = import::subtitles::parse(SRT).unwrap();
+    let texts: Vec<&str> = cues.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(texts, ["Guten Morgen, Anna. Wie geht", "es dir?", "Gut, danke. Und dir?"]);
+    assert_eq!((cues[0].start_ms, cues[0].end_ms), (1000, 3500));
+
+    let cues = import::subtitles::parse(VTT).unwrap();
+    let texts: Vec<&str> = cues.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(texts, ["Hallo & willkommen!", "Bis bald."]);
+    assert_eq!((cues[1].start_ms, cues[1].end_ms), (3_602_500, 3_604_000));
+
+    let error = import::subtitles::parse("1\nkeine Zeit\nHallo\n").unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+}
+
+#[test]
+fn subtitle_sentences_keep_the_time_of_their_cues() {
+    let cues = import::subtitles::parse(SRT).unwrap();
+    let transcript = import::subtitles::transcript(&cues);
+
+    // The pause before the last cue starts a new paragraph
+    assert_eq!(transcript.content, "Guten Morgen, Anna. Wie geht es dir?\n\nGut, danke. Und dir?");
+    let times: Vec<(i64, i64, i64, i64)> = transcript
+        .timestamps
+        .iter()
+        .map(|t| (t.token_offset, t.token_count, t.start_ms, t.end_ms))
+        .collect();
+    assert_eq!(times, [(0, 3, 1000, 3500), (3, 4, 1000, 5000), (7, 2, 10000, 12250), (9, 2, 10000, 12250)]);
+}
+
+#[tokio::test]
+async fn subtitle_import_stores_timestamps_until_the_content_changes() {
+    let pool = memory_pool().await;
+    let path = temp_file("folge1.srt", SRT.as_bytes());
+
+    let report = import::subtitles::import(&pool, &path, None, at(CREATED)).await.unwrap();
+    assert!(report.collection.is_none());
+    let text = repo::texts::get(&pool, report.texts[0].id).await.unwrap().unwrap();
+    assert_eq!(text.title, "folge1");
+    assert_eq!(text.metadata.source.as_deref(), Some("folge1.srt"));
+
+    // "dir" in "Wie geht es dir?"
+    let sentence = repo::timestamps::at_offset(&pool, text.id, 6).await.unwrap().unwrap();
+    assert_eq!((sentence.start_ms, sentence.end_ms), (1000, 5000));
+    assert!(repo::timestamps::at_offset(&pool, text.id, 11).await.unwrap().is_none());
+
+    repo::texts::update(&pool, text.id, "Folge 1", &text.content, chrono::Utc::now()).await.unwrap();
+    assert_eq!(repo::timestamps::list(&pool, text.id).await.unwrap().len(), 4);
+    repo::texts::update(&pool, text.id, "Folge 1", "Ganz anders.", chrono::Utc::now()).await.unwrap();
+    assert!(repo::timestamps::list(&pool, text.id).await.unwrap().is_empty());
+}
+
+const ARTICLE: &str = r#"<!DOCTYPE html>
+<html><head>
+  <title>Der Bäcker von nebenan | Stadtblatt</title>
+  <meta name="author" content="Lena Vogel">
+  <script>var tracking = "Werbung";</script>
+</head><body>
+  <header><a href="/">Stadtblatt</a> <a href="/lokales">Lokales</a> <a href="/sport">Sport</a></header>
+  <nav><ul><li><a href="/a">Startseite</a></li><li><a href="/b">Kontakt</a></li></ul></nav>
+  <div class="ad-banner">Jetzt kaufen, nur heute, alles günstiger!</div>
+  <main><article class="article-body">
+    <h1>Der Bäcker von nebenan</h1>
+    <p>Jeden Morgen um vier Uhr steht Paul in seiner kleinen Backstube, knetet den Teig und heizt den Ofen an.</p>
+    <p>Die Nachbarn lieben sein Brot, und am Samstag, wenn die Schlange bis zur Ecke reicht, verkauft er auch Kuchen.</p>
+    <ul class="tags"><li><a href="/t/brot">Brot</a></li><li><a href="/t/handwerk">Handwerk</a></li></ul>
+  </article></main>
+  <aside class="sidebar"><p>Die meistgelesenen Artikel dieser Woche, sortiert nach Klicks, Kommentaren und Zeit.</p></aside>
+  <div id="comments"><p>Toller Artikel, ich kaufe dort auch immer ein, das Brot ist wirklich gut, danke!</p></div>
+  <footer>© Stadtblatt, Impressum, Datenschutz</footer>
+</body></html>"#;
+
+#[test]
+fn html_import_keeps_only_the_article() {
+    let document = import::document::parse(ARTICLE, import::document::Format::Html);
+    assert_eq!(document.title.as_deref(), Some("Der Bäcker von nebenan"));
+    assert_eq!(document.author.as_deref(), Some("Lena Vogel"));
+    assert_eq!(
+        document.content,
+        "Jeden Morgen um vier Uhr steht Paul in seiner kleinen Backstube, knetet den Teig und heizt den Ofen an.\n\n\
+         Die Nachbarn lieben sein Brot, und am Samstag, wenn die Schlange bis zur Ecke reicht, verkauft er auch Kuchen."
+    );
+}
+
+#[test]
+fn markdown_and_plain_text_keep_their_paragraphs() {
+    let markdown = "---\ntags: [lesen]\n---\n\n# Ein *kurzer* Text\n\nErster Absatz\nüber zwei Zeilen.\n\n- eins\n- zwei\n";
+    let document = import::document::parse(markdown, import::document::Format::Markdown);
+    assert_eq!(document.title.as_deref(), Some("Ein kurzer Text"));
+    assert_eq!(document.content, "Ein kurzer Text\n\nErster Absatz über zwei Zeilen.\n\neins\n\nzwei");
+
+    let wrapped = "Erster Absatz,\r\numgebrochen.\r\n\r\n\r\nZweiter   Absatz.\r\n";
+    let document = import::document::parse(wrapped, import::document::Format::PlainText);
+    assert_eq!(document.content, "Erster Absatz, umgebrochen.\n\nZweiter Absatz.");
+    let lines = import::document::parse("Eine Zeile.\nNoch eine.", import::document::Format::PlainText);
+    assert_eq!(lines.content, "Eine Zeile.\n\nNoch eine.");
+}
+
+#[tokio::test]
+async fn document_import_creates_a_text() {
+    let pool = memory_pool().await;
+    let path = temp_file("baecker.html", ARTICLE.as_bytes());
+    let metadata = TextMetadata { level: Some("A2".to_string()), ..Default::default() };
+
+    let report = import::document::import(&pool, &path, None, &metadata, at(CREATED)).await.unwrap();
+    let text = repo::texts::get(&pool, report.texts[0].id).await.unwrap().unwrap();
+    assert_eq!(text.title, "Der Bäcker von nebenan");
+    assert_eq!(text.metadata.author.as_deref(), Some("Lena Vogel"));
+    assert_eq!(text.metadata.level.as_deref(), Some("A2"));
+    assert_eq!(text.metadata.source.as_deref(), Some("baecker.html"));
+    assert!(common::word(&pool, "Backstube").await.id > 0);
+    assert!(repo::words::list(&pool).await.unwrap().iter().all(|w| w.word != "Startseite"));
+
+    let csv = temp_file("liste.csv", b"a;b");
+    let error = import::document::import(&pool, &csv, None, &metadata, at(CREATED)).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    let empty = temp_file("leer.txt", b" \n ");
+    let error = import::document::import(&pool, &empty, None, &metadata, at(CREATED)).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+}
+
+// A PDF with one line of text per entry (empty entries leave a gap) and a
+// bookmark for each titled page
+fn pdf(pages: &[(Option<&str>, &[&[u8]])]) -> Vec<u8> {
+    let mut doc = lopdf::Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica", "Encoding" => "WinAnsiEncoding",
+    });
+    let resources_id = doc.add_object(dictionary! { "Font" => dictionary! { "F1" => font_id } });
+
+    let mut kids: Vec<Object> = Vec::new();
+    for (bookmark, lines) in pages {
+        let mut operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), 12.into()]),
+            Operation::new("Td", vec![72.into(), 780.into()]),
+        ];
+        for line in lines.iter() {
+            operations.push(Operation::new("Td", vec![0.into(), (-14).into()]));
+            if !line.is_empty() {
+                operations.push(Operation::new("Tj", vec![Object::string_literal(line.to_vec())]));
+            }
+        }
+        operations.push(Operation::new("ET", vec![]));
+        let content = Content { operations }.encode().unwrap();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page_id = doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content_id });
+        if let Some(title) = bookmark {
+            doc.add_bookmark(Bookmark::new(title.to_string(), [0.0; 3], 0, page_id), None);
+        }
+        kids.push(page_id.into());
+    }
+
+    let count = kids.len() as i64;
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages", "Kids" => kids, "Count" => count, "Resources" => resources_id,
+        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+    }));
+    let mut catalog = dictionary! { "Type" => "Catalog", "Pages" => pages_id };
+    if let Some(outline_id) = doc.build_outline() {
+        catalog.set("Outlines", outline_id);
+    }
+    let catalog_id = doc.add_object(catalog);
+    doc.trailer.set("Root", catalog_id);
+    let info_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal("Ein kleines Buch"), "Author" => Object::string_literal("Mia Roth"),
+    });
+    doc.trailer.set("Info", info_id);
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+fn reader() -> Vec<u8> {
+    pdf(&[
+        (None, &[b"Ein kleines Buch", b"", b"Inhalt", b"", b"- i -"]),
+        (Some("Erstes Kapitel"), &[
+            b"Ein kleines Buch", b"", b"Die Ent-", b"scheidung fiel am Nord-", b"S\xfcd-Bahnhof, mitten", b"", b"1",
+        ]),
+        (None, &[b"Ein kleines Buch", b"", b"in der Nacht.", b"", b"Ein neuer Absatz.", b"", b"2"]),
+        (Some("Zweites Kapitel"), &[b"Ein kleines Buch", b"", b"Am Morgen regnete es.", b"", b"Seite 3 von 3"]),
+    ])
+}
+
+#[test]
+fn pdf_pages_lose_headers_page_numbers_and_hyphens() {
+    let pages = import::pdf::clean_pages(&import::pdf::extract_pages(&reader()).unwrap());
+    assert_eq!(
+        import::pdf::pages_text(&pages[1..3]),
+        "Die Entscheidung fiel am Nord-Süd-Bahnhof, mitten in der Nacht.\n\nEin neuer Absatz."
+    );
+    assert_eq!(import::pdf::pages_text(&pages[3..]), "Am Morgen regnete es.");
+
+    let info = import::pdf::inspect(&reader()).unwrap();
+    assert_eq!((info.title.as_deref(), info.author.as_deref()), (Some("Ein kleines Buch"), Some("Mia Roth")));
+    assert_eq!(info.page_count, 4);
+    let chapters: Vec<(Option<&str>, usize, usize)> =
+        info.chapters.iter().map(|c| (c.title.as_deref(), c.start_page, c.end_page)).collect();
+    assert_eq!(chapters, [(Some("Erstes Kapitel"), 2, 3), (Some("Zweites Kapitel"), 4, 4)]);
+
+    let error = import::pdf::inspect(b"%PDF-1.4 nichts weiter").unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+}
+
+#[tokio::test]
+async fn pdf_import_creates_a_text_per_chapter_or_page_range() {
+    let pool = memory_pool().await;
+    let path = temp_file("buch.pdf", &reader());
+
+    let report = import::pdf::import(&pool, &path, None, at(CREATED)).await.unwrap();
+    assert_eq!(report.collection.unwrap().name, "Ein kleines Buch");
+    let titles: Vec<&str> = report.texts.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["Erstes Kapitel", "Zweites Kapitel"]);
+    let text = repo::texts::get(&pool, report.texts[1].id).await.unwrap().unwrap();
+    assert_eq!(text.content, "Am Morgen regnete es.");
+    assert_eq!(text.metadata.author.as_deref(), Some("Mia Roth"));
+
+    let pages = vec![import::pdf::PdfSection { title: None, start_page: 3, end_page: 4 }];
+    let report = import::pdf::import(&pool, &path, Some(pages), at(CREATED)).await.unwrap();
+    assert!(report.collection.is_none());
+    assert_eq!(report.texts[0].title, "Ein kleines Buch (pp. 3-4)");
+
+    let pages = vec![import::pdf::PdfSection { title: None, start_page: 4, end_page: 5 }];
+    let error = import::pdf::import(&pool, &path, Some(pages), at(CREATED)).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+}
+
+#[tokio::test]
+async fn folder_import_skips_duplicates_and_reports_progress() {
+    let pool = memory_pool().await;
+    let dir = temp_file("lektionen/01 Begrüßung.txt", b"Hallo, wie geht es dir?").parent().unwrap().to_path_buf();
+    temp_file("lektionen/02 Zahlen.md", b"# Die Zahlen\n\nEins, zwei, drei.");
+    temp_file("lektionen/03 Kopie.txt", b"Hallo, wie geht es dir?\n");
+    temp_file("lektionen/04 Kaputt.pdf", b"%PDF-1.4 kaputt");
+    temp_file("lektionen/05 Schon da.txt", "Der Hund schläft.".as_bytes());
+    temp_file("lektionen/audio/06 Folge.srt", SRT.as_bytes());
+    temp_file("lektionen/.versteckt.txt", b"Geheim.");
+    temp_file("lektionen/notizen.docx", b"Word");
+    repo::texts::create(&pool, "Hund", "Der Hund schläft.", at(CREATED)).await.unwrap();
+
+    let mut progress = Vec::new();
+    let mut on_progress = |p: &import::folder::FolderProgress| progress.push((p.current, p.total, p.status));
+    let report = import::folder::import(&pool, &dir, None, at(CREATED), &mut on_progress).await.unwrap();
+
+    use import::folder::FileStatus::*;
+    assert_eq!(
+        progress,
+        [(1, 6, Imported), (2, 6, Imported), (3, 6, Duplicate), (4, 6, Failed), (5, 6, Duplicate), (6, 6, Imported)]
+    );
+    let collection = report.collection.unwrap();
+    assert_eq!(collection.name, "lektionen");
+    assert_eq!(collection.text_count, 3);
+    let titles: Vec<&str> = report.texts.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["01 Begrüßung", "Die Zahlen", "06 Folge"]);
+    let skipped: Vec<&str> = report.skipped.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(skipped, ["03 Kopie.txt", "04 Kaputt.pdf", "05 Schon da.txt"]);
+    assert_eq!(repo::timestamps::list(&pool, report.texts[2].id).await.unwrap().len(), 4);
+
+    // Importing again only finds duplicates
+    let again = import::folder::import(&pool, &dir, Some(collection.id), at(CREATED), &mut |_| {}).await.unwrap();
+    assert!(again.texts.is_empty());
+    assert_eq!(again.collection.unwrap().text_count, 3);
+}
+
+#[tokio::test]
+async fn folder_import_without_new_texts_creates_no_collection() {
+    let pool = memory_pool().await;
+    let dir = temp_file("nichts-neues/01 Schon da.txt", "Der Hund schläft.".as_bytes()).parent().unwrap().to_path_buf();
+    temp_file("nichts-neues/02 Kaputt.pdf", b"%PDF-1.4 kaputt");
+    repo::texts::create(&pool, "Hund", "Der Hund schläft.", at(CREATED)).await.unwrap();
+
+    let report = import::folder::import(&pool, &dir, None, at(CREATED), &mut |_| {}).await.unwrap();
+    assert!(report.collection.is_none());
+    assert_eq!(report.skipped.len(), 2);
+    assert!(repo::collections::list(&pool).await.unwrap().is_empty());
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{at, memory_pool, CREATED};
+use word_echo_lib::pagination::{self, paginate};
+use word_echo_lib::repo;
+
+// A sentence of `words` distinct words, prefixed with `prefix`
+fn paragraph(prefix: &str, words: usize) -> String {
+    let words: Vec<String> = (0..words)
+        .map(|w| format!("{}{}{}", prefix, (b'a' + (w / 26) as u8) as char, (b'a' + (w % 26) as u8) as char))
+        .collect();
+    words.join(" ") + "."
+}
+
+#[test]
+fn pages_end_between_paragraphs() {
+    let content = format!("{}\n\n{}\n\n{}", paragraph("a", 30), paragraph("b", 30), paragraph("c", 30));
+
+    let pages = paginate(&content, 60);
+
+    assert_eq!(pages.len(), 2);
+    assert_eq!((pages[0].token_offset, pages[0].token_count), (0, 60));
+    assert_eq!((pages[1].token_offset, pages[1].token_count), (60, 30));
+    assert!(content[pages[0].start..pages[0].end].ends_with('.'));
+    assert!(!content[pages[1].start..pages[1].end].starts_with('\n'));
+}
+
+#[test]
+fn long_paragraphs_are_split_between_sentences() {
+    let content = "Eins zwei drei. Vier fünf sechs! Sieben acht neun? Zehn elf zwölf.";
+
+    let pages = paginate(content, 20);
+    assert_eq!(pages.len(), 1);
+
+    // Target below a paragraph: whole sentences only
+    let pages: Vec<&str> = paginate(content, 6).iter().map(|p| &content[p.start..p.end]).collect();
+    assert_eq!(pages, ["Eins zwei drei. Vier fünf sechs!", "Sieben acht neun? Zehn elf zwölf."]);
+}
+
+#[test]
+fn empty_texts_have_one_empty_page() {
+    let pages = paginate("  \n\n ", 50);
+    assert_eq!(pages.len(), 1);
+    assert_eq!(pages[0].token_count, 0);
+}
+
+#[tokio::test]
+async fn a_page_comes_with_its_words_and_counts() {
+    let pool = memory_pool().await;
+    let content = format!("{}\nHund Katze Hund.\n{}", paragraph("a", 20), paragraph("b", 5));
+    let text = repo::texts::create(&pool, "Tiere", &content, at(CREATED)).await.unwrap();
+    repo::words::mark_known(&pool, common::word(&pool, "Katze").await.id).await.unwrap();
+
+    let summaries = pagination::pages(&pool, text.id, 22).await.unwrap();
+    assert_eq!(summaries.len(), 2);
+    assert_eq!(summaries[1].token_offset, 20);
+
+    let page = pagination::page(&pool, text.id, 1, 22).await.unwrap();
+    assert_eq!(page.page_count, 2);
+    assert!(page.content.starts_with("Hund Katze Hund."));
+    let words: Vec<&str> = page.words.iter().map(|w| w.word.as_str()).collect();
+    assert_eq!(&words[..2], ["Hund", "Katze"]);
+    assert_eq!(page.summary.known_count, 1);
+    assert_eq!(page.summary.new_count as usize, page.words.len() - 1);
+
+    let error = pagination::page(&pool, text.id, 2, 22).await.unwrap_err();
+    assert_eq!(error.code(), "not_found");
+}
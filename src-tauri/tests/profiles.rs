@@ -0,0 +1,125 @@
+mod common;
+
+use common::{at, temp_file, CREATED};
+use std::path::PathBuf;
+use word_echo_lib::profiles::{self, DEFAULT_PROFILE_ID, REGISTRY_FILE};
+
+// An empty library folder, `name` must be unique per test
+fn library_dir(name: &str) -> PathBuf {
+    let marker = temp_file(&format!("{}/.keep", name), b"");
+    marker.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn a_library_without_registry_has_the_default_profile() {
+    let dir = library_dir("ohne-registry");
+
+    let list = profiles::list(&dir, at(CREATED));
+
+    assert_eq!(list.active, DEFAULT_PROFILE_ID);
+    assert_eq!(list.profiles.len(), 1);
+    assert_eq!(list.profiles[0].created_at, "2025-02-01T08:00:00.000Z");
+    assert_eq!(profiles::active_id(&dir), DEFAULT_PROFILE_ID);
+    assert_eq!(profiles::database_path(&dir, DEFAULT_PROFILE_ID), dir.join("db.sqlite"));
+    assert_eq!(profiles::database_files(&dir), [PathBuf::from("db.sqlite")]);
+    assert!(!dir.join(REGISTRY_FILE).exists());
+
+    // An unreadable registry counts as missing
+    std::fs::write(dir.join(REGISTRY_FILE), "{ kaputt").unwrap();
+    assert_eq!(profiles::active(&dir, at(CREATED)).id, DEFAULT_PROFILE_ID);
+}
+
+#[test]
+fn an_unreadable_registry_is_not_overwritten() {
+    let dir = library_dir("kaputt");
+    profiles::create(&dir, "Anna", at(CREATED)).unwrap();
+    let registry = std::fs::read_to_string(dir.join(REGISTRY_FILE)).unwrap();
+    let damaged = &registry[..registry.len() / 2];
+    std::fs::write(dir.join(REGISTRY_FILE), damaged).unwrap();
+
+    assert_eq!(profiles::create(&dir, "Ben", at(CREATED)).unwrap_err().code(), "invalid_data");
+    assert_eq!(profiles::switch(&dir, DEFAULT_PROFILE_ID, at(CREATED)).unwrap_err().code(), "invalid_data");
+    assert_eq!(std::fs::read_to_string(dir.join(REGISTRY_FILE)).unwrap(), damaged);
+    // Reading still works, with the default profile
+    assert_eq!(profiles::active_id(&dir), DEFAULT_PROFILE_ID);
+}
+
+#[test]
+fn profiles_are_created_with_unique_names() {
+    let dir = library_dir("anlegen");
+
+    let anna = profiles::create(&dir, " Anna ", at(CREATED)).unwrap();
+    let ben = profiles::create(&dir, "Ben", at(CREATED)).unwrap();
+
+    assert_eq!(anna.name, "Anna");
+    assert_eq!(anna.created_at, "2025-02-01T08:00:00.000Z");
+    // Same moment, still different ids
+    assert_ne!(anna.id, ben.id);
+    assert_eq!(
+        profiles::database_path(&dir, &anna.id),
+        dir.join("profiles").join(&anna.id).join("db.sqlite")
+    );
+    assert_eq!(profiles::database_files(&dir).len(), 3);
+    assert_eq!(profiles::find(&dir, "ANNA", at(CREATED)).unwrap().id, anna.id);
+
+    assert_eq!(profiles::create(&dir, "anna", at(CREATED)).unwrap_err().code(), "invalid_input");
+    assert_eq!(profiles::create(&dir, "default", at(CREATED)).unwrap_err().code(), "invalid_input");
+    assert_eq!(profiles::create(&dir, "  ", at(CREATED)).unwrap_err().code(), "invalid_input");
+    assert_eq!(profiles::find(&dir, "Carla", at(CREATED)).unwrap_err().code(), "not_found");
+}
+
+#[test]
+fn profiles_are_renamed_and_switched() {
+    let dir = library_dir("umbenennen");
+    let anna = profiles::create(&dir, "Anna", at(CREATED)).unwrap();
+    profiles::create(&dir, "Ben", at(CREATED)).unwrap();
+
+    assert_eq!(profiles::rename(&dir, &anna.id, "ANNA", at(CREATED)).unwrap().name, "ANNA");
+    let error = profiles::rename(&dir, &anna.id, "ben", at(CREATED)).unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    let error = profiles::rename(&dir, "p0", "Carla", at(CREATED)).unwrap_err();
+    assert_eq!(error.code(), "not_found");
+
+    profiles::switch(&dir, &anna.id, at(CREATED)).unwrap();
+    assert_eq!(profiles::active_id(&dir), anna.id);
+    assert_eq!(profiles::active(&dir, at(CREATED)).name, "ANNA");
+    assert_eq!(profiles::switch(&dir, "p0", at(CREATED)).unwrap_err().code(), "not_found");
+}
+
+#[test]
+fn delete_removes_the_profile_and_its_data() {
+    let dir = library_dir("loeschen");
+    let anna = profiles::create(&dir, "Anna", at(CREATED)).unwrap();
+    let ben = profiles::create(&dir, "Ben", at(CREATED)).unwrap();
+    let ben_db = temp_file(&format!("loeschen/profiles/{}/db.sqlite", ben.id), b"");
+
+    profiles::switch(&dir, &anna.id, at(CREATED)).unwrap();
+    let error = profiles::delete(&dir, &anna.id, at(CREATED)).unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+
+    profiles::switch(&dir, &ben.id, at(CREATED)).unwrap();
+    let error = profiles::delete(&dir, DEFAULT_PROFILE_ID, at(CREATED)).unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+
+    profiles::switch(&dir, DEFAULT_PROFILE_ID, at(CREATED)).unwrap();
+    profiles::delete(&dir, &ben.id, at(CREATED)).unwrap();
+    assert!(!ben_db.exists());
+    let names: Vec<String> = profiles::list(&dir, at(CREATED)).profiles.into_iter().map(|p| p.name).collect();
+    assert_eq!(names, ["Default", "Anna"]);
+    assert_eq!(profiles::delete(&dir, &ben.id, at(CREATED)).unwrap_err().code(), "not_found");
+}
+
+#[test]
+fn a_registry_pointing_at_a_missing_profile_falls_back_to_the_default() {
+    let dir = library_dir("verwaist");
+    let anna = profiles::create(&dir, "Anna", at(CREATED)).unwrap();
+    profiles::switch(&dir, &anna.id, at(CREATED)).unwrap();
+
+    // Edited by hand, the active profile was removed from the list
+    let registry = std::fs::read_to_string(dir.join(REGISTRY_FILE)).unwrap();
+    let edited = registry.replace(&format!("\"id\": \"{}\"", anna.id), "\"id\": \"p0\"");
+    std::fs::write(dir.join(REGISTRY_FILE), edited).unwrap();
+
+    assert_eq!(profiles::active_id(&dir), DEFAULT_PROFILE_ID);
+    assert_eq!(profiles::active(&dir, at(CREATED)).id, DEFAULT_PROFILE_ID);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::{at, memory_pool, CREATED};
+use word_echo_lib::{repo, transfer};
+
+#[tokio::test]
+async fn unread_texts_start_at_the_beginning() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Neu", "eins zwei drei", at(CREATED)).await.unwrap();
+
+    let progress = repo::progress::get(&pool, text.id).await.unwrap();
+
+    assert_eq!((progress.token_offset, progress.token_count), (0, 3));
+    assert!(!progress.completed);
+    assert_eq!(progress.updated_at, None);
+    assert_eq!(repo::progress::get(&pool, 999).await.unwrap_err().code(), "not_found");
+}
+
+#[tokio::test]
+async fn positions_are_saved_and_clamped() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Lang", "a b c d e", at(CREATED)).await.unwrap();
+
+    let progress = repo::progress::save_position(&pool, text.id, 3, at("2024-05-01T08:00:00Z")).await.unwrap();
+    assert_eq!(progress.token_offset, 3);
+
+    let progress = repo::progress::save_position(&pool, text.id, 50, at("2024-05-01T08:05:00Z")).await.unwrap();
+    assert_eq!(progress.token_offset, 4);
+
+    let error = repo::progress::save_position(&pool, text.id, -1, at("2024-05-01T08:06:00Z")).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+}
+
+#[tokio::test]
+async fn continue_reading_lists_unfinished_texts_most_recent_first() {
+    let pool = memory_pool().await;
+    let first = repo::texts::create(&pool, "Erster", "eins zwei", at(CREATED)).await.unwrap();
+    let second = repo::texts::create(&pool, "Zweiter", "drei vier", at(CREATED)).await.unwrap();
+    let done = repo::texts::create(&pool, "Fertig", "fünf", at(CREATED)).await.unwrap();
+    repo::texts::create(&pool, "Ungelesen", "sechs", at(CREATED)).await.unwrap();
+
+    repo::progress::save_position(&pool, first.id, 1, at("2024-05-01T08:00:00Z")).await.unwrap();
+    repo::progress::save_position(&pool, second.id, 1, at("2024-05-02T08:00:00Z")).await.unwrap();
+    repo::progress::save_position(&pool, done.id, 0, at("2024-05-03T08:00:00Z")).await.unwrap();
+    let progress = repo::progress::set_completed(&pool, done.id, true, at("2024-05-03T09:00:00Z")).await.unwrap();
+    assert!(progress.completed);
+    assert!(progress.completed_at.is_some());
+
+    let texts = repo::progress::continue_reading(&pool, 10).await.unwrap();
+    let titles: Vec<&str> = texts.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["Zweiter", "Erster"]);
+    assert_eq!((texts[0].token_offset, texts[0].token_count), (1, 2));
+}
+
+#[tokio::test]
+async fn bookmarks_are_kept_in_reading_order_and_travel_with_sync_keys() {
+    let source = memory_pool().await;
+    let text = repo::texts::create(&source, "Roman", "a b c d e f", at(CREATED)).await.unwrap();
+    repo::progress::add_bookmark(&source, text.id, "Kapitel 2", 4, at("2024-05-01T08:00:00Z")).await.unwrap();
+    let first = repo::progress::add_bookmark(&source, text.id, " Anfang ", 0, at("2024-05-01T08:01:00Z")).await.unwrap();
+    assert_eq!(first.name, "Anfang");
+    let error = repo::progress::add_bookmark(&source, text.id, "", 1, at("2024-05-01T08:02:00Z")).await.unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    repo::progress::save_position(&source, text.id, 2, at("2024-05-01T09:00:00Z")).await.unwrap();
+
+    let target = memory_pool().await;
+    let sync_key = transfer::build_sync_key(&source).await.unwrap();
+    transfer::apply_sync_key(&target, &sync_key).await.unwrap();
+
+    let names: Vec<String> = repo::progress::bookmarks(&target, text.id).await.unwrap().into_iter().map(|b| b.name).collect();
+    assert_eq!(names, ["Anfang", "Kapitel 2"]);
+    assert_eq!(repo::progress::get(&target, text.id).await.unwrap().token_offset, 2);
+
+    repo::progress::delete_bookmark(&target, first.id).await.unwrap();
+    assert_eq!(repo::progress::bookmarks(&target, text.id).await.unwrap().len(), 1);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use chrono::Duration;
+use common::{at, memory_pool, word, CREATED};
+use word_echo_lib::{repo, review};
+
+const NOW: &str = "2025-03-01T09:00:00+00:00";
+
+#[tokio::test]
+async fn saving_a_meaning_makes_the_word_due() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Text", "Apfel", at(CREATED)).await.unwrap();
+    let apfel = word(&pool, "Apfel").await;
+
+    assert_eq!(review::next_due(&pool, at(NOW)).await.unwrap_err().code(), "not_found");
+
+    repo::words::update_meaning(&pool, apfel.id, "apple", at(NOW)).await.unwrap();
+
+    let due = review::next_due(&pool, at(NOW)).await.unwrap();
+    assert_eq!(due.id, apfel.id);
+    assert_eq!(due.status.as_deref(), Some("seen"));
+    assert_eq!(due.meaning.as_deref(), Some("apple"));
+}
+
+#[tokio::test]
+async fn review_schedules_the_next_review() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Text", "Birne", at(CREATED)).await.unwrap();
+    let birne = word(&pool, "Birne").await;
+    repo::words::update_meaning(&pool, birne.id, "pear", at(NOW)).await.unwrap();
+
+    let next_review = review::review(&pool, birne.id, "Good", at(NOW)).await.unwrap();
+
+    assert!(next_review >= at(NOW) + Duration::days(1));
+    let reviewed = word(&pool, "Birne").await;
+    assert_eq!(reviewed.last_review_date, Some(at(NOW).to_rfc3339()));
+    assert_eq!(reviewed.next_review_date, Some(next_review.to_rfc3339()));
+    assert!(review::next_due(&pool, at(NOW)).await.is_err());
+    assert_eq!(review::next_due(&pool, next_review).await.unwrap().id, birne.id);
+}
+
+#[tokio::test]
+async fn invalid_rating_is_rejected() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Text", "Kirsche", at(CREATED)).await.unwrap();
+    let kirsche = word(&pool, "Kirsche").await;
+
+    let error = review::review(&pool, kirsche.id, "Perfect", at(NOW)).await.unwrap_err();
+
+    assert_eq!(error.code(), "invalid_input");
+    assert!(word(&pool, "Kirsche").await.last_review_date.is_none());
+}
+
+#[tokio::test]
+async fn retention_decays_after_a_review() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Text", "Traube", at(CREATED)).await.unwrap();
+    let traube = word(&pool, "Traube").await;
+
+    assert_eq!(review::estimate_retention(&pool, traube.id, at(NOW)).await.unwrap(), 1.0);
+
+    review::review(&pool, traube.id, "Good", at(NOW)).await.unwrap();
+    let soon = review::estimate_retention(&pool, traube.id, at(NOW) + Duration::days(1)).await.unwrap();
+    let later = review::estimate_retention(&pool, traube.id, at(NOW) + Duration::days(30)).await.unwrap();
+
+    assert!(soon < 1.0);
+    assert!(later < soon);
+}
+
+#[tokio::test]
+async fn stats_count_words_by_state() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Text", "eins zwei drei", at(CREATED)).await.unwrap();
+    repo::words::update_meaning(&pool, word(&pool, "eins").await.id, "one", at(NOW)).await.unwrap();
+    repo::words::mark_known(&pool, word(&pool, "zwei").await.id).await.unwrap();
+
+    let stats = repo::words::stats(&pool, at(NOW)).await.unwrap();
+
+    assert_eq!((stats.total, stats.new, stats.seen, stats.known), (3, 1, 1, 1));
+    assert_eq!((stats.due_now, stats.due_today, stats.reviewed_today), (1, 1, 0));
+}
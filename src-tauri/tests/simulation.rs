@@ -0,0 +1,84 @@
+mod common;
+
+use common::{at, count, memory_pool};
+use word_echo_lib::clock::{Clock, ManualClock};
+use word_echo_lib::repo;
+use word_echo_lib::simulation::{simulate, SimulationOptions};
+
+const START: &str = "2025-03-01T09:00:00+00:00";
+
+async fn library() -> sqlx::SqlitePool {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Zahlen", "eins zwei drei vier fünf sechs sieben acht neun zehn", at(START))
+        .await
+        .unwrap();
+    pool
+}
+
+fn options(days: u32, new_words_per_day: u32) -> SimulationOptions {
+    SimulationOptions {
+        days,
+        new_words_per_day,
+        seed: Some(7),
+    }
+}
+
+#[tokio::test]
+async fn new_words_are_introduced_and_reviewed() {
+    let pool = library().await;
+
+    let report = simulate(&pool, at(START), &options(3, 4)).await.unwrap();
+
+    let new_words: Vec<i64> = report.days.iter().map(|d| d.new_words).collect();
+    assert_eq!(new_words, [4, 4, 2]);
+    assert_eq!(report.days[0].reviews, 4);
+    assert_eq!(report.days[0].date, "2025-03-01");
+    assert_eq!(report.days[2].date, "2025-03-03");
+    assert!(report.total_reviews >= 10);
+    assert!(report.final_average_retention > 0.0 && report.final_average_retention <= 1.0);
+}
+
+#[tokio::test]
+async fn same_seed_gives_the_same_projection() {
+    let pool = library().await;
+
+    let first = simulate(&pool, at(START), &options(30, 2)).await.unwrap();
+    let second = simulate(&pool, at(START), &options(30, 2)).await.unwrap();
+
+    let reviews = |r: &word_echo_lib::simulation::SimulationReport| -> Vec<(i64, i64)> {
+        r.days.iter().map(|d| (d.reviews, d.lapses)).collect()
+    };
+    assert_eq!(reviews(&first), reviews(&second));
+}
+
+#[tokio::test]
+async fn the_library_is_left_alone() {
+    let pool = library().await;
+
+    simulate(&pool, at(START), &options(10, 5)).await.unwrap();
+
+    let stats = repo::words::stats(&pool, at(START)).await.unwrap();
+    assert_eq!((stats.new, stats.seen), (10, 0));
+    assert_eq!(count(&pool, "words").await, 10);
+}
+
+#[tokio::test]
+async fn days_are_limited() {
+    let pool = library().await;
+
+    let error = simulate(&pool, at(START), &options(0, 0)).await.unwrap_err();
+
+    assert_eq!(error.code(), "invalid_input");
+}
+
+#[tokio::test]
+async fn manual_clock_only_moves_when_told() {
+    let clock = ManualClock::new(at(START));
+    assert_eq!(clock.now(), at(START));
+
+    clock.advance(chrono::Duration::days(2));
+    assert_eq!(clock.now(), at("2025-03-03T09:00:00+00:00"));
+
+    clock.set(at("2030-01-01T00:00:00+00:00"));
+    assert_eq!(clock.now(), at("2030-01-01T00:00:00+00:00"));
+}
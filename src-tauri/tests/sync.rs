@@ -0,0 +1,171 @@
+mod common;
+
+use common::{at, count, memory_pool, temp_file, word, CREATED};
+use tokio::net::TcpListener;
+use word_echo_lib::repo::dictionary_providers::ProviderInput;
+use word_echo_lib::sync::protocol::{Change, ChangeOp, SyncRequest, SyncResponse, MAX_BATCH_SIZE, PROTOCOL_VERSION};
+use word_echo_lib::sync::{client, server};
+use word_echo_lib::{repo, transfer};
+
+#[tokio::test]
+async fn sync_key_copies_the_library() {
+    let source = memory_pool().await;
+    repo::texts::create(&source, "Eins", "Katze und Hund", at(CREATED)).await.unwrap();
+    repo::texts::create(&source, "Zwei", "Maus und Katze", at(CREATED)).await.unwrap();
+    repo::words::update_meaning(&source, word(&source, "Katze").await.id, "cat", chrono::Utc::now())
+        .await
+        .unwrap();
+    repo::preferences::save_country_code(&source, "de").await.unwrap();
+    let provider = ProviderInput {
+        name: "Duden".to_string(),
+        url_template: "https://www.duden.de/suchen/dudenonline/{word}".to_string(),
+        ..Default::default()
+    };
+    repo::dictionary_providers::create(&source, &provider).await.unwrap();
+
+    let target = memory_pool().await;
+    repo::texts::create(&target, "Old", "wird ersetzt", at(CREATED)).await.unwrap();
+
+    let sync_key = transfer::build_sync_key(&source).await.unwrap();
+    transfer::apply_sync_key(&target, &sync_key).await.unwrap();
+
+    let titles: Vec<String> = repo::texts::list(&target).await.unwrap().into_iter().map(|t| t.title).collect();
+    assert_eq!(titles, ["Eins", "Zwei"]);
+    // Same uids, or both devices would push the texts as new ones
+    let uids = "SELECT uid FROM texts ORDER BY id";
+    let source_uids: Vec<String> = sqlx::query_scalar(uids).fetch_all(&source).await.unwrap();
+    let target_uids: Vec<String> = sqlx::query_scalar(uids).fetch_all(&target).await.unwrap();
+    assert_eq!(target_uids, source_uids);
+    assert_eq!(count(&target, "words").await, 4);
+    assert_eq!(count(&target, "text_words").await, 6);
+    assert_eq!(word(&target, "Katze").await.meaning.as_deref(), Some("cat"));
+    assert_eq!(repo::preferences::country_code(&target).await.unwrap().as_deref(), Some("de"));
+    assert_eq!(
+        repo::dictionary_providers::list(&target).await.unwrap(),
+        repo::dictionary_providers::list(&source).await.unwrap()
+    );
+
+    // New rows must not collide with the imported ids
+    repo::texts::create(&target, "Drei", "Vogel", at(CREATED)).await.unwrap();
+    assert_eq!(count(&target, "texts").await, 3);
+}
+
+#[tokio::test]
+async fn invalid_sync_key_is_rejected() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Keep", "bleibt", at(CREATED)).await.unwrap();
+
+    let error = transfer::apply_sync_key(&pool, "not a sync key").await.unwrap_err();
+
+    assert_eq!(error.code(), "invalid_data");
+    assert_eq!(count(&pool, "texts").await, 1);
+}
+
+// A server on a fresh database file, in memory every connection of its
+// pool would see a database of its own. Returns the address to sync with.
+async fn start_server(name: &str) -> String {
+    let path = temp_file(name, b"");
+    let pool = server::open_database(path.to_str().unwrap()).await.unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(server::serve(listener, pool, "geheim".to_string()));
+    address
+}
+
+#[tokio::test]
+async fn changes_travel_through_the_sync_server() {
+    let address = start_server("changes.sqlite").await;
+
+    let laptop = memory_pool().await;
+    let phone = memory_pool().await;
+    repo::texts::create(&laptop, "Notiz", "hallo welt", at(CREATED)).await.unwrap();
+    client::configure(&laptop, &address, "geheim").await.unwrap();
+    client::configure(&phone, &address, "geheim").await.unwrap();
+
+    let pushed = client::sync_now(&laptop).await.unwrap();
+    assert!(pushed.pushed > 0);
+    client::sync_now(&phone).await.unwrap();
+
+    assert_eq!(repo::texts::list(&phone).await.unwrap()[0].title, "Notiz");
+    assert_eq!(count(&phone, "text_words").await, 2);
+
+    // And back: a meaning added on the phone reaches the laptop
+    repo::words::update_meaning(&phone, word(&phone, "hallo").await.id, "hello", chrono::Utc::now())
+        .await
+        .unwrap();
+    client::sync_now(&phone).await.unwrap();
+    client::sync_now(&laptop).await.unwrap();
+
+    assert_eq!(word(&laptop, "hallo").await.meaning.as_deref(), Some("hello"));
+
+    // Dictionary providers too, by uid
+    let google = repo::dictionary_providers::list(&phone).await.unwrap().remove(2);
+    repo::dictionary_providers::delete(&phone, google.id).await.unwrap();
+    client::sync_now(&phone).await.unwrap();
+    client::sync_now(&laptop).await.unwrap();
+    let names: Vec<String> =
+        repo::dictionary_providers::list(&laptop).await.unwrap().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, ["dict.cc", "DeepL", "TheFreeDictionary"]);
+    assert_eq!(client::status(&laptop).await.unwrap().pending_changes, 0);
+}
+
+#[tokio::test]
+async fn large_texts_are_split_over_several_messages() {
+    let address = start_server("large.sqlite").await;
+
+    let laptop = memory_pool().await;
+    let phone = memory_pool().await;
+    // Together well over one message, digits leave nothing to link
+    let content = "0".repeat(3 * 1024 * 1024);
+    for title in ["Eins", "Zwei", "Drei", "Vier", "Fünf", "Sechs"] {
+        repo::texts::create(&laptop, title, &content, at(CREATED)).await.unwrap();
+    }
+    client::configure(&laptop, &address, "geheim").await.unwrap();
+    client::configure(&phone, &address, "geheim").await.unwrap();
+
+    client::sync_now(&laptop).await.unwrap();
+    client::sync_now(&phone).await.unwrap();
+
+    assert_eq!(count(&phone, "texts").await, 6);
+    assert_eq!(client::status(&laptop).await.unwrap().pending_changes, 0);
+}
+
+#[tokio::test]
+async fn sync_server_rejects_a_wrong_secret() {
+    let address = start_server("wrong-secret.sqlite").await;
+
+    let laptop = memory_pool().await;
+    repo::texts::create(&laptop, "Notiz", "hallo welt", at(CREATED)).await.unwrap();
+    client::configure(&laptop, &address, "falsch").await.unwrap();
+
+    let error = client::sync_now(&laptop).await.unwrap_err();
+
+    assert_eq!(error.code(), "sync");
+    assert!(client::status(&laptop).await.unwrap().pending_changes > 0);
+}
+
+#[tokio::test]
+async fn sync_server_rejects_an_oversized_push() {
+    let pool = server::open_database(temp_file("oversized.sqlite", b"").to_str().unwrap()).await.unwrap();
+    let change = Change {
+        entity: "words".to_string(),
+        key: "hallo".to_string(),
+        op: ChangeOp::Delete,
+        payload: None,
+        changed_at: CREATED.to_string(),
+    };
+    let push = |changes: usize| SyncRequest::Push {
+        version: PROTOCOL_VERSION,
+        secret: "geheim".to_string(),
+        device_id: "laptop".to_string(),
+        changes: vec![change.clone(); changes],
+    };
+
+    let error = server::handle_request(&pool, "geheim", push(MAX_BATCH_SIZE as usize + 1)).await.unwrap_err();
+    assert!(error.contains("Too many changes"));
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM changes").fetch_one(&pool).await.unwrap();
+    assert_eq!(stored, 0);
+
+    let response = server::handle_request(&pool, "geheim", push(MAX_BATCH_SIZE as usize)).await.unwrap();
+    assert!(matches!(response, SyncResponse::Pushed { cursor } if cursor == MAX_BATCH_SIZE));
+}
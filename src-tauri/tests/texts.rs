@@ -0,0 +1,166 @@
+mod common;
+
+use common::{at, count, memory_pool, word, CREATED};
+use word_echo_lib::models::TextMetadata;
+use word_echo_lib::repo;
+use word_echo_lib::repo::texts::{TextFilter, TextSort};
+
+const EDITED: &str = "2025-03-01T09:00:00+00:00";
+
+#[tokio::test]
+async fn create_links_every_word_once() {
+    let pool = memory_pool().await;
+
+    let text = repo::texts::create(&pool, "Greeting", "Hallo Welt. Hallo!", at(CREATED)).await.unwrap();
+
+    assert_eq!(text.title, "Greeting");
+    assert_eq!(count(&pool, "words").await, 2);
+    assert_eq!(count(&pool, "text_words").await, 2);
+    assert_eq!(word(&pool, "Hallo").await.status.as_deref(), Some("new"));
+    assert_eq!(repo::texts::word_counts(&pool, text.id).await.unwrap(), (2, 0, 0));
+}
+
+#[tokio::test]
+async fn words_are_shared_between_texts() {
+    let pool = memory_pool().await;
+
+    let first = repo::texts::create(&pool, "One", "der Hund", at(CREATED)).await.unwrap();
+    let second = repo::texts::create(&pool, "Two", "der Kater", at(CREATED)).await.unwrap();
+
+    assert_eq!(count(&pool, "words").await, 3);
+    assert_eq!(repo::texts::word_counts(&pool, first.id).await.unwrap(), (2, 0, 0));
+    assert_eq!(repo::texts::word_counts(&pool, second.id).await.unwrap(), (2, 0, 0));
+}
+
+#[tokio::test]
+async fn update_links_new_words() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Draft", "guten Morgen", at(CREATED)).await.unwrap();
+
+    repo::texts::update(&pool, text.id, "Final", "guten Abend", at(EDITED)).await.unwrap();
+
+    let updated = repo::texts::get(&pool, text.id).await.unwrap().unwrap();
+    assert_eq!(updated.title, "Final");
+    assert_eq!(updated.content, "guten Abend");
+    word(&pool, "Abend").await;
+    // Words removed from the content stay linked
+    assert_eq!(repo::texts::word_counts(&pool, text.id).await.unwrap(), (3, 0, 0));
+}
+
+#[tokio::test]
+async fn words_come_back_in_reading_order() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Order", "Wo ist der Hund? Der Hund schläft—im Vor-Garten.", at(CREATED)).await.unwrap();
+
+    let (fetched, words) = repo::texts::get_with_words(&pool, text.id).await.unwrap();
+
+    assert_eq!(fetched.id, text.id);
+    let words: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+    assert_eq!(words, ["Wo", "ist", "der", "Hund", "Der", "Hund", "schläft", "im", "Vor", "Garten"]);
+    // One word per reading position
+    assert_eq!(words.len() as i64, repo::texts::token_count(&fetched.content));
+}
+
+#[tokio::test]
+async fn delete_keeps_the_vocabulary() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Gone", "bald weg", at(CREATED)).await.unwrap();
+
+    repo::texts::delete(&pool, text.id).await.unwrap();
+
+    assert!(repo::texts::get(&pool, text.id).await.unwrap().is_none());
+    assert_eq!(count(&pool, "text_words").await, 0);
+    assert_eq!(count(&pool, "words").await, 2);
+}
+
+#[tokio::test]
+async fn missing_text_is_not_found() {
+    let pool = memory_pool().await;
+
+    let error = repo::texts::get_with_words(&pool, 42).await.unwrap_err();
+
+    assert_eq!(error.code(), "not_found");
+}
+
+#[tokio::test]
+async fn metadata_is_cleaned_and_kept_on_edit() {
+    let pool = memory_pool().await;
+    let metadata = TextMetadata {
+        source: Some(" https://example.org/märchen ".into()),
+        author: Some("Brüder Grimm".into()),
+        level: Some("b1".into()),
+        notes: Some("".into()),
+    };
+
+    let text = repo::texts::create_with_metadata(&pool, "Rotkäppchen", "Es war einmal", &metadata, at(CREATED)).await.unwrap();
+    assert_eq!(text.metadata.source.as_deref(), Some("https://example.org/märchen"));
+    assert_eq!(text.metadata.level.as_deref(), Some("B1"));
+    assert_eq!(text.metadata.notes, None);
+    assert_eq!(text.created_at.as_deref(), Some("2025-02-01T08:00:00.000Z"));
+    assert_eq!(text.created_at, text.updated_at);
+
+    // Editing the content does not touch the metadata
+    repo::texts::update(&pool, text.id, "Rotkäppchen", "Es war einmal ein Mädchen", at(EDITED)).await.unwrap();
+    let edited = repo::texts::get(&pool, text.id).await.unwrap().unwrap();
+    assert_eq!(edited.metadata, text.metadata);
+    assert_eq!(edited.updated_at.as_deref(), Some("2025-03-01T09:00:00.000Z"));
+
+    let level = TextMetadata { level: Some("D1".into()), ..Default::default() };
+    let error = repo::texts::update_metadata(&pool, text.id, &level, at(EDITED))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+
+    // An invalid level leaves the title and content as they were too
+    let error = repo::texts::update_with_metadata(&pool, text.id, "Neu", "Ganz neu", Some(&level), at(EDITED))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), "invalid_input");
+    let unchanged = repo::texts::get(&pool, text.id).await.unwrap().unwrap();
+    assert_eq!(unchanged.content, "Es war einmal ein Mädchen");
+    assert_eq!(count(&pool, "words").await, 5);
+}
+
+#[tokio::test]
+async fn updating_a_missing_text_links_nothing() {
+    let pool = memory_pool().await;
+
+    let error = repo::texts::update(&pool, 42, "Weg", "verloren", at(EDITED)).await.unwrap_err();
+
+    assert_eq!(error.code(), "not_found");
+    assert_eq!(count(&pool, "words").await, 0);
+}
+
+#[tokio::test]
+async fn list_filters_by_level() {
+    let pool = memory_pool().await;
+    let easy = TextMetadata { level: Some("A2".into()), author: Some("Lehrbuch".into()), ..Default::default() };
+    repo::texts::create_with_metadata(&pool, "Einfach", "ich bin", &easy, at(CREATED)).await.unwrap();
+    repo::texts::create(&pool, "Ohne", "nichts", at(CREATED)).await.unwrap();
+
+    let filter = TextFilter { level: Some("a2".into()), ..Default::default() };
+    let texts = repo::texts::list_summaries(&pool, &filter).await.unwrap();
+
+    assert_eq!(texts.len(), 1);
+    assert_eq!(texts[0].metadata.author.as_deref(), Some("Lehrbuch"));
+}
+
+#[tokio::test]
+async fn created_and_updated_come_from_the_given_time() {
+    let pool = memory_pool().await;
+    let later = repo::texts::create(&pool, "Später", "zwei", at("2025-02-01T10:00:00+00:00")).await.unwrap();
+    repo::texts::create(&pool, "Früher", "eins", at("2025-02-01T09:30:00.5+00:00")).await.unwrap();
+    repo::texts::update(&pool, later.id, "Später", "zwei drei", at("2025-02-01T10:00:00.25+00:00")).await.unwrap();
+
+    let by_created = TextFilter { sort: Some(TextSort::Created), ..Default::default() };
+    let texts = repo::texts::list_summaries(&pool, &by_created).await.unwrap();
+    let titles: Vec<&str> = texts.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["Früher", "Später"]);
+    assert_eq!(texts[0].created_at.as_deref(), Some("2025-02-01T09:30:00.500Z"));
+
+    // Edits are stored in the same format as inserts
+    let by_updated = TextFilter { sort: Some(TextSort::Updated), ..Default::default() };
+    let texts = repo::texts::list_summaries(&pool, &by_updated).await.unwrap();
+    assert_eq!(texts[1].updated_at.as_deref(), Some("2025-02-01T10:00:00.250Z"));
+    assert_eq!(texts[1].created_at.as_deref(), Some("2025-02-01T10:00:00.000Z"));
+}
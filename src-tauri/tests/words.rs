@@ -0,0 +1,68 @@
+mod common;
+
+use common::{at, count, memory_pool, word, CREATED};
+use word_echo_lib::repo;
+
+#[tokio::test]
+async fn mark_known_clears_the_schedule() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Text", "Haus", at(CREATED)).await.unwrap();
+    let haus = word(&pool, "Haus").await;
+    repo::words::update_meaning(&pool, haus.id, "house", chrono::Utc::now()).await.unwrap();
+
+    repo::words::mark_known(&pool, haus.id).await.unwrap();
+
+    let known = word(&pool, "Haus").await;
+    assert_eq!(known.status.as_deref(), Some("known"));
+    assert!(known.next_review_date.is_none());
+}
+
+#[tokio::test]
+async fn start_learning_schedules_without_a_meaning() {
+    let pool = memory_pool().await;
+    repo::texts::create(&pool, "Text", "Baum", at(CREATED)).await.unwrap();
+    let baum = word(&pool, "Baum").await;
+
+    repo::words::start_learning(&pool, baum.id, at("2025-03-01T09:00:00+00:00")).await.unwrap();
+
+    let seen = word(&pool, "Baum").await;
+    assert_eq!(seen.status.as_deref(), Some("seen"));
+    assert_eq!(seen.meaning, None);
+    assert_eq!(repo::words::due(&pool, at("2025-03-01T09:00:00+00:00"), 10).await.unwrap().len(), 1);
+    assert_eq!(repo::words::start_learning(&pool, 99, at(CREATED)).await.unwrap_err().code(), "not_found");
+}
+
+#[tokio::test]
+async fn unknown_word_ids_are_not_found() {
+    let pool = memory_pool().await;
+
+    assert_eq!(repo::words::mark_known(&pool, 7).await.unwrap_err().code(), "not_found");
+    assert_eq!(
+        repo::words::update_meaning(&pool, 7, "x", chrono::Utc::now()).await.unwrap_err().code(),
+        "not_found"
+    );
+    assert_eq!(repo::words::delete(&pool, 7).await.unwrap_err().code(), "not_found");
+}
+
+#[tokio::test]
+async fn delete_unlinks_the_word_from_its_texts() {
+    let pool = memory_pool().await;
+    let text = repo::texts::create(&pool, "Text", "rot grün", at(CREATED)).await.unwrap();
+
+    repo::words::delete(&pool, word(&pool, "rot").await.id).await.unwrap();
+
+    assert_eq!(count(&pool, "words").await, 1);
+    assert_eq!(repo::texts::word_counts(&pool, text.id).await.unwrap(), (1, 0, 0));
+}
+
+#[tokio::test]
+async fn only_one_country_code_is_kept() {
+    let pool = memory_pool().await;
+    assert_eq!(repo::preferences::country_code(&pool).await.unwrap(), None);
+
+    repo::preferences::save_country_code(&pool, "de").await.unwrap();
+    repo::preferences::save_country_code(&pool, "fr").await.unwrap();
+
+    assert_eq!(repo::preferences::country_code(&pool).await.unwrap().as_deref(), Some("fr"));
+    assert_eq!(count(&pool, "user_preferences").await, 1);
+}
@@ -1,4 +1,5 @@
-import React from "react";
+import React, { useState, useEffect } from "react";
+import { invoke } from '@tauri-apps/api/core';
 import { BrowserRouter as Router, Routes, Route } from 'react-router-dom';
 import { createTheme, ThemeProvider } from '@mui/material/styles';
 import CssBaseline from '@mui/material/CssBaseline';
@@ -9,6 +10,7 @@ import Edit from './pages/Edit';
 import New from './pages/New';
 import Vocab from './pages/Vocab';
 import Navbar from './components/Navbar';
+import Unlock from './components/Unlock';
 
 const theme= createTheme({	//mui theme
 	palette: {
@@ -43,6 +45,24 @@ Color Palette
 */
 
 function App() {
+	const [locked, setLocked]= useState(false);
+
+	// an encrypted library stays closed until the passphrase is entered
+	useEffect(()=> {
+		invoke('get_database_status')
+			.then((status)=> setLocked(status.encrypted && !status.unlocked))
+			.catch((error)=> console.error('Error fetching database status:', error));
+	}, []);
+
+	if(locked){
+		return (
+			<ThemeProvider theme={theme}>
+			<CssBaseline/>
+				<Unlock onUnlock={()=> setLocked(false)}/>
+			</ThemeProvider>
+		);
+	}
+
 	return (
 		<ThemeProvider theme={theme}>
 		<CssBaseline/>
@@ -1,16 +1,14 @@
 import React, { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { Box, Typography, TextField, IconButton, Tooltip, Link, useMediaQuery, useTheme } from "@mui/material";
+import { Box, Typography, TextField, IconButton, Tooltip, Link, Chip, useMediaQuery, useTheme } from "@mui/material";
 import PlaylistAddIcon from '@mui/icons-material/PlaylistAdd';
 import PlaylistAddCheckIcon from '@mui/icons-material/PlaylistAddCheck';
 import CloseIcon from '@mui/icons-material/Close';
 
 function TextBox({ title, textblock, words, selectedWord, setSelectedWord, updateWords }) {
   const [newMeaning, setNewMeaning]= useState("");
-  const [dictionaryLink, setDictionaryLink]= useState("");
-  const [dictionaryLink2, setDictionaryLink2]= useState("");
-  const [dictionaryLink3, setDictionaryLink3]= useState("");
-  const [dictionaryLink4, setDictionaryLink4]= useState("");
+  const [dictionaryLinks, setDictionaryLinks]= useState([]);
+  const [meaningSuggestions, setMeaningSuggestions]= useState([]);
   const theme= useTheme();
   const isMobile= useMediaQuery(theme.breakpoints.down('sm'));
 
@@ -57,103 +55,80 @@ const closeSidePanel= ()=>{
   setSelectedWord("");
 };
 
-const countryCodeToDictionaryLink = {
-  dk: "https://m.dict.cc/danish-english/",
-  nl: "https://m.dict.cc/dutch-english/",
-  fr: "https://m.dict.cc/french-english/",
-  de: "https://m.dict.cc/deutsch-englisch/",
-  it: "https://m.dict.cc/italian-english/",
-  no: "https://m.dict.cc/norwegian-english/",
-  pl: "https://m.dict.cc/polish-english/",
-  pt: "https://m.dict.cc/portuguese-english/",
-  ru: "https://m.dict.cc/russian-english/",
-  es: "https://m.dict.cc/spanish-english/",
-  se: "https://m.dict.cc/swedish-english/",
-  tr: "https://m.dict.cc/turkish-english/",
-};
+useEffect(()=> {
+  if(!selectedWord){
+    setDictionaryLinks([]);
+    return;
+  }
+  const fetchDictionaryLinks= async()=> {
+    try{
+      const links= await invoke("get_dictionary_links", { word: selectedWord });
+      setDictionaryLinks(links);
+    }
+    catch(error){
+      console.error("Error fetching dictionary links:", error);
+      setDictionaryLinks([]);
+    }
+  };
 
-const countryCodeToDictionaryLink2 = {
-  dk: "https://www.deepl.com/en/translator#da/en/",
-  nl: "https://www.deepl.com/en/translator#nl/en/",
-  fr: "https://www.deepl.com/en/translator#fr/en/",
-  de: "https://www.deepl.com/en/translator#de/en/",
-  it: "https://www.deepl.com/en/translator#it/en/",
-  no: "https://www.deepl.com/en/translator#nb/en/",
-  pl: "https://www.deepl.com/en/translator#pl/en/",
-  pt: "https://www.deepl.com/en/translator#pt/en/",
-  ru: "https://www.deepl.com/en/translator#ru/en/",
-  es: "https://www.deepl.com/en/translator#es/en/",
-  se: "https://www.deepl.com/en/translator#sv/en/",
-  tr: "https://www.deepl.com/en/translator#tr/en/",
-};
+  fetchDictionaryLinks();
+}, [selectedWord]);
 
-const countryCodeToDictionaryLink3 = {
-  dk: "https://dk.thefreedictionary.com/",  //* doesnt exist
-  nl: "https://nl.thefreedictionary.com/",
-  fr: "https://fr.thefreedictionary.com/",
-  de: "https://de.thefreedictionary.com/",
-  it: "https://it.thefreedictionary.com/",
-  no: "https://no.thefreedictionary.com/",
-  pl: "https://pl.thefreedictionary.com/",
-  pt: "https://pt.thefreedictionary.com/",
-  ru: "https://ru.thefreedictionary.com/",
-  es: "https://es.thefreedictionary.com/",
-  se: "https://se.thefreedictionary.com/",  //* doesnt exist
-  tr: "https://tr.thefreedictionary.com/",
-};
+const embeddedLink= dictionaryLinks.find((link)=> link.embedded);
+const otherLinks= dictionaryLinks.filter((link)=> link!== embeddedLink);
 
-const countryCodeToDictionaryLink4 = {
-  dk: "https://translate.google.com/?sl=da&tl=en&text=",
-  nl: "https://translate.google.com/?sl=nl&tl=en&text=",
-  fr: "https://translate.google.com/?sl=fr&tl=en&text=",
-  de: "https://translate.google.com/?sl=de&tl=en&text=",
-  it: "https://translate.google.com/?sl=it&tl=en&text=",
-  no: "https://translate.google.com/?sl=no&tl=en&text=",
-  pl: "https://translate.google.com/?sl=pl&tl=en&text=",
-  pt: "https://translate.google.com/?sl=pt&tl=en&text=",
-  ru: "https://translate.google.com/?sl=ru&tl=en&text=",
-  es: "https://translate.google.com/?sl=es&tl=en&text=",
-  se: "https://translate.google.com/?sl=sv&tl=en&text=",
-  tr: "https://translate.google.com/?sl=tr&tl=en&text=",
+const hostName= (url)=> {
+  try{
+    return new URL(url).hostname.replace(/^www\./, "");
+  }
+  catch{
+    return url;
+  }
 };
 
+const renderDictionaryLinks= ()=> (
+  <Box sx={{ display: 'flex', justifyContent: 'space-around' }} >
+    {otherLinks.map((link, index)=> (
+      <Link key={link.provider_id} href={link.url} target='_blank' sx={{ color: '#dddddd' }} >
+        <Typography variant="body1" sx={{ marginLeft: index=== 0 ? '2px' : '10px' }} >{link.name}</Typography>
+        <Typography variant="body2" sx={{ color: '#999', fontSize: '12px' }} >{hostName(link.url)}</Typography>
+      </Link>
+    ))}
+  </Box>
+);
+
+const selectedWordData= words.find((w)=> w.word=== selectedWord);
+
 useEffect(()=> {
-  const fetchCountryCode= async()=> {
+  const wordId= selectedWordData?.id;
+  if(!wordId){
+    setMeaningSuggestions([]);
+    return;
+  }
+  const fetchMeaningSuggestions= async()=> {
     try{
-      const savedCountryCode= await invoke("get_selected_country_code");
-      if(savedCountryCode){
-        const link= countryCodeToDictionaryLink[savedCountryCode] || "https://m.dict.cc/turkish-english/";
-        const link2= countryCodeToDictionaryLink2[savedCountryCode] || "https://www.deepl.com/en/translator#tr/en/";
-        const link3= countryCodeToDictionaryLink3[savedCountryCode] || "https://www.thefreedictionary.com/";
-        const link4= countryCodeToDictionaryLink4[savedCountryCode] || "https://translate.google.com/?sl=auto&tl=en&text=";
-        setDictionaryLink(link);
-        setDictionaryLink2(link2);
-        setDictionaryLink3(link3);
-        setDictionaryLink4(link4);
-      }
-      else{
-        setDictionaryLink("https://m.dict.cc/turkish-english/");
-        setDictionaryLink2("https://www.deepl.com/en/translator#tr/en/");
-        setDictionaryLink3("https://www.deepl.com/en/translator#tr/en/");
-        setDictionaryLink4("https://translate.google.com/?sl=auto&tl=en&text=");
-      }
+      const suggestions= await invoke("suggest_meanings", { wordId });
+      setMeaningSuggestions(suggestions);
     }
     catch(error){
-      console.error("Error fetching country code:", error);
-      setDictionaryLink("https://m.dict.cc/turkish-english/");
-      setDictionaryLink2("https://www.deepl.com/en/translator#tr/en/");
-      setDictionaryLink3("https://www.thefreedictionary.com/");
-      setDictionaryLink4("https://translate.google.com/?sl=auto&tl=en&text=");
-    }
-    finally{
-      setIsLoading(false);
+      console.error("Error fetching meaning suggestions:", error);
+      setMeaningSuggestions([]);
     }
   };
 
-  fetchCountryCode();
-}, []);
+  fetchMeaningSuggestions();
+}, [selectedWordData?.id, selectedWordData?.meaning]);
 
-const selectedWordData= words.find((w)=> w.word=== selectedWord);
+const renderMeaningSuggestions= ()=> meaningSuggestions.length > 0 && (
+  <Box sx={{ display: 'flex', flexWrap: 'wrap', gap: 0.5, marginTop: 1 }} >
+    {meaningSuggestions.map((suggestion)=> (
+      <Tooltip key={suggestion.meaning} title={suggestion.dictionary_name ? `${suggestion.matched_word} (${suggestion.dictionary_name})` : suggestion.matched_word} >
+        <Chip size="small" label={suggestion.meaning} onClick={()=> setNewMeaning(suggestion.meaning)}
+          sx={{ color: '#dddddd', backgroundColor: suggestion.source=== "vocabulary" ? '#3a4a5a' : '#333333', maxWidth: '100%' }} />
+      </Tooltip>
+    ))}
+  </Box>
+);
 
 console.log("Selected word:", selectedWord);
 console.log("Words array:", words);
@@ -249,29 +224,17 @@ const handleMarkAsKnown= async()=> {
             <PlaylistAddCheckIcon />
           </IconButton>
           </Tooltip>
+          {renderMeaningSuggestions()}
         </Box>
         <Box sx={{ backgroundColor: '#262626', borderRadius: '5px', padding: 1, marginTop: 1.5}}>
           <Typography variant="caption" sx={{ color: '#aaaaaa' }} > Saved Meaning </Typography>
           <Typography sx={{ height: '20px'  }}> {selectedWordData?.meaning || ""} </Typography>
         </Box>
         <Box sx={{ marginTop: 1.5 }}>
-          <iframe width="350px" style={{ height: '500px' }} src={`${dictionaryLink}${selectedWord || ""}.html`} frameborder="0" ></iframe>
-          {/* <iframe width="350px" style={{ height: '325px', marginTop: '15px' }} src={`${dictionaryLink2}`} frameborder="0" ></iframe> */}
-          
-          <Box sx={{ display: 'flex', justifyContent: 'space-around' }} >
-          <Link href={`${dictionaryLink2}${selectedWord}`} target='_blank' sx={{ color: '#dddddd' }} >
-              <Typography variant="body1" sx={{ marginLeft: '2px' }} >DeepL</Typography>
-              <Typography variant="body2" sx={{ color: '#999', fontSize: '12px' }} >deepl.com</Typography>
-            </Link>
-          <Link href={`${dictionaryLink4}${selectedWord}&op=translate`} target='_blank' sx={{ color: '#dddddd' }} >
-              <Typography variant="body1" sx={{ marginLeft: '10px' }} >Translate</Typography>
-              <Typography variant="body2" sx={{ color: '#999', fontSize: '12px' }} >translate.google.com</Typography>
-            </Link>
-            <Link href={`${dictionaryLink3}${selectedWord}`} target='_blank' sx={{ color: '#dddddd' }} >
-              <Typography variant="body1" sx={{ marginLeft: '10px' }} >Dictionary</Typography>
-              <Typography variant="body2" sx={{ color: '#999', fontSize: '12px' }} >thefreedictionary.com</Typography>
-            </Link>
-          </Box>
+          {embeddedLink && (
+            <iframe width="350px" style={{ height: '500px' }} src={embeddedLink.url} frameborder="0" ></iframe>
+          )}
+          {renderDictionaryLinks()}
         </Box>
       </Box>
     )):
@@ -303,29 +266,17 @@ const handleMarkAsKnown= async()=> {
             <PlaylistAddCheckIcon />
           </IconButton>
           </Tooltip>
+          {renderMeaningSuggestions()}
         </Box>
         <Box sx={{ backgroundColor: '#262626', borderRadius: '5px', padding: 1, marginTop: 1.5}}>
           <Typography variant="caption" sx={{ color: '#aaaaaa' }} > Saved Meaning </Typography>
           <Typography sx={{ height: '20px'  }}> {selectedWordData?.meaning || ""} </Typography>
         </Box>
         <Box sx={{ marginTop: 1.5 }}>
-          <iframe width="350px" style={{ height: '635px' }} src={`${dictionaryLink}${selectedWord || ""}.html`} frameborder="0" ></iframe>
-          {/* <iframe width="350px" style={{ height: '325px', marginTop: '15px' }} src={`${dictionaryLink2}`} frameborder="0" ></iframe> */}
-          
-          <Box sx={{ display: 'flex', justifyContent: 'space-around' }} >
-          <Link href={`${dictionaryLink2}${selectedWord}`} target='_blank' sx={{ color: '#dddddd' }} >
-              <Typography variant="body1" sx={{ marginLeft: '2px' }} >DeepL</Typography>
-              <Typography variant="body2" sx={{ color: '#999', fontSize: '12px' }} >deepl.com</Typography>
-            </Link>
-          <Link href={`${dictionaryLink4}${selectedWord}&op=translate`} target='_blank' sx={{ color: '#dddddd' }} >
-              <Typography variant="body1" sx={{ marginLeft: '10px' }} >Translate</Typography>
-              <Typography variant="body2" sx={{ color: '#999', fontSize: '12px' }} >translate.google.com</Typography>
-            </Link>
-            <Link href={`${dictionaryLink3}${selectedWord}`} target='_blank' sx={{ color: '#dddddd' }} >
-              <Typography variant="body1" sx={{ marginLeft: '10px' }} >Dictionary</Typography>
-              <Typography variant="body2" sx={{ color: '#999', fontSize: '12px' }} >thefreedictionary.com</Typography>
-            </Link>
-          </Box>
+          {embeddedLink && (
+            <iframe width="350px" style={{ height: '635px' }} src={embeddedLink.url} frameborder="0" ></iframe>
+          )}
+          {renderDictionaryLinks()}
           
           {/* <iframe width="350px" style={{ height: '330px', marginTop: '15px' }} src="https://en.openrussian.org/ru/%D0%9C%D0%B5%D0%BD%D1%8F" frameborder="0" ></iframe> */}
           
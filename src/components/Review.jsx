@@ -18,7 +18,7 @@ function Review(){
 		catch(error){
             console.error('Error fetching random word:', error);
 
-            if(error=== "No words are due for review"){
+            if(error?.code=== "not_found"){
                 console.log("No more words available for review.");
                 setHasRemainingWords(false);
             }
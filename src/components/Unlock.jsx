@@ -0,0 +1,43 @@
+import React, { useState } from 'react';
+import { Box, TextField, Button, Typography } from '@mui/material';
+import { invoke } from '@tauri-apps/api/core';
+
+// asks for the passphrase of an encrypted library
+function Unlock({ onUnlock }) {
+  const [passphrase, setPassphrase]= useState('');
+  const [error, setError]= useState('');
+
+  const handleUnlock= async()=> {
+    try{
+      await invoke('unlock_database', { passphrase });
+      setPassphrase('');
+      onUnlock();
+    }
+    catch(error){
+      console.error('Error unlocking database:', error);
+      setError(error?.message ?? 'Failed to unlock the library.');
+    }
+  };
+
+  return (
+    <Box sx={{ padding: 1, display: 'flex', justifyContent: 'center' }}>
+      <Box sx={{ display: 'flex', flexDirection: 'column', gap: 1, backgroundColor: '#202020', borderRadius: '5px', padding: 2, marginTop: 1.5, width: '400px' }}>
+        <Typography>This library is encrypted. Enter the passphrase to open it.</Typography>
+        <TextField
+          type="password"
+          variant="outlined"
+          placeholder="Passphrase"
+          value={passphrase}
+          error={!!error}
+          helperText={error}
+          autoFocus
+          onChange={(e)=>setPassphrase(e.target.value)}
+          onKeyDown={(e)=> { if(e.key=== 'Enter') handleUnlock(); }}
+        />
+        <Button variant="contained" onClick={handleUnlock} disabled={!passphrase}>Unlock</Button>
+      </Box>
+    </Box>
+  );
+}
+
+export default Unlock;
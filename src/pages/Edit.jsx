@@ -1,5 +1,5 @@
 import React, { useState, useEffect } from 'react';
-import { TextField, Box, Button, Link } from '@mui/material';
+import { TextField, Box, Button, Link, MenuItem } from '@mui/material';
 import { invoke } from '@tauri-apps/api/core';
 import { useParams } from 'react-router-dom';
 
@@ -7,6 +7,22 @@ function Edit() {
   const { id }= useParams();
   const [title, setTitle]= useState('');
   const [content, setContent]= useState('');
+  const [metadata, setMetadata]= useState({ source: '', author: '', level: '', notes: '' });
+
+  const levels= ['A1', 'A2', 'B1', 'B2', 'C1', 'C2'];
+  const fieldStyle= { "& .MuiOutlinedInput-root":{
+    "& fieldset": {
+      borderColor: "#202020",
+    },
+    "&:hover fieldset": {
+      borderColor: "#202020",
+    },
+    "&.Mui-focused fieldset": {
+      borderColor: "#303030",
+    },
+  } };
+
+  const updateMetadata= (field)=> (e)=> setMetadata({ ...metadata, [field]: e.target.value });
 
   // fetches the text and loads the content from database
   useEffect(()=> {
@@ -17,6 +33,12 @@ function Edit() {
           if(result){
             setTitle(result.title);
             setContent(result.content);
+            setMetadata({
+              source: result.source || '',
+              author: result.author || '',
+              level: result.level || '',
+              notes: result.notes || '',
+            });
           }
           else{
             console.error('Text not found');
@@ -35,10 +57,10 @@ function Edit() {
   const handleSave= async()=> {
     try{
       if(id){
-        await invoke('update_text', { id: parseInt(id, 10), title, content });
+        await invoke('update_text', { id: parseInt(id, 10), title, content, metadata });
       }
       else{
-        const newText= await invoke('create_text', { title, content });
+        const newText= await invoke('create_text', { title, content, metadata });
         console.log('New text added:', newText);
       }
       setTitle('');
@@ -75,6 +97,15 @@ function Edit() {
             borderColor: "#303030",
           },
         } }} variant="outlined"  placeholder="Title" value={title} onChange={(e)=>setTitle(e.target.value)}/>
+        <Box sx={{ display: 'flex', gap: 1 }}>
+          <TextField sx={{ flexGrow: 1, ...fieldStyle }} size="small" placeholder="Author" value={metadata.author} onChange={updateMetadata('author')}/>
+          <TextField sx={{ flexGrow: 2, ...fieldStyle }} size="small" placeholder="Source" value={metadata.source} onChange={updateMetadata('source')}/>
+          <TextField sx={{ width: '90px', ...fieldStyle }} size="small" select label="Level" value={metadata.level} onChange={updateMetadata('level')}>
+            <MenuItem value=''>-</MenuItem>
+            {levels.map((level)=> <MenuItem key={level} value={level}>{level}</MenuItem>)}
+          </TextField>
+        </Box>
+        <TextField sx={{ width: '100%', ...fieldStyle }} size="small" multiline placeholder="Notes" value={metadata.notes} onChange={updateMetadata('notes')}/>
         <TextField sx={{ width: '100%', "& .MuiOutlinedInput-root":{
           "& fieldset": {
             borderColor: "#202020",
@@ -6,34 +6,14 @@ import AddCircleIcon from '@mui/icons-material/AddCircle';
 
 function Home() {
 	const [texts, setTexts] = useState([]);
-	const [wordCounts, setWordCounts] = useState({});
 
-	// fetches the texts from database
+	// fetches the texts with their word counts from database
 	useEffect(()=> {
 		const fetchData= async()=> {
 			try{
-				const result= await invoke('get_texts');
+				const result= await invoke('list_texts', { sort: 'created' });
 				console.log("Fetched texts:", result);
 				setTexts(result);
-	
-				const countsMap= {};
-				for(const text of result) {
-					console.log(`Fetching word counts for text ID ${text.id}`);
-					const countsArray= await invoke('get_text_word_counts', { textId: text.id });
-					console.log(`Fetched word counts for text ID ${text.id}:`, countsArray);
-	
-					if(countsArray && Array.isArray(countsArray)){
-						const [newCount, seenCount, knownCount]= countsArray;
-						countsMap[text.id]= { newCount, seenCount, knownCount };
-					}
-					else{
-						console.warn(`Invalid word counts for text ID ${text.id}`);
-						countsMap[text.id]= { newCount: 0, seenCount: 0, knownCount: 0 };
-					}
-				}
-	
-				console.log("Word counts map:", countsMap);
-				setWordCounts(countsMap);
 			}
 			catch(error){
 				console.error('Error fetching data:', error);
@@ -62,17 +42,15 @@ function Home() {
 			<Divider sx={{ borderColor: '#131313' }} variant='middle' />
 			<Box sx={{ marginTop: {xs:1, sm:3} }}>
       			{texts.map((text) => {
-					  const counts= wordCounts[text.id] || { newCount: 0, seenCount: 0, knownCount: 0 };
-					  console.log(`Rendering TextList for text ID ${text.id}: ${counts.newCount}, ${counts.seenCount}, ${counts.knownCount} }`);
 					return(
 						<TextList
 						key={text.id}
 						id={text.id}
 						link={`/read/${text.id}`}
 						title={text.title}
-						newCount= {counts.newCount}
-						seenCount= {counts.seenCount}
-						knownCount= {counts.knownCount}
+						newCount= {text.new_count}
+						seenCount= {text.seen_count}
+						knownCount= {text.known_count}
 						/>
 					);
 				})}
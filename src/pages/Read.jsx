@@ -2,7 +2,7 @@ import React, { useState, useEffect } from 'react';
 import { useParams } from 'react-router-dom';
 import { invoke } from '@tauri-apps/api/core';
 import ReadT from '../components/ReadComponent';
-import { Box } from '@mui/material';
+import { Box, Button } from '@mui/material';
 import CircularProgress from '@mui/material/CircularProgress';
 
 function Read() {
@@ -10,6 +10,7 @@ function Read() {
 	const [text, setText]= useState(null);
   const [words, setWords]= useState([]);
   const [selectedWord, setSelectedWord]= useState(null);
+  const [finishEvent, setFinishEvent]= useState(null);
 
   // get words from database
   useEffect(()=> {
@@ -38,6 +39,30 @@ function Read() {
     }
   };
 
+  // marks every word that is still new as known
+  const handleFinish= async()=> {
+    try{
+      const event= await invoke('finish_page', { textId: parseInt(id, 10) });
+      setFinishEvent(event);
+      await updateWords();
+    }
+    catch(error){
+      console.error('Error finishing text:', error);
+    }
+  };
+
+  // puts the words of the last finish back to new
+  const handleUndo= async()=> {
+    try{
+      await invoke('undo_reading_event', { eventId: finishEvent.id });
+      setFinishEvent(null);
+      await updateWords();
+    }
+    catch(error){
+      console.error('Error undoing finish:', error);
+    }
+  };
+
   // loading screen
   if(!text){
     return (
@@ -57,6 +82,13 @@ function Read() {
 				setSelectedWord={setSelectedWord}
         updateWords={updateWords} 
 			/>
+      <Box sx={{ display: 'flex', justifyContent: 'center', gap: 1, marginBottom: 2 }}>
+        {finishEvent ?
+          <Button size='small' variant='contained' sx={{ backgroundColor: '#262626', borderRadius: '7px', fontSize: '12px' }} disableRipple onClick={handleUndo} >Undo ({finishEvent.word_count} known)</Button>
+          :
+          <Button size='small' variant='contained' sx={{ backgroundColor: '#262626', borderRadius: '7px', fontSize: '12px' }} disableRipple onClick={handleFinish} >Finish: mark new words as known</Button>
+        }
+      </Box>
 		</div>
 	);
 }